@@ -2,7 +2,7 @@ use malrs::env::Env;
 use malrs::printer::pr_str;
 use malrs::reader::read_str;
 use malrs::readline::Readline;
-use malrs::types::MalValueType::{List, Map, Number, RustFunc, Symbol, Vector};
+use malrs::types::MalValueType::{Int, List, Map, RustFunc, Symbol, Vector};
 use malrs::types::{MalError, MalList, MalMap, MalResult, MalValue};
 use std::iter::once;
 
@@ -34,7 +34,7 @@ fn create_env() -> Env {
     env.set(
         "+",
         MalValue::new_rust_func(
-            |args, _env| eval_arithmetic_operation(args, |a, b| a + b),
+            |args, _env| eval_arithmetic_operation(args, i64::checked_add),
             &env,
         ),
     );
@@ -42,7 +42,7 @@ fn create_env() -> Env {
     env.set(
         "-",
         MalValue::new_rust_func(
-            |args, _env| eval_arithmetic_operation(args, |a, b| a - b),
+            |args, _env| eval_arithmetic_operation(args, i64::checked_sub),
             &env,
         ),
     );
@@ -50,7 +50,7 @@ fn create_env() -> Env {
     env.set(
         "*",
         MalValue::new_rust_func(
-            |args, _env| eval_arithmetic_operation(args, |a, b| a * b),
+            |args, _env| eval_arithmetic_operation(args, i64::checked_mul),
             &env,
         ),
     );
@@ -58,7 +58,7 @@ fn create_env() -> Env {
     env.set(
         "/",
         MalValue::new_rust_func(
-            |args, _env| eval_arithmetic_operation(args, |a, b| a / b),
+            |args, _env| eval_arithmetic_operation(args, i64::checked_div),
             &env,
         ),
     );
@@ -66,7 +66,7 @@ fn create_env() -> Env {
     env
 }
 
-fn eval_arithmetic_operation(args: &[MalValue], op: fn(f64, f64) -> f64) -> MalResult {
+fn eval_arithmetic_operation(args: &[MalValue], op: fn(i64, i64) -> Option<i64>) -> MalResult {
     if args.len() != 2 {
         return Err(MalError::RustFunction(format!(
             "Expected 2 arguments, got {}",
@@ -74,23 +74,27 @@ fn eval_arithmetic_operation(args: &[MalValue], op: fn(f64, f64) -> f64) -> MalR
         )));
     }
 
-    let arg_1 = if let Number(n) = *args[0].mal_type {
+    let arg_1 = if let Int(n) = *args[0].mal_type {
         Ok(n)
     } else {
         Err(MalError::RustFunction(
-            "First argument must be a number".to_string(),
+            "First argument must be an integer".to_string(),
         ))
     }?;
 
-    let arg_2 = if let Number(n) = *args[1].mal_type {
+    let arg_2 = if let Int(n) = *args[1].mal_type {
         Ok(n)
     } else {
         Err(MalError::RustFunction(
-            "Second argument must be a number".to_string(),
+            "Second argument must be an integer".to_string(),
         ))
     }?;
 
-    Ok(MalValue::new(Number(op(arg_1, arg_2))))
+    let result = op(arg_1, arg_2).ok_or_else(|| {
+        MalError::RustFunction("Integer overflow or division by zero".to_string())
+    })?;
+
+    Ok(MalValue::new(Int(result)))
 }
 
 fn rep(s: &str, env: &Env) -> Result<String, MalError> {
@@ -121,7 +125,7 @@ fn eval(ast: &MalValue, env: &Env) -> MalResult {
                             .mal_type
                         {
                             (rust_function.func)(
-                                &evaluated_list[1..],
+                                &evaluated_list.iter().skip(1).cloned().collect::<Vec<_>>(),
                                 &mut rust_function.env.clone(),
                             )
                         } else {
@@ -166,6 +170,9 @@ fn eval_map(mal_map: &MalMap, env: &Env) -> MalResult {
     )?)))
 }
 
-fn eval_ast_seq(seq: &[MalValue], env: &Env) -> Result<Vec<MalValue>, MalError> {
-    seq.iter().map(|mal_val| eval(mal_val, env)).collect()
+fn eval_ast_seq<'a>(
+    seq: impl IntoIterator<Item = &'a MalValue>,
+    env: &Env,
+) -> Result<Vec<MalValue>, MalError> {
+    seq.into_iter().map(|mal_val| eval(mal_val, env)).collect()
 }
@@ -56,6 +56,7 @@ fn eval(ast: &MalValue, env: &mut Env) -> MalResult {
     match *ast.mal_type {
         List(ref mal_list) if mal_list.vec.is_empty() => Ok(ast.clone()),
         List(MalList { vec: ref list, .. }) => {
+            let list: Vec<_> = list.iter().cloned().collect();
             let first_arg = &list[0];
 
             match *first_arg.mal_type {
@@ -85,8 +86,11 @@ fn eval_ast(ast: &MalValue, env: &mut Env) -> MalResult {
     }
 }
 
-fn eval_ast_seq(seq: &[MalValue], env: &mut Env) -> Result<Vec<MalValue>, MalError> {
-    seq.iter().map(|mal_val| eval(mal_val, env)).collect()
+fn eval_ast_seq<'a>(
+    seq: impl IntoIterator<Item = &'a MalValue>,
+    env: &mut Env,
+) -> Result<Vec<MalValue>, MalError> {
+    seq.into_iter().map(|mal_val| eval(mal_val, env)).collect()
 }
 
 fn eval_map(mal_map: &MalMap, env: &mut Env) -> MalResult {
@@ -111,14 +115,15 @@ fn apply_ast(ast: &MalValue, env: &mut Env) -> MalResult {
             .expect("Evaluation of non-empty list resulted in empty list.")
             .mal_type
         {
-            RustFunc(ref rust_function) => {
-                (rust_function.func)(&evaluated_list[1..], &mut rust_function.env.clone())
-            }
+            RustFunc(ref rust_function) => (rust_function.func)(
+                &evaluated_list.iter().skip(1).cloned().collect::<Vec<_>>(),
+                &mut rust_function.env.clone(),
+            ),
             MalFunc(ref mal_func) => {
                 let mut func_env = Env::with_binds(
                     Some(&mal_func.outer_env),
                     &mal_func.parameters,
-                    &evaluated_list[1..],
+                    &evaluated_list.iter().skip(1).cloned().collect::<Vec<_>>(),
                 )?;
                 eval(&mal_func.body, &mut func_env)
             }
@@ -170,7 +175,7 @@ fn apply_special_form_let(args: &[MalValue], env: &Env) -> MalResult {
         })
         | Vector(MalVector {
             vec: ref bindings, ..
-        }) => Ok(bindings.as_slice()),
+        }) => Ok(bindings),
         _ => Err(MalError::SpecialForm(
             "let* first argument must be a list or a vector".to_string(),
         )),
@@ -217,7 +222,7 @@ fn apply_special_form_fn(args: &[MalValue], env: &Env) -> MalResult {
         })
         | Vector(MalVector {
             vec: ref bindings, ..
-        }) => Ok(bindings.as_slice()),
+        }) => Ok(bindings),
         _ => Err(MalError::SpecialForm(
             "fn* first argument must be a list or a vector".to_string(),
         )),
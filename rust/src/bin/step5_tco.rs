@@ -72,6 +72,7 @@ fn eval(ast: &MalValue, env: &mut Env) -> MalResult {
         match *cur_ast.mal_type {
             List(ref mal_list) if mal_list.vec.is_empty() => return Ok(cur_ast.clone()),
             List(MalList { vec: ref list, .. }) => {
+                let list: Vec<_> = list.iter().cloned().collect();
                 let first_arg = &list[0];
 
                 let apply_result = match *first_arg.mal_type {
@@ -116,8 +117,11 @@ fn eval_ast(ast: &MalValue, env: &mut Env) -> MalResult {
     }
 }
 
-fn eval_ast_seq(seq: &[MalValue], env: &mut Env) -> Result<Vec<MalValue>, MalError> {
-    seq.iter().map(|mal_val| eval(mal_val, env)).collect()
+fn eval_ast_seq<'a>(
+    seq: impl IntoIterator<Item = &'a MalValue>,
+    env: &mut Env,
+) -> Result<Vec<MalValue>, MalError> {
+    seq.into_iter().map(|mal_val| eval(mal_val, env)).collect()
 }
 
 fn eval_map(mal_map: &MalMap, env: &mut Env) -> MalResult {
@@ -143,14 +147,14 @@ fn apply_ast(ast: &MalValue, env: &mut Env) -> ApplyResult {
             .mal_type
         {
             RustFunc(ref rust_function) => Ok(Return((rust_function.func)(
-                &evaluated_list[1..],
+                &evaluated_list.iter().skip(1).cloned().collect::<Vec<_>>(),
                 &mut rust_function.env.clone(),
             )?)),
             MalFunc(ref mal_func) => {
                 let func_env = Env::with_binds(
                     Some(&mal_func.outer_env),
                     &mal_func.parameters,
-                    &evaluated_list[1..],
+                    &evaluated_list.iter().skip(1).cloned().collect::<Vec<_>>(),
                 )?;
                 Ok(TailCall(mal_func.body.clone(), func_env))
             }
@@ -202,7 +206,7 @@ fn apply_special_form_let(args: &[MalValue], env: &Env) -> ApplyResult {
         })
         | Vector(MalVector {
             vec: ref bindings, ..
-        }) => Ok(bindings.as_slice()),
+        }) => Ok(bindings),
         _ => Err(MalError::SpecialForm(
             "let* first argument must be a list or a vector".to_string(),
         )),
@@ -247,7 +251,7 @@ fn apply_special_form_fn(args: &[MalValue], env: &Env) -> ApplyResult {
         })
         | Vector(MalVector {
             vec: ref bindings, ..
-        }) => Ok(bindings.as_slice()),
+        }) => Ok(bindings),
         _ => Err(MalError::SpecialForm(
             "fn* first argument must be a list or a vector".to_string(),
         )),
@@ -23,9 +23,7 @@ fn main() {
 }
 
 fn create_root_env(args: &[String]) -> Env {
-    let mut env = Env::new();
-
-    core::set_eval_func(eval);
+    let mut env = Env::with_eval_func(eval);
 
     env.set(
         "*ARGV*",
@@ -33,7 +31,7 @@ fn create_root_env(args: &[String]) -> Env {
             args.iter()
                 .skip(2)
                 .map(|arg| MalValue::new(Str(arg.clone())))
-                .collect(),
+                .collect::<Vec<_>>(),
         ),
     );
 
@@ -42,11 +40,6 @@ fn create_root_env(args: &[String]) -> Env {
     }
 
     rep("(def! not (fn* (a) (if a false true)))", &mut env).unwrap();
-    rep(
-        r#"(def! load-file (fn* (f) (eval (read-string (str "(do " (slurp f) ")")))))"#,
-        &mut env,
-    )
-    .unwrap();
 
     env
 }
@@ -113,6 +106,7 @@ fn eval(ast: &MalValue, env: &mut Env) -> MalResult {
         match *cur_ast.mal_type {
             List(ref mal_list) if mal_list.vec.is_empty() => return Ok(cur_ast.clone()),
             List(MalList { vec: ref list, .. }) => {
+                let list: Vec<_> = list.iter().cloned().collect();
                 let first_arg = &list[0];
 
                 let apply_result = match *first_arg.mal_type {
@@ -163,8 +157,11 @@ fn eval_ast(ast: &MalValue, env: &mut Env) -> MalResult {
     }
 }
 
-fn eval_ast_seq(seq: &[MalValue], env: &mut Env) -> Result<Vec<MalValue>, MalError> {
-    seq.iter().map(|mal_val| eval(mal_val, env)).collect()
+fn eval_ast_seq<'a>(
+    seq: impl IntoIterator<Item = &'a MalValue>,
+    env: &mut Env,
+) -> Result<Vec<MalValue>, MalError> {
+    seq.into_iter().map(|mal_val| eval(mal_val, env)).collect()
 }
 
 fn eval_map(mal_map: &MalMap, env: &mut Env) -> MalResult {
@@ -190,14 +187,14 @@ fn apply_ast(ast: &MalValue, env: &mut Env) -> ApplyResult {
             .mal_type
         {
             RustFunc(ref rust_function) => Ok(Return((rust_function.func)(
-                &evaluated_list[1..],
+                &evaluated_list.iter().skip(1).cloned().collect::<Vec<_>>(),
                 &mut rust_function.env.clone(),
             )?)),
             MalFunc(ref mal_func) => {
                 let func_env = Env::with_binds(
                     Some(&mal_func.outer_env),
                     &mal_func.parameters,
-                    &evaluated_list[1..],
+                    &evaluated_list.iter().skip(1).cloned().collect::<Vec<_>>(),
                 )?;
                 Ok(TailCall(mal_func.body.clone(), func_env))
             }
@@ -249,7 +246,7 @@ fn apply_special_form_let(args: &[MalValue], env: &Env) -> ApplyResult {
         })
         | Vector(MalVector {
             vec: ref bindings, ..
-        }) => Ok(bindings.as_slice()),
+        }) => Ok(bindings),
         _ => Err(MalError::SpecialForm(
             "let* first argument must be a list or a vector".to_string(),
         )),
@@ -294,7 +291,7 @@ fn apply_special_form_fn(args: &[MalValue], env: &Env) -> ApplyResult {
         })
         | Vector(MalVector {
             vec: ref bindings, ..
-        }) => Ok(bindings.as_slice()),
+        }) => Ok(bindings),
         _ => Err(MalError::SpecialForm(
             "fn* first argument must be a list or a vector".to_string(),
         )),
@@ -410,20 +407,20 @@ fn quasiquote(ast: &MalValue) -> MalResult {
                             Ok(MalValue::new_list(vec![
                                 MalValue::new(Symbol("concat".to_string())),
                                 inner_vec[1].clone(),
-                                quasiquote(&MalValue::new_list(vec[1..].to_vec()))?,
+                                quasiquote(&MalValue::new_list(vec.skip(1)))?,
                             ]))
                         }
                     }
                     _ => Ok(MalValue::new_list(vec![
                         MalValue::new(Symbol("cons".to_string())),
                         quasiquote(elem0)?,
-                        quasiquote(&MalValue::new_list(vec[1..].to_vec()))?,
+                        quasiquote(&MalValue::new_list(vec.skip(1)))?,
                     ])),
                 },
                 _ => Ok(MalValue::new_list(vec![
                     MalValue::new(Symbol("cons".to_string())),
                     quasiquote(elem0)?,
-                    quasiquote(&MalValue::new_list(vec[1..].to_vec()))?,
+                    quasiquote(&MalValue::new_list(vec.skip(1)))?,
                 ])),
             }
         }
@@ -1,15 +1,12 @@
 #![allow(non_snake_case)]
 
-use crate::ApplyOkResult::{Return, TailCall};
-use malrs::core;
 use malrs::env::Env;
-use malrs::printer::pr_str;
+use malrs::eval::eval;
+use malrs::printer::try_pr_str;
 use malrs::reader::read_str;
 use malrs::readline::Readline;
-use malrs::types::MalValueType;
-use malrs::types::MalValueType::{List, MalFunc, Map, Nil, RustFunc, Str, Symbol, Vector};
-use malrs::types::{MalError, MalList, MalMap, MalResult, MalValue, MalVector};
-use std::iter::once;
+use malrs::types::{MalError, MalResult, MalValue};
+use malrs::Interpreter;
 use std::{env, process};
 
 fn main() {
@@ -25,46 +22,9 @@ fn main() {
 }
 
 fn create_root_env(args: &[String]) -> Env {
-    let mut env = Env::new();
-
-    core::set_eval_func(eval);
-
-    env.set("*host-language*", MalValue::new(Str("rust".to_string())));
-
-    env.set(
-        "*ARGV*",
-        MalValue::new_list(
-            args.iter()
-                .skip(2)
-                .map(|arg| MalValue::new(Str(arg.clone())))
-                .collect(),
-        ),
-    );
-
-    for (name, val) in core::ns(&env) {
-        env.set(name, val);
-    }
-
-    rep(r#"(def! *gensym-counter* (atom 0))"#, &mut env).unwrap();
-    rep(
-        r#"(def! gensym (fn* [] (symbol (str "G__" (swap! *gensym-counter* (fn* [x] (+ 1 x)))))))"#,
-        &mut env,
-    )
-    .unwrap();
-    rep("(def! not (fn* (a) (if a false true)))", &mut env).unwrap();
-    rep(
-        r#"(def! load-file (fn* (f) (eval (read-string (str "(do " (slurp f) ")")))))"#,
-        &mut env,
-    )
-    .unwrap();
-    rep(
-        r#"(defmacro! cond (fn* (& xs) (if (> (count xs) 0) (list 'if (first xs) (if (> (count xs) 1) (nth xs 1) (throw "odd number of forms to cond")) (cons 'cond (rest (rest xs)))))))"#,
-        &mut env,
-    )
-    .unwrap();
-    rep(r#"(defmacro! or (fn* (& xs) (if (empty? xs) nil (if (= 1 (count xs)) (first xs) (let* (condvar (gensym)) `(let* (~condvar ~(first xs)) (if ~condvar ~condvar (or ~@(rest xs)))))))))"#, &mut env).unwrap();
-
-    env
+    Interpreter::with_args(args.get(2..).unwrap_or(&[]))
+        .env()
+        .clone()
 }
 
 fn run_file(file_path: &str, env: &mut Env) -> ! {
@@ -105,557 +65,325 @@ fn run_repl(env: &mut Env) {
 fn rep(s: &str, env: &mut Env) -> Result<String, MalError> {
     let read_val = read(s)?;
     let eval_val = eval(&read_val, env)?;
-    Ok(print(&eval_val))
+    print(&eval_val)
 }
 
 fn read(s: &str) -> MalResult {
     read_str(s)
 }
 
-fn print(mal_val: &MalValue) -> String {
-    pr_str(mal_val, true)
+fn print(mal_val: &MalValue) -> Result<String, MalError> {
+    try_pr_str(mal_val, true)
 }
 
-enum ApplyOkResult {
-    Return(MalValue),
-    TailCall(MalValue, Env),
-}
-
-type ApplyResult = Result<ApplyOkResult, MalError>;
-
-fn eval(ast: &MalValue, env: &mut Env) -> MalResult {
-    let mut cur_ast = ast.clone();
-    let mut cur_env = env.clone();
-
-    loop {
-        cur_ast = macroexpand(&cur_ast, env)?;
-
-        match *cur_ast.mal_type {
-            List(ref mal_list) if mal_list.vec.is_empty() => return Ok(cur_ast.clone()),
-            List(MalList { vec: ref list, .. }) => {
-                let first_arg = &list[0];
-
-                let apply_result = match *first_arg.mal_type {
-                    Symbol(ref name) if name == "def!" => {
-                        apply_special_form_def(&list[1..], &mut cur_env)
-                    }
-                    Symbol(ref name) if name == "let*" => {
-                        apply_special_form_let(&list[1..], &cur_env)
-                    }
-                    Symbol(ref name) if name == "fn*" => {
-                        apply_special_form_fn(&list[1..], &cur_env)
-                    }
-                    Symbol(ref name) if name == "do" => {
-                        apply_special_form_do(&list[1..], &mut cur_env)
-                    }
-                    Symbol(ref name) if name == "if" => {
-                        apply_special_form_if(&list[1..], &mut cur_env)
-                    }
-                    Symbol(ref name) if name == "quote" => {
-                        apply_special_form_quote(&list[1..], &mut cur_env)
-                    }
-                    Symbol(ref name) if name == "quasiquote" => {
-                        apply_special_form_quasiquote(&list[1..], &mut cur_env)
-                    }
-                    Symbol(ref name) if name == "defmacro!" => {
-                        apply_special_form_defmacro(&list[1..], &mut cur_env)
-                    }
-                    Symbol(ref name) if name == "macroexpand" => {
-                        apply_special_form_macroexpand(&list[1..], &mut cur_env)
-                    }
-                    Symbol(ref name) if name == "try*" => {
-                        apply_special_form_try(&list[1..], &mut cur_env)
-                    }
-                    _ => apply_ast(&cur_ast, &mut cur_env),
-                }?;
-
-                match apply_result {
-                    Return(mal_value) => return Ok(mal_value),
-                    TailCall(mal_val, new_env) => {
-                        cur_ast = mal_val;
-                        cur_env = new_env;
-                    }
-                }
-            }
-            _ => return eval_ast(&cur_ast, &mut cur_env),
-        };
-    }
-}
-
-fn eval_ast(ast: &MalValue, env: &mut Env) -> MalResult {
-    match *ast.mal_type {
-        Symbol(ref s) => env.get(&s),
-        List(ref mal_list) => Ok(MalValue::new_list(eval_ast_seq(&mal_list.vec, env)?)),
-        Vector(ref mal_vec) => Ok(MalValue::new_vector(eval_ast_seq(&mal_vec.vec, env)?)),
-        Map(ref mal_map) => eval_map(mal_map, env),
-        _ => Ok(ast.clone()),
-    }
-}
-
-fn eval_ast_seq(seq: &[MalValue], env: &mut Env) -> Result<Vec<MalValue>, MalError> {
-    seq.iter().map(|mal_val| eval(mal_val, env)).collect()
-}
-
-fn eval_map(mal_map: &MalMap, env: &mut Env) -> MalResult {
-    let map_args: Result<Vec<_>, _> = mal_map
-        .iter()
-        .flat_map(|(key, val)| once(Ok(key.clone())).chain(once(eval(val, env))))
-        .collect();
-
-    Ok(MalValue::new(Map(MalMap::from_arguments(
-        map_args?.as_slice(),
-    )?)))
-}
-
-fn apply_ast(ast: &MalValue, env: &mut Env) -> ApplyResult {
-    let evaluated_list_ast = eval_ast(ast, env)?;
-    match *evaluated_list_ast.mal_type {
-        List(MalList {
-            vec: ref evaluated_list,
-            ..
-        }) => match *evaluated_list
-            .get(0)
-            .expect("Evaluation of non-empty list resulted in empty list.")
-            .mal_type
-        {
-            RustFunc(ref rust_function) => Ok(Return((rust_function.func)(
-                &evaluated_list[1..],
-                &mut rust_function.env.clone(),
-            )?)),
-            MalFunc(ref mal_func) => {
-                let func_env = Env::with_binds(
-                    Some(&mal_func.outer_env),
-                    &mal_func.parameters,
-                    &evaluated_list[1..],
-                )?;
-                Ok(TailCall(mal_func.body.clone(), func_env))
-            }
-            _ => Err(MalError::Evaluation(
-                "First element of a list must evaluate to a function.".to_string(),
-            )),
-        },
-        _ => panic!(
-            "Evaluation of list resulted in non-list: {:?}",
-            evaluated_list_ast
-        ),
-    }
-}
-
-fn get_macro_function(ast: &MalValue, env: &Env) -> Option<MalValue> {
-    if let List(MalList { ref vec, .. }) = *ast.mal_type {
-        let first = vec.get(0)?;
-
-        if let Symbol(ref symbol) = *first.mal_type {
-            let val = env.get(symbol).ok()?;
-
-            if let MalFunc(ref function) = *val.mal_type {
-                if function.is_macro {
-                    return Some(val);
-                }
-            }
-        }
-    }
-
-    None
-}
-
-fn macroexpand(ast: &MalValue, env: &mut Env) -> MalResult {
-    let mut ast: MalValue = ast.clone();
-
-    while let Some(ref macro_val) = get_macro_function(&ast, env) {
-        if let MalFunc(ref function) = *macro_val.mal_type {
-            if let List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) = *ast.mal_type
-            {
-                let mut macro_env =
-                    Env::with_binds(Some(&function.outer_env), &function.parameters, &vec[1..])?;
-
-                ast = eval(&function.body, &mut macro_env)?;
-            } else {
-                unreachable!()
-            }
-        } else {
-            unreachable!()
-        }
-    }
-
-    Ok(ast.clone())
-}
-
-fn apply_special_form_def(args: &[MalValue], env: &mut Env) -> ApplyResult {
-    if args.len() != 2 {
-        return Err(MalError::SpecialForm(format!(
-            "def! expected 2 arguments, got {}",
-            args.len()
-        )));
-    }
-
-    let arg1 = if let Symbol(ref symbol) = *args[0].mal_type {
-        Ok(symbol)
-    } else {
-        Err(MalError::SpecialForm(
-            "def! first argument must be a valid symbol name".to_string(),
-        ))
-    }?;
-
-    let arg2 = eval(&args[1], env)?;
-
-    env.set(arg1.as_str(), arg2.clone());
-
-    Ok(Return(arg2))
-}
-
-fn apply_special_form_let(args: &[MalValue], env: &Env) -> ApplyResult {
-    if args.len() != 2 {
-        return Err(MalError::SpecialForm(format!(
-            "let* expected 2 arguments, got {}",
-            args.len()
-        )));
-    }
-
-    let bindings = match *args[0].mal_type {
-        List(MalList {
-            vec: ref bindings, ..
-        })
-        | Vector(MalVector {
-            vec: ref bindings, ..
-        }) => Ok(bindings.as_slice()),
-        _ => Err(MalError::SpecialForm(
-            "let* first argument must be a list or a vector".to_string(),
-        )),
-    }?;
-
-    if bindings.len() % 2 != 0 {
-        return Err(MalError::SpecialForm(
-            "let* bindings list must have an even number of elements".to_string(),
-        ));
-    }
-
-    let mut inner_env = Env::with_outer_env(env);
-
-    for i in (0..bindings.len()).step_by(2) {
-        let binding_name = if let Symbol(ref symbol) = *bindings[i].mal_type {
-            Ok(symbol)
-        } else {
-            Err(MalError::SpecialForm(
-                "let* odd numbered elements of binding list must be valid symbol names".to_string(),
-            ))
-        }?;
-
-        let binding_expr = eval(&bindings[i + 1], &mut inner_env)?;
-
-        inner_env.set(binding_name.as_str(), binding_expr);
-    }
-
-    Ok(TailCall(args[1].clone(), inner_env))
-}
-
-fn apply_special_form_fn(args: &[MalValue], env: &Env) -> ApplyResult {
-    if args.len() != 2 {
-        return Err(MalError::SpecialForm(format!(
-            "fn* expected 2 arguments, got {}",
-            args.len()
-        )));
-    }
-
-    let bindings = match *args[0].mal_type {
-        List(MalList {
-            vec: ref bindings, ..
-        })
-        | Vector(MalVector {
-            vec: ref bindings, ..
-        }) => Ok(bindings.as_slice()),
-        _ => Err(MalError::SpecialForm(
-            "fn* first argument must be a list or a vector".to_string(),
-        )),
-    }?;
-
-    let parameters: Result<Vec<String>, _> = bindings
-        .iter()
-        .map(|val| {
-            if let Symbol(ref symbol) = *val.mal_type {
-                Ok(symbol.clone())
-            } else {
-                Err(MalError::SpecialForm(
-                    "fn*! first argument must be a sequence of valid symbol names".to_string(),
-                ))
-            }
-        })
-        .collect();
-
-    Ok(Return(MalValue::new_mal_func(
-        args[1].clone(),
-        parameters?,
-        env.clone(),
-    )))
-}
-
-fn apply_special_form_do(args: &[MalValue], env: &mut Env) -> ApplyResult {
-    if args.is_empty() {
-        return Ok(Return(MalValue::nil()));
-    }
-
-    for expr in args[..args.len() - 1].iter() {
-        eval(expr, env)?;
-    }
-
-    Ok(TailCall(args.last().unwrap().clone(), env.clone()))
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use malrs::types::MalError::*;
 
-fn apply_special_form_if(args: &[MalValue], env: &mut Env) -> ApplyResult {
-    if args.len() < 2 || args.len() > 3 {
-        return Err(MalError::SpecialForm(format!(
-            "if expected 2 or 3 arguments, got {}",
-            args.len()
-        )));
+    #[test]
+    fn test_empty_program() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("", &mut env), Err(EmptyProgram));
     }
 
-    let test_result = eval(&args[0], env)?;
-
-    match *test_result.mal_type {
-        MalValueType::False | Nil => {
-            if args.len() == 3 {
-                Ok(TailCall(args[2].clone(), env.clone()))
-            } else {
-                Ok(Return(MalValue::nil()))
-            }
-        }
-        _ => Ok(TailCall(args[1].clone(), env.clone())),
+    #[test]
+    fn test_empty_list() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("()", &mut env), Ok("()".to_string()));
     }
-}
 
-fn apply_special_form_quote(args: &[MalValue], _env: &mut Env) -> ApplyResult {
-    if args.len() != 1 {
-        return Err(MalError::SpecialForm(format!(
-            "quote expects 1 argument, got {}",
-            args.len()
-        )));
+    #[test]
+    fn test_empty_vector() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("[]", &mut env), Ok("[]".to_string()));
     }
 
-    Ok(Return(args[0].clone()))
-}
-
-fn apply_special_form_quasiquote(args: &[MalValue], env: &mut Env) -> ApplyResult {
-    if args.len() != 1 {
-        return Err(MalError::SpecialForm(format!(
-            "quasiquote expects 1 argument, got {}",
-            args.len()
-        )));
+    #[test]
+    fn test_empty_map() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("{}", &mut env), Ok("{}".to_string()));
     }
 
-    Ok(TailCall(quasiquote(&args[0])?, env.clone()))
-}
-
-fn quasiquote(ast: &MalValue) -> MalResult {
-    match *ast.mal_type {
-        MalValueType::List(MalList { ref vec, .. })
-        | MalValueType::Vector(MalVector { ref vec, .. })
-            if !vec.is_empty() =>
-        {
-            let elem0 = &vec[0];
-            match *elem0.mal_type {
-                Symbol(ref s) if s == "unquote" => {
-                    if vec.len() != 2 {
-                        Err(MalError::SpecialForm(format!(
-                            "unquote expects 1 argument, got {}",
-                            vec.len() - 1
-                        )))
-                    } else {
-                        Ok(vec[1].clone())
-                    }
-                }
-                MalValueType::List(MalList {
-                    vec: ref inner_vec, ..
-                })
-                | MalValueType::Vector(MalVector {
-                    vec: ref inner_vec, ..
-                }) if !inner_vec.is_empty() => match *inner_vec[0].mal_type {
-                    Symbol(ref s) if s == "splice-unquote" => {
-                        if inner_vec.len() != 2 {
-                            Err(MalError::SpecialForm(format!(
-                                "splice-unquote expects 1 argument, got {}",
-                                inner_vec.len() - 1
-                            )))
-                        } else {
-                            Ok(MalValue::new_list(vec![
-                                MalValue::new(Symbol("concat".to_string())),
-                                inner_vec[1].clone(),
-                                quasiquote(&MalValue::new_list(vec[1..].to_vec()))?,
-                            ]))
-                        }
-                    }
-                    _ => Ok(MalValue::new_list(vec![
-                        MalValue::new(Symbol("cons".to_string())),
-                        quasiquote(elem0)?,
-                        quasiquote(&MalValue::new_list(vec[1..].to_vec()))?,
-                    ])),
-                },
-                _ => Ok(MalValue::new_list(vec![
-                    MalValue::new(Symbol("cons".to_string())),
-                    quasiquote(elem0)?,
-                    quasiquote(&MalValue::new_list(vec[1..].to_vec()))?,
-                ])),
-            }
-        }
-        _ => Ok(MalValue::new_list(vec![
-            MalValue::new(Symbol("quote".to_string())),
-            ast.clone(),
-        ])),
+    #[test]
+    fn test_nested_arithmetic() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(+ 2 (* 3 4))", &mut env), Ok("14".to_string()));
     }
-}
 
-fn apply_special_form_defmacro(args: &[MalValue], env: &mut Env) -> ApplyResult {
-    if args.len() != 2 {
-        return Err(MalError::SpecialForm(format!(
-            "defmacro! expected 2 arguments, got {}",
-            args.len()
-        )));
+    #[test]
+    fn test_int_and_float_arithmetic() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(+ 1 2)", &mut env), Ok("3".to_string()));
+        assert_eq!(rep("(+ 1 2.0)", &mut env), Ok("3.0".to_string()));
+        assert_eq!(rep("(* 1.5 2)", &mut env), Ok("3.0".to_string()));
+        assert_eq!(rep("(/ 6 2)", &mut env), Ok("3".to_string()));
+        assert_eq!(rep("(/ 7 2)", &mut env), Ok("7/2".to_string()));
+        assert_eq!(rep("(/ 7 2.0)", &mut env), Ok("3.5".to_string()));
+        assert_eq!(
+            rep("(+ 9007199254740992 1)", &mut env),
+            Ok("9007199254740993".to_string())
+        );
+        assert_eq!(
+            rep("(+ 9223372036854775807 1)", &mut env),
+            Ok("9223372036854775808N".to_string())
+        );
+        assert!(rep("(/ 1 0)", &mut env).is_err());
     }
 
-    let arg1 = if let Symbol(ref symbol) = *args[0].mal_type {
-        Ok(symbol)
-    } else {
-        Err(MalError::SpecialForm(
-            "defmacro! first argument must be a valid symbol name".to_string(),
-        ))
-    }?;
-
-    let arg2 = eval(&args[1], env)?;
-
-    let macro_val = if let MalFunc(ref mal_function) = *arg2.mal_type {
-        MalValue::new_mal_macro(
-            mal_function.body.clone(),
-            mal_function.parameters.clone(),
-            mal_function.outer_env.clone(),
+    #[test]
+    fn test_bigints_and_ratios() {
+        let mut env = create_root_env(&[]);
+        rep(
+            "(def! fact (fn* (n) (if (< n 2) 1 (* n (fact (- n 1))))))",
+            &mut env,
         )
-    } else {
-        Err(MalError::SpecialForm(
-            "defmacro! second argument must evaluate to a function".to_string(),
-        ))?
-    };
-
-    env.set(arg1.as_str(), macro_val.clone());
-
-    Ok(Return(macro_val))
-}
-
-fn apply_special_form_macroexpand(args: &[MalValue], env: &mut Env) -> ApplyResult {
-    if args.len() != 1 {
-        return Err(MalError::SpecialForm(format!(
-            "macroexpand expected 1 arguments, got {}",
-            args.len()
-        )));
+        .unwrap();
+        assert_eq!(
+            rep("(fact 25)", &mut env),
+            Ok("15511210043330985984000000N".to_string())
+        );
+        assert_eq!(
+            rep("(/ (fact 25) (fact 24))", &mut env),
+            Ok("25".to_string())
+        );
+        assert_eq!(rep("(+ 1/3 2/3)", &mut env), Ok("1".to_string()));
+        assert_eq!(rep("(* 22/7 7)", &mut env), Ok("22".to_string()));
+        assert_eq!(rep("(- 1/2 1)", &mut env), Ok("-1/2".to_string()));
+        assert_eq!(rep("(+ 1/2 0.5)", &mut env), Ok("1.0".to_string()));
+        assert_eq!(rep("(< 1/3 0.34)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(> 1/3 1/4)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(= 1/3 2/6)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(= 5 5N)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(+ 5N 1)", &mut env), Ok("6N".to_string()));
+        assert_eq!(rep("(str 5N)", &mut env), Ok("\"5\"".to_string()));
+        assert_eq!(rep("(numerator 6/4)", &mut env), Ok("3".to_string()));
+        assert_eq!(rep("(denominator 6/4)", &mut env), Ok("2".to_string()));
+        assert_eq!(rep("(denominator 5)", &mut env), Ok("1".to_string()));
+        assert!(rep("(numerator 1.5)", &mut env).is_err());
+        assert_eq!(rep("(rationalize 0.1)", &mut env), Ok("1/10".to_string()));
+        assert_eq!(rep("(rationalize 2.5e3)", &mut env), Ok("2500".to_string()));
+        assert_eq!(rep("(rationalize 1/3)", &mut env), Ok("1/3".to_string()));
+        assert_eq!(rep("(bigint? 5N)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(bigint? 5)", &mut env), Ok("false".to_string()));
+        assert_eq!(rep("(number? 1/2)", &mut env), Ok("true".to_string()));
+        assert!(rep("(/ 1/2 0)", &mut env).is_err());
     }
 
-    let expanded = macroexpand(&args[0], env)?;
-
-    Ok(Return(expanded))
-}
-
-fn apply_special_form_try(args: &[MalValue], env: &mut Env) -> ApplyResult {
-    if args.len() != 2 {
-        return Err(MalError::SpecialForm(format!(
-            "try* expected 2 arguments, got {}",
-            args.len()
-        )));
-    }
-
-    let exception_symbol;
-    let catch_expression;
-
-    if let List(MalList {
-        vec: ref catch_vec, ..
-    }) = *args[1].mal_type
-    {
-        if catch_vec.is_empty() {
-            return Err(MalError::SpecialForm(
-                "try* second argument must be a non-empty list".to_string(),
-            ));
-        }
-
-        match *catch_vec[0].mal_type {
-            Symbol(ref s) if s == "catch*" => {}
-            _ => {
-                return Err(MalError::SpecialForm(
-                    "try* second argument must start with symbol 'catch*'".to_string(),
-                ));
-            }
-        }
-
-        if catch_vec.len() != 3 {
-            return Err(MalError::SpecialForm(format!(
-                "catch* expected 2 arguments, got {}",
-                catch_vec.len() - 1
-            )));
-        }
-
-        exception_symbol = if let Symbol(ref s) = *catch_vec[1].mal_type {
-            s
-        } else {
-            return Err(MalError::SpecialForm(
-                "catch* first argument must be a symbol".to_string(),
-            ));
-        };
-
-        catch_expression = &catch_vec[2];
-    } else {
-        return Err(MalError::SpecialForm(
-            "try* second argument must be a non-empty list".to_string(),
-        ));
+    #[test]
+    fn test_int_and_float_comparison() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(< 1 1.5)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(>= 2 2.0)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(= 2 2.0)", &mut env), Ok("false".to_string()));
+        assert_eq!(rep("(= 2.0 2.0)", &mut env), Ok("true".to_string()));
     }
 
-    let try_result = eval(&args[0], env);
-
-    if try_result.is_ok() {
-        return Ok(Return(try_result.unwrap()));
+    #[test]
+    fn test_strings_read_back() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(
+            rep(r#"(= "\x41\u00e9" "Aé")"#, &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep(r#"r"C:\temp\new""#, &mut env),
+            Ok(r#""C:\\temp\\new""#.to_string())
+        );
+        assert_eq!(
+            rep(r#""tab\tcr\rnul\0bell\u{7}""#, &mut env),
+            Ok(r#""tab\tcr\rnul\0bell\u{7}""#.to_string())
+        );
+        assert_eq!(
+            rep(
+                r#"(let* [s "q\"\\\n\t\r\0\u{1b}\u{1F600}"] (= s (read-string (pr-str s))))"#,
+                &mut env
+            ),
+            Ok("true".to_string())
+        );
     }
 
-    let mal_error = try_result.err().unwrap();
-
-    let exception = if let MalError::Exception(ref exception_val) = mal_error {
-        exception_val.clone()
-    } else {
-        MalValue::new(Str(mal_error.to_string()))
-    };
-
-    let mut catch_env = Env::with_outer_env(env);
-    catch_env.set(exception_symbol, exception);
-
-    Ok(Return(eval(catch_expression, &mut catch_env)?))
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use malrs::types::MalError::*;
-
     #[test]
-    fn test_empty_program() {
+    fn test_chars() {
         let mut env = create_root_env(&[]);
-        assert_eq!(rep("", &mut env), Err(EmptyProgram));
+        assert_eq!(rep(r"\a", &mut env), Ok(r"\a".to_string()));
+        assert_eq!(
+            rep(r"[\space \u03bb]", &mut env),
+            Ok(r"[\space \λ]".to_string())
+        );
+        assert_eq!(
+            rep(r#"(seq "héllo")"#, &mut env),
+            Ok(r"(\h \é \l \l \o)".to_string())
+        );
+        assert_eq!(rep(r#"(first "abc")"#, &mut env), Ok(r"\a".to_string()));
+        assert_eq!(rep(r#"(first "")"#, &mut env), Ok("nil".to_string()));
+        assert_eq!(rep(r#"(nth "héllo" 1)"#, &mut env), Ok(r"\é".to_string()));
+        assert!(rep(r#"(nth "abc" 3)"#, &mut env).is_err());
+        assert_eq!(
+            rep(r#"(= (first "abc") \a)"#, &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(rep(r#"(= \a "a")"#, &mut env), Ok("false".to_string()));
+        assert_eq!(
+            rep(r"(str \a \b \newline)", &mut env),
+            Ok("\"ab\\n\"".to_string())
+        );
+        assert_eq!(rep(r"(char? \a)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep(r#"(char? "a")"#, &mut env), Ok("false".to_string()));
+        assert_eq!(rep(r"(int \A)", &mut env), Ok("65".to_string()));
+        assert_eq!(rep("(char 955)", &mut env), Ok(r"\λ".to_string()));
+        assert!(rep("(char -1)", &mut env).is_err());
+        assert!(rep("(char 55296)", &mut env).is_err());
+        assert_eq!(rep("(int -2.7)", &mut env), Ok("-2".to_string()));
+        assert_eq!(rep("(int 7/2)", &mut env), Ok("3".to_string()));
+        assert!(rep("(int 1e19)", &mut env).is_err());
+        assert_eq!(
+            rep(r"(= (read-string (pr-str \newline)) \newline)", &mut env),
+            Ok("true".to_string())
+        );
     }
 
     #[test]
-    fn test_empty_list() {
+    fn test_arbitrary_map_keys() {
         let mut env = create_root_env(&[]);
-        assert_eq!(rep("()", &mut env), Ok("()".to_string()));
+        assert_eq!(
+            rep("(get {[1 2] :a} (list 1 2))", &mut env),
+            Ok(":a".to_string())
+        );
+        assert_eq!(
+            rep("(get (assoc {} 1 :int 1.0 :float nil :nil) 1)", &mut env),
+            Ok(":int".to_string())
+        );
+        assert_eq!(
+            rep("(get {{:x 1} :map #{2 3} :set} #{3 2})", &mut env),
+            Ok(":set".to_string())
+        );
+        assert_eq!(
+            rep("(contains? (dissoc {1 :a 2 :b} 1) 1)", &mut env),
+            Ok("false".to_string())
+        );
+        assert_eq!(
+            rep("(let* [x 2] (get {(+ x 1) :three} 3))", &mut env),
+            Ok(":three".to_string())
+        );
+        assert_eq!(
+            rep("(= {[1] :a} {'(1) :a})", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(count #{1 1N [1] '(1)})", &mut env),
+            Ok("2".to_string())
+        );
+        rep("(def! memo (atom {}))", &mut env).unwrap();
+        rep("(swap! memo assoc [1 2] 3)", &mut env).unwrap();
+        assert_eq!(rep("(get @memo [1 2])", &mut env), Ok("3".to_string()));
     }
 
     #[test]
-    fn test_empty_vector() {
+    fn test_sets() {
         let mut env = create_root_env(&[]);
-        assert_eq!(rep("[]", &mut env), Ok("[]".to_string()));
+        assert_eq!(rep("#{}", &mut env), Ok("#{}".to_string()));
+        assert_eq!(rep("#{:a :a}", &mut env), Ok("#{:a}".to_string()));
+        assert_eq!(
+            rep(r#"(let* [k :b] (= #{(keyword "a") k} #{:b :a}))"#, &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(= (hash-set :a :b :a) #{:a :b})", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(= (set [:a :b :a]) #{:a :b})", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(rep("(set nil)", &mut env), Ok("#{}".to_string()));
+        assert_eq!(rep("(set? #{})", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(set? {})", &mut env), Ok("false".to_string()));
+        assert_eq!(
+            rep("(disj #{:a :b :c} :b :c :d)", &mut env),
+            Ok("#{:a}".to_string())
+        );
+        assert_eq!(
+            rep("(= (conj #{:a} :b :a) #{:a :b})", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(contains? #{:a} :a)", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(contains? #{:a} :b)", &mut env),
+            Ok("false".to_string())
+        );
+        assert_eq!(rep("(count #{:a :b})", &mut env), Ok("2".to_string()));
+        assert_eq!(rep("(empty? #{})", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(seq #{})", &mut env), Ok("nil".to_string()));
+        assert_eq!(rep("(seq #{:a})", &mut env), Ok("(:a)".to_string()));
+        assert_eq!(
+            rep("(= #{:a :b} #{:b :a})", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(rep("(= #{:a} [:a])", &mut env), Ok("false".to_string()));
+        assert_eq!(rep("(union)", &mut env), Ok("#{}".to_string()));
+        assert_eq!(
+            rep("(= (union #{:a} #{:b} #{:a}) #{:a :b})", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(intersection #{:a :b :c} #{:b :c} #{:c :d})", &mut env),
+            Ok("#{:c}".to_string())
+        );
+        assert_eq!(
+            rep("(difference #{:a :b :c} #{:b} #{:c})", &mut env),
+            Ok("#{:a}".to_string())
+        );
+        assert_eq!(
+            rep("(meta (with-meta #{:a} {:m 1}))", &mut env),
+            Ok("{:m 1}".to_string())
+        );
+        assert!(rep("(union #{:a} [:b])", &mut env).is_err());
     }
 
     #[test]
-    fn test_empty_map() {
+    fn test_number_literals_read_back() {
         let mut env = create_root_env(&[]);
-        assert_eq!(rep("{}", &mut env), Ok("{}".to_string()));
+        assert_eq!(
+            rep("[0xff 0b11 0o10 1_000 +3]", &mut env),
+            Ok("[255 3 8 1000 3]".to_string())
+        );
+        assert_eq!(
+            rep("[1e3 .5 -2.5e-1]", &mut env),
+            Ok("[1000.0 0.5 -0.25]".to_string())
+        );
+        assert_eq!(rep("(/ 1.0 0)", &mut env), Ok("##Inf".to_string()));
+        assert_eq!(
+            rep("(= (read-string (pr-str (/ -1.0 0))) ##-Inf)", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(pr-str (- ##Inf ##Inf))", &mut env),
+            Ok("\"##NaN\"".to_string())
+        );
+        assert_eq!(
+            rep("[1e300 -1.5e-300]", &mut env),
+            Ok("[1e300 -1.5e-300]".to_string())
+        );
+        assert_eq!(
+            rep(
+                "(= (read-string (pr-str 1.2345e-200)) 1.2345e-200)",
+                &mut env
+            ),
+            Ok("true".to_string())
+        );
+        assert!(rep("(+ 1e 2)", &mut env).is_err());
     }
 
     #[test]
-    fn test_nested_arithmetic() {
+    fn test_nth_requires_integer_index() {
         let mut env = create_root_env(&[]);
-        assert_eq!(rep("(+ 2 (* 3 4))", &mut env), Ok("14".to_string()));
+        assert_eq!(rep("(nth [1 2 3] 1)", &mut env), Ok("2".to_string()));
+        assert!(rep("(nth [1 2 3] 1.9)", &mut env).is_err());
+        assert!(rep("(nth [1 2 3] -1)", &mut env).is_err());
     }
 
     #[test]
@@ -909,4 +637,909 @@ mod tests {
             Ok("12345".to_string())
         );
     }
+
+    #[test]
+    fn test_error_position() {
+        let mut env = create_root_env(&[]);
+
+        assert_eq!(
+            rep("(let* [a 1]\n  (+ a (abc 2)))", &mut env)
+                .unwrap_err()
+                .to_string(),
+            "2:9: 'abc' not found"
+        );
+
+        assert_eq!(
+            rep(
+                r#"(eval (read-string "(do\n (nth [] 1))" "f.mal"))"#,
+                &mut env
+            )
+            .unwrap_err()
+            .to_string(),
+            "f.mal:2:2: Error when calling rust function: nth: index out of range"
+        );
+
+        assert_eq!(
+            rep(r#"(try* (abc 1) (catch* e e))"#, &mut env),
+            Ok(r#""'abc' not found""#.to_string())
+        );
+    }
+
+    #[test]
+    fn test_load_file() {
+        let mut env = create_root_env(&[]);
+
+        let file_path = std::env::temp_dir().join("malrs_test_load_file.mal");
+        let file_name = file_path.to_str().unwrap();
+
+        std::fs::write(
+            &file_path,
+            "(def! x 1)\n(def! y (+ x 1)) ; no trailing newline",
+        )
+        .unwrap();
+
+        assert_eq!(
+            rep(&format!(r#"(load-file "{}")"#, file_name), &mut env),
+            Ok("2".to_string())
+        );
+        assert_eq!(rep("y", &mut env), Ok("2".to_string()));
+
+        std::fs::write(
+            &file_path,
+            "(def! z 1)\n\n(do\n  (undefined-fn z))\n(def! w 2)",
+        )
+        .unwrap();
+
+        assert_eq!(
+            rep(&format!(r#"(load-file "{}")"#, file_name), &mut env)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "{0}:4:4: 'undefined-fn' not found (in top-level form #2 at {0}:3:1)",
+                file_name
+            )
+        );
+        assert_eq!(rep("z", &mut env), Ok("1".to_string()));
+        assert_eq!(
+            rep("w", &mut env).unwrap_err().root_cause(),
+            UndefinedSymbol("w".to_string())
+        );
+
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_large_persistent_collections() {
+        let mut env = create_root_env(&[]);
+        rep(
+            "(def! build (fn* (coll n) (if (= n 0) coll (build (conj coll n) (- n 1)))))",
+            &mut env,
+        )
+        .unwrap();
+        rep(
+            "(def! build-map (fn* (m n) (if (= n 0) m (build-map (assoc m n n) (- n 1)))))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(
+            rep("(count (build [] 10000))", &mut env),
+            Ok("10000".to_string())
+        );
+        assert_eq!(
+            rep("(count (keys (build-map {} 10000)))", &mut env),
+            Ok("10000".to_string())
+        );
+        assert_eq!(
+            rep("(let* [v (build [] 3)] [(conj v 0) v])", &mut env),
+            Ok("[[3 2 1 0] [3 2 1]]".to_string())
+        );
+        assert_eq!(
+            rep("(let* [l (list 1 2 3)] [(rest l) (cons 0 l) l])", &mut env),
+            Ok("[(2 3) (0 1 2 3) (1 2 3)]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(compare 1 2)", &mut env), Ok("-1".to_string()));
+        assert_eq!(rep("(compare 2N 2.0)", &mut env), Ok("0".to_string()));
+        assert_eq!(rep("(compare 1/2 0.25)", &mut env), Ok("1".to_string()));
+        assert_eq!(rep(r#"(compare "b" "abc")"#, &mut env), Ok("1".to_string()));
+        assert_eq!(rep("(compare :a :b)", &mut env), Ok("-1".to_string()));
+        assert_eq!(
+            rep("(compare [1 2] '(1 3))", &mut env),
+            Ok("-1".to_string())
+        );
+        assert_eq!(rep("(compare [9] [1 1])", &mut env), Ok("-1".to_string()));
+        assert_eq!(
+            rep(r#"(compare nil false)"#, &mut env),
+            Ok("-1".to_string())
+        );
+        assert_eq!(rep(r#"(compare "a" 1)"#, &mut env), Ok("1".to_string()));
+        assert!(rep("(compare {} {})", &mut env).is_err());
+    }
+
+    #[test]
+    fn test_sorted_collections() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(
+            rep("(sorted-map :c 3 :a 1 :b 2)", &mut env),
+            Ok("{:a 1 :b 2 :c 3}".to_string())
+        );
+        assert_eq!(
+            rep("(keys (assoc (sorted-map 2 :b 1 :a) 0 :z))", &mut env),
+            Ok("(0 1 2)".to_string())
+        );
+        assert_eq!(
+            rep("(vals (dissoc (sorted-map 2 :b 1 :a 3 :c) 2))", &mut env),
+            Ok("(:a :c)".to_string())
+        );
+        assert_eq!(
+            rep("(get (sorted-map [1 2] :x) '(1 2))", &mut env),
+            Ok(":x".to_string())
+        );
+        assert_eq!(
+            rep(
+                "(sorted-map-by (fn* (a b) (compare b a)) 1 :a 3 :c 2 :b)",
+                &mut env
+            ),
+            Ok("{3 :c 2 :b 1 :a}".to_string())
+        );
+        assert_eq!(
+            rep("(seq (sorted-map-by > 1 :a 2 :b))", &mut env),
+            Ok("([2 :b] [1 :a])".to_string())
+        );
+        assert_eq!(
+            rep(r#"(sorted-set "b" "c" "a" "b")"#, &mut env),
+            Ok(r#"#{"a" "b" "c"}"#.to_string())
+        );
+        assert_eq!(
+            rep("(conj (sorted-set-by > 1 3) 2 4)", &mut env),
+            Ok("#{4 3 2 1}".to_string())
+        );
+        assert_eq!(
+            rep("(disj (sorted-set 1 2 3) 2)", &mut env),
+            Ok("#{1 3}".to_string())
+        );
+        assert_eq!(
+            rep("(= (sorted-map 1 2 3 4) {3 4 1 2})", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(= (sorted-set 1 2) #{2 1})", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep(
+                "[(map? (sorted-map)) (set? (sorted-set)) (sorted? (sorted-set))]",
+                &mut env
+            ),
+            Ok("[true true true]".to_string())
+        );
+        assert_eq!(
+            rep("(count (sorted-map 1 2 3 4))", &mut env),
+            Ok("2".to_string())
+        );
+        assert!(rep("(sorted-set 1 {})", &mut env).is_err());
+        assert_eq!(
+            rep("(union (sorted-set 3 1) #{2})", &mut env),
+            Ok("#{1 2 3}".to_string())
+        );
+        assert_eq!(
+            rep("(sorted? (union (sorted-set 3 1) #{2}))", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(intersection (sorted-set-by > 1 2 3) #{1 3 4})", &mut env),
+            Ok("#{3 1}".to_string())
+        );
+        assert_eq!(
+            rep("(= #{1 3} (difference #{1 2 3} (sorted-set 2)))", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(sorted? (difference #{1 2 3} (sorted-set 2)))", &mut env),
+            Ok("false".to_string())
+        );
+    }
+
+    #[test]
+    fn test_subseq() {
+        let mut env = create_root_env(&[]);
+        rep("(def! s (sorted-set 1 2 3 4 5))", &mut env).unwrap();
+        assert_eq!(rep("(subseq s > 2)", &mut env), Ok("(3 4 5)".to_string()));
+        assert_eq!(rep("(subseq s <= 2)", &mut env), Ok("(1 2)".to_string()));
+        assert_eq!(
+            rep("(subseq s >= 2 < 4)", &mut env),
+            Ok("(2 3)".to_string())
+        );
+        assert_eq!(
+            rep("(rsubseq s > 1 <= 4)", &mut env),
+            Ok("(4 3 2)".to_string())
+        );
+        assert_eq!(rep("(subseq s > 5)", &mut env), Ok("nil".to_string()));
+        assert_eq!(
+            rep("(subseq (sorted-map :a 1 :b 2 :c 3) >= :b)", &mut env),
+            Ok("([:b 2] [:c 3])".to_string())
+        );
+        assert_eq!(
+            rep("(subseq (sorted-set-by > 1 2 3) > 2)", &mut env),
+            Ok("(1)".to_string())
+        );
+        assert!(rep("(subseq s = 2)", &mut env).is_err());
+    }
+
+    #[test]
+    fn test_maps_keep_insertion_order() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(
+            rep("{:z 1 :y 2 :x 3 :w 4}", &mut env),
+            Ok("{:z 1 :y 2 :x 3 :w 4}".to_string())
+        );
+        assert_eq!(
+            rep("(assoc {:b 1 :a 2} :c 3 :b 0)", &mut env),
+            Ok("{:b 0 :a 2 :c 3}".to_string())
+        );
+        assert_eq!(
+            rep("(keys (hash-map 3 :c 1 :a 2 :b))", &mut env),
+            Ok("(3 1 2)".to_string())
+        );
+        assert_eq!(
+            rep("(vals (dissoc {:b 1 :a 2 :c 3} :a))", &mut env),
+            Ok("(1 3)".to_string())
+        );
+        assert_eq!(
+            rep("(seq {:b 1 :a 2})", &mut env),
+            Ok("([:b 1] [:a 2])".to_string())
+        );
+        assert_eq!(rep("(seq {})", &mut env), Ok("nil".to_string()));
+        assert_eq!(rep("(count {:a 1 :b 2})", &mut env), Ok("2".to_string()));
+        assert_eq!(
+            rep("(= {:a 1 :b 2} {:b 2 :a 1})", &mut env),
+            Ok("true".to_string())
+        );
+    }
+
+    #[test]
+    fn test_lazy_seqs() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(
+            rep("(take 5 (map (fn* (x) (* x x)) (range)))", &mut env),
+            Ok("(0 1 4 9 16)".to_string())
+        );
+        assert_eq!(
+            rep("(range 2 12 3)", &mut env),
+            Ok("(2 5 8 11)".to_string())
+        );
+        assert_eq!(rep("(range 3 0 -1)", &mut env), Ok("(3 2 1)".to_string()));
+        assert_eq!(rep("(range 0)", &mut env), Ok("()".to_string()));
+        assert_eq!(
+            rep("(take 4 (iterate (fn* (x) (* 2 x)) 1))", &mut env),
+            Ok("(1 2 4 8)".to_string())
+        );
+        assert_eq!(
+            rep("(take 3 (drop 2 (cycle [:a :b :c])))", &mut env),
+            Ok("(:c :a :b)".to_string())
+        );
+        assert_eq!(rep("(repeat 3 :x)", &mut env), Ok("(:x :x :x)".to_string()));
+        assert_eq!(
+            rep("(take 2 (repeat \"x\"))", &mut env),
+            Ok("(\"x\" \"x\")".to_string())
+        );
+        assert_eq!(
+            rep(
+                "(take 3 (filter (fn* (x) (> (* x x) 10)) (drop 1 (range))))",
+                &mut env
+            ),
+            Ok("(4 5 6)".to_string())
+        );
+        assert_eq!(
+            rep("(first (concat (range 0) [] (range 7 10)))", &mut env),
+            Ok("7".to_string())
+        );
+        assert_eq!(
+            rep("(conj (cons 1 (range 2 4)) 0)", &mut env),
+            Ok("(0 1 2 3)".to_string())
+        );
+        assert_eq!(rep("(nth (range) 1000)", &mut env), Ok("1000".to_string()));
+        assert_eq!(rep("(apply + (range 2 4))", &mut env), Ok("5".to_string()));
+        assert_eq!(rep("(seq (range 0))", &mut env), Ok("nil".to_string()));
+        assert_eq!(
+            rep("(= (range 3) [0 1 2])", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(count (range 100000))", &mut env),
+            Ok("100000".to_string())
+        );
+    }
+
+    #[test]
+    fn test_lazy_seq_special_form() {
+        let mut env = create_root_env(&[]);
+        rep(
+            "(def! fib (fn* (a b) (lazy-seq (cons a (fib b (+ a b))))))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(
+            rep("(take 8 (fib 0 1))", &mut env),
+            Ok("(0 1 1 2 3 5 8 13)".to_string())
+        );
+        assert_eq!(rep("(lazy-seq nil)", &mut env), Ok("()".to_string()));
+
+        rep("(def! calls (atom 0))", &mut env).unwrap();
+        rep(
+            "(do (def! s (lazy-seq (swap! calls (fn* (n) (+ n 1))) (list 1 2))) nil)",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(rep("(realized? s)", &mut env), Ok("false".to_string()));
+        assert_eq!(rep("(first s)", &mut env), Ok("1".to_string()));
+        assert_eq!(rep("(rest s)", &mut env), Ok("(2)".to_string()));
+        assert_eq!(rep("(realized? s)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("@calls", &mut env), Ok("1".to_string()));
+
+        assert!(rep("(lazy-seq (throw \"oops\"))", &mut env).is_err());
+        assert_eq!(
+            rep(
+                "(try* (count (lazy-seq (throw \"oops\"))) (catch* e e))",
+                &mut env
+            ),
+            Ok("\"oops\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sequence_builtins_accept_any_collection() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(first \"abc\")", &mut env), Ok("\\a".to_string()));
+        assert_eq!(rep("(rest \"abc\")", &mut env), Ok("(\\b \\c)".to_string()));
+        assert_eq!(rep("(nth \"abc\" 2)", &mut env), Ok("\\c".to_string()));
+        assert_eq!(
+            rep("(map (fn* (c) (str c c)) \"ab\")", &mut env),
+            Ok("(\"aa\" \"bb\")".to_string())
+        );
+        assert_eq!(rep("(first {:a 1})", &mut env), Ok("[:a 1]".to_string()));
+        assert_eq!(
+            rep("(map first {:a 1 :b 2})", &mut env),
+            Ok("(:a :b)".to_string())
+        );
+        assert_eq!(
+            rep("(nth (sorted-map 2 :b 1 :a) 1)", &mut env),
+            Ok("[2 :b]".to_string())
+        );
+        assert_eq!(
+            rep("(first (sorted-set 3 1 2))", &mut env),
+            Ok("1".to_string())
+        );
+        assert_eq!(rep("(apply + 1 #{2})", &mut env), Ok("3".to_string()));
+        assert_eq!(rep("(apply list 1 nil)", &mut env), Ok("(1)".to_string()));
+        assert_eq!(
+            rep("(concat \"ab\" nil [1] (sorted-set 2))", &mut env),
+            Ok("(\\a \\b 1 2)".to_string())
+        );
+        assert_eq!(rep("(cons 0 nil)", &mut env), Ok("(0)".to_string()));
+        assert_eq!(rep("(cons 0 \"a\")", &mut env), Ok("(0 \\a)".to_string()));
+        assert_eq!(rep("(count (seq \"abc\"))", &mut env), Ok("3".to_string()));
+        assert_eq!(rep("(empty? nil)", &mut env), Ok("true".to_string()));
+        assert_eq!(
+            rep("(= (set \"aba\") #{\\a \\b})", &mut env),
+            Ok("true".to_string())
+        );
+        assert!(rep("(first 1)", &mut env).is_err());
+        assert!(rep("(map list :a)", &mut env).is_err());
+    }
+
+    #[test]
+    fn test_sequence_library() {
+        let mut env = create_root_env(&[]);
+        rep(
+            "(def! odd? (fn* (n) (= 1 (- n (* 2 (int (/ n 2)))))))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(
+            rep("(remove odd? [1 2 3 4])", &mut env),
+            Ok("(2 4)".to_string())
+        );
+        assert_eq!(rep("(reduce + [1 2 3 4])", &mut env), Ok("10".to_string()));
+        assert_eq!(
+            rep("(reduce + 10 (range 5))", &mut env),
+            Ok("20".to_string())
+        );
+        assert_eq!(rep("(reduce list [])", &mut env), Ok("()".to_string()));
+        assert_eq!(
+            rep(
+                "(reduce (fn* (acc x) (if (> x 3) (reduced acc) (+ acc x))) 0 (range))",
+                &mut env
+            ),
+            Ok("6".to_string())
+        );
+        assert_eq!(
+            rep("(reduced? (reduced 1))", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(rep("@(reduced 1)", &mut env), Ok("1".to_string()));
+        assert_eq!(
+            rep("(take-while (fn* (x) (< x 3)) (range))", &mut env),
+            Ok("(0 1 2)".to_string())
+        );
+        assert_eq!(
+            rep("(drop-while (fn* (x) (< x 3)) [1 2 3 4 1])", &mut env),
+            Ok("(3 4 1)".to_string())
+        );
+        assert_eq!(
+            rep("(reverse [1 2 3])", &mut env),
+            Ok("(3 2 1)".to_string())
+        );
+        assert_eq!(
+            rep("(sort [3 \"b\" 1 nil \"a\"])", &mut env),
+            Ok("(nil 1 3 \"a\" \"b\")".to_string())
+        );
+        assert_eq!(rep("(sort > [3 1 2])", &mut env), Ok("(3 2 1)".to_string()));
+        assert_eq!(
+            rep("(sort-by count [\"ccc\" \"a\" \"bb\" \"d\"])", &mut env),
+            Ok("(\"a\" \"d\" \"bb\" \"ccc\")".to_string())
+        );
+        assert_eq!(
+            rep("(sort-by first > [[1 :a] [3 :b] [2 :c]])", &mut env),
+            Ok("([3 :b] [2 :c] [1 :a])".to_string())
+        );
+        assert!(rep("(sort [(atom 1) (atom 2)])", &mut env).is_err());
+        assert_eq!(
+            rep("(group-by odd? [1 2 3 4 5])", &mut env),
+            Ok("{true [1 3 5] false [2 4]}".to_string())
+        );
+        assert_eq!(
+            rep("(frequencies [:a :b :a :c :a])", &mut env),
+            Ok("{:a 3 :b 1 :c 1}".to_string())
+        );
+        assert_eq!(
+            rep("(partition 2 (range 7))", &mut env),
+            Ok("((0 1) (2 3) (4 5))".to_string())
+        );
+        assert_eq!(
+            rep("(partition 3 2 [:x] (range 6))", &mut env),
+            Ok("((0 1 2) (2 3 4) (4 5 :x))".to_string())
+        );
+        assert_eq!(
+            rep("(take 2 (partition 2 1 (range)))", &mut env),
+            Ok("((0 1) (1 2))".to_string())
+        );
+        assert_eq!(
+            rep("(interleave [:a :b :c] (range))", &mut env),
+            Ok("(:a 0 :b 1 :c 2)".to_string())
+        );
+        assert_eq!(
+            rep("(distinct [1 2 1 3 2])", &mut env),
+            Ok("(1 2 3)".to_string())
+        );
+        assert_eq!(
+            rep("(mapcat (fn* (x) [x x]) [1 2])", &mut env),
+            Ok("(1 1 2 2)".to_string())
+        );
+        assert_eq!(rep("(some odd? [2 4 5])", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(some odd? [2 4])", &mut env), Ok("nil".to_string()));
+        assert_eq!(rep("(every? odd? [1 3])", &mut env), Ok("true".to_string()));
+        assert_eq!(
+            rep("(every? odd? [1 2])", &mut env),
+            Ok("false".to_string())
+        );
+        assert_eq!(
+            rep("(into [1] (range 2 4))", &mut env),
+            Ok("[1 2 3]".to_string())
+        );
+        assert_eq!(rep("(into () [1 2])", &mut env), Ok("(2 1)".to_string()));
+        assert_eq!(
+            rep("(into {:a 1} [[:b 2] [:a 3]])", &mut env),
+            Ok("{:a 3 :b 2}".to_string())
+        );
+        assert_eq!(
+            rep("(into (sorted-map) {:b 1 :a 2})", &mut env),
+            Ok("{:a 2 :b 1}".to_string())
+        );
+        assert_eq!(
+            rep("(map list [1 2 3] [:a :b])", &mut env),
+            Ok("((1 :a) (2 :b))".to_string())
+        );
+        assert_eq!(
+            rep("(map + (range) [10 20])", &mut env),
+            Ok("(10 21)".to_string())
+        );
+        assert_eq!(
+            rep("(reduce + (range 100000))", &mut env),
+            Ok("4999950000".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sort_with_inconsistent_comparator() {
+        let mut env = create_root_env(&[]);
+        rep("(def! n (atom 0))", &mut env).unwrap();
+        assert_eq!(
+            rep(
+                "(count (sort (fn* [a b] (do (swap! n inc) (if (= 0 (rem @n 3)) -1 1))) (range 200)))",
+                &mut env
+            ),
+            Ok("200".to_string())
+        );
+        assert_eq!(
+            rep("(sort-by first [[1 :b] [0 :x] [1 :a]])", &mut env),
+            Ok("([0 :x] [1 :b] [1 :a])".to_string())
+        );
+        assert_eq!(
+            rep(
+                r#"(try* (sort (fn* [a b] (throw "bad comparator")) [2 1]) (catch* e e))"#,
+                &mut env
+            ),
+            Ok("\"bad comparator\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_variadic_arithmetic_and_comparison() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(+)", &mut env), Ok("0".to_string()));
+        assert_eq!(rep("(*)", &mut env), Ok("1".to_string()));
+        assert_eq!(rep("(+ 1 2 3 4)", &mut env), Ok("10".to_string()));
+        assert_eq!(rep("(* 2 3 4)", &mut env), Ok("24".to_string()));
+        assert_eq!(rep("(- 5)", &mut env), Ok("-5".to_string()));
+        assert_eq!(rep("(- 10 1 2)", &mut env), Ok("7".to_string()));
+        assert_eq!(rep("(/ 4)", &mut env), Ok("1/4".to_string()));
+        assert_eq!(rep("(/ 12 2 3)", &mut env), Ok("2".to_string()));
+        assert!(rep("(-)", &mut env).is_err());
+        assert!(rep("(+ 1 :a)", &mut env).is_err());
+
+        assert_eq!(rep("(< 1 2 3)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(< 1 3 2)", &mut env), Ok("false".to_string()));
+        assert_eq!(rep("(<= 1 1 2)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(> 3 2 2)", &mut env), Ok("false".to_string()));
+        assert_eq!(rep("(>= 3 2 2)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(< 1)", &mut env), Ok("true".to_string()));
+        assert!(rep("(< 1 :a)", &mut env).is_err());
+        assert_eq!(rep("(= 1 1 1)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(= 1 1 2)", &mut env), Ok("false".to_string()));
+        assert_eq!(rep("(not= 1 1 2)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(not= :a :a)", &mut env), Ok("false".to_string()));
+
+        assert_eq!(rep("(max 1 3.5 2)", &mut env), Ok("3.5".to_string()));
+        assert_eq!(rep("(min 4 1/2 2)", &mut env), Ok("1/2".to_string()));
+        assert_eq!(rep("(inc 1/2)", &mut env), Ok("3/2".to_string()));
+        assert_eq!(rep("(dec 0)", &mut env), Ok("-1".to_string()));
+        assert_eq!(rep("(quot -7 2)", &mut env), Ok("-3".to_string()));
+        assert_eq!(rep("(rem -7 2)", &mut env), Ok("-1".to_string()));
+        assert_eq!(rep("(mod -7 2)", &mut env), Ok("1".to_string()));
+        assert!(rep("(mod 1 0)", &mut env).is_err());
+    }
+
+    #[test]
+    fn test_math_library() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(sqrt 16)", &mut env), Ok("4.0".to_string()));
+        assert_eq!(
+            rep("(pow 2 100)", &mut env),
+            Ok("1267650600228229401496703205376N".to_string())
+        );
+        assert_eq!(rep("(pow 2 -1)", &mut env), Ok("1/2".to_string()));
+        assert_eq!(rep("(pow 9 0.5)", &mut env), Ok("3.0".to_string()));
+        assert_eq!(rep("(exp 0)", &mut env), Ok("1.0".to_string()));
+        assert_eq!(rep("(log E)", &mut env), Ok("1.0".to_string()));
+        assert_eq!(rep("(log10 1000)", &mut env), Ok("3.0".to_string()));
+        assert_eq!(rep("(sin 0)", &mut env), Ok("0.0".to_string()));
+        assert_eq!(rep("(cos 0)", &mut env), Ok("1.0".to_string()));
+        assert_eq!(
+            rep("(= (atan2 1 1) (/ PI 4))", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(rep("(floor 7/2)", &mut env), Ok("3".to_string()));
+        assert_eq!(rep("(floor -3.5)", &mut env), Ok("-4.0".to_string()));
+        assert_eq!(rep("(ceil 3.2)", &mut env), Ok("4.0".to_string()));
+        assert_eq!(rep("(round 5/2)", &mut env), Ok("3".to_string()));
+        assert_eq!(rep("(abs -5)", &mut env), Ok("5".to_string()));
+        assert_eq!(rep("(abs -1/3)", &mut env), Ok("1/3".to_string()));
+        assert_eq!(rep("(signum -2.5)", &mut env), Ok("-1.0".to_string()));
+        assert_eq!(rep("(signum 7)", &mut env), Ok("1".to_string()));
+        assert_eq!(rep("(bit-and 12 10 8)", &mut env), Ok("8".to_string()));
+        assert_eq!(rep("(bit-or 12 3)", &mut env), Ok("15".to_string()));
+        assert_eq!(rep("(bit-xor 12 10)", &mut env), Ok("6".to_string()));
+        assert_eq!(rep("(bit-not 0)", &mut env), Ok("-1".to_string()));
+        assert_eq!(
+            rep("(bit-shift-left 1 10)", &mut env),
+            Ok("1024".to_string())
+        );
+        assert_eq!(
+            rep("(bit-shift-right -16 2)", &mut env),
+            Ok("-4".to_string())
+        );
+        assert!(rep("(bit-shift-left 1 64)", &mut env).is_err());
+        assert_eq!(
+            rep("(bit-and 1 1.0)", &mut env),
+            Err(
+                MalError::RustFunction("bit-and: argument 2 must be an integer".to_string())
+                    .at(&read("(bit-and 1 1.0)").unwrap())
+            )
+        );
+        assert_eq!(rep("(bit-and 3N 5)", &mut env), Ok("1".to_string()));
+        assert_eq!(rep("(nan? (sqrt -1))", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(nan? 1)", &mut env), Ok("false".to_string()));
+        assert_eq!(
+            rep("(infinite? (/ 1.0 0))", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(sqrt \"4\")", &mut env),
+            Err(
+                MalError::RustFunction("sqrt: argument 1 must be a number".to_string())
+                    .at(&read("(sqrt \"4\")").unwrap())
+            )
+        );
+    }
+
+    #[test]
+    fn test_string_library() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(
+            rep("(subs \"héllo\" 1 3)", &mut env),
+            Ok("\"él\"".to_string())
+        );
+        assert_eq!(
+            rep("(subs \"héllo\" 2)", &mut env),
+            Ok("\"llo\"".to_string())
+        );
+        assert!(rep("(subs \"abc\" 2 4)", &mut env).is_err());
+        assert!(rep("(subs \"abc\" 2 1)", &mut env).is_err());
+        assert_eq!(
+            rep("(split \"a,b,,c,,\" \",\")", &mut env),
+            Ok("[\"a\" \"b\" \"\" \"c\"]".to_string())
+        );
+        assert_eq!(
+            rep("(split \"a b c\" \\space 2)", &mut env),
+            Ok("[\"a\" \"b c\"]".to_string())
+        );
+        assert_eq!(
+            rep("(split \"añb\" \"\")", &mut env),
+            Ok("[\"a\" \"ñ\" \"b\"]".to_string())
+        );
+        assert_eq!(
+            rep("(split-lines \"a\\nb\\r\\nc\")", &mut env),
+            Ok("[\"a\" \"b\" \"c\"]".to_string())
+        );
+        assert_eq!(
+            rep("(join [1 \"a\" :b])", &mut env),
+            Ok("\"1a:b\"".to_string())
+        );
+        assert_eq!(
+            rep("(join \", \" (range 3))", &mut env),
+            Ok("\"0, 1, 2\"".to_string())
+        );
+        assert_eq!(
+            rep("(replace \"a-b-c\" \"-\" \"+\")", &mut env),
+            Ok("\"a+b+c\"".to_string())
+        );
+        assert_eq!(
+            rep("(replace-first \"a-b-c\" \\- \\+)", &mut env),
+            Ok("\"a+b-c\"".to_string())
+        );
+        assert_eq!(
+            rep("(upper-case \"straße\")", &mut env),
+            Ok("\"STRASSE\"".to_string())
+        );
+        assert_eq!(
+            rep("(lower-case \"ÀB\")", &mut env),
+            Ok("\"àb\"".to_string())
+        );
+        assert_eq!(
+            rep("(capitalize \"éCOLE\")", &mut env),
+            Ok("\"École\"".to_string())
+        );
+        assert_eq!(
+            rep("(trim \"\\t a b \\n\")", &mut env),
+            Ok("\"a b\"".to_string())
+        );
+        assert_eq!(rep("(triml \"  a \")", &mut env), Ok("\"a \"".to_string()));
+        assert_eq!(rep("(trimr \"  a \")", &mut env), Ok("\"  a\"".to_string()));
+        assert_eq!(
+            rep("(starts-with? \"hello\" \"he\")", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(ends-with? \"hello\" \\o)", &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep("(includes? \"hello\" \"xl\")", &mut env),
+            Ok("false".to_string())
+        );
+        assert_eq!(
+            rep("(index-of \"añbñ\" \\ñ)", &mut env),
+            Ok("1".to_string())
+        );
+        assert_eq!(
+            rep("(index-of \"añbñ\" \\ñ 2)", &mut env),
+            Ok("3".to_string())
+        );
+        assert_eq!(
+            rep("(index-of \"abc\" \"x\")", &mut env),
+            Ok("nil".to_string())
+        );
+        assert_eq!(
+            rep("(last-index-of \"añbñ\" \"ñ\")", &mut env),
+            Ok("3".to_string())
+        );
+        assert_eq!(
+            rep("(last-index-of \"añbñ\" \"ñ\" 2)", &mut env),
+            Ok("1".to_string())
+        );
+        assert_eq!(
+            rep("(index-of \"aaa\" \"aa\" 1)", &mut env),
+            Ok("1".to_string())
+        );
+        assert_eq!(
+            rep("(index-of \"ñññ\" \"ññ\" 1)", &mut env),
+            Ok("1".to_string())
+        );
+        assert_eq!(
+            rep("(last-index-of \"aaa\" \"aa\")", &mut env),
+            Ok("1".to_string())
+        );
+        assert_eq!(
+            rep("(last-index-of \"ñññ\" \"ññ\" 0)", &mut env),
+            Ok("0".to_string())
+        );
+        assert_eq!(
+            rep("(last-index-of \"añbñ\" \"bñ\" 1)", &mut env),
+            Ok("nil".to_string())
+        );
+        assert_eq!(rep("(blank? \" \\t\")", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(blank? nil)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(blank? \" a\")", &mut env), Ok("false".to_string()));
+        assert_eq!(
+            rep("(reverse-str \"añb\")", &mut env),
+            Ok("\"bña\"".to_string())
+        );
+        assert_eq!(
+            rep("(pad-left \"7\" 3 \"0\")", &mut env),
+            Ok("\"007\"".to_string())
+        );
+        assert_eq!(
+            rep("(pad-right \"ñ\" 3)", &mut env),
+            Ok("\"ñ  \"".to_string())
+        );
+        assert_eq!(
+            rep("(pad-left \"abcd\" 3)", &mut env),
+            Ok("\"abcd\"".to_string())
+        );
+        assert!(rep("(upper-case :a)", &mut env).is_err());
+    }
+
+    #[test]
+    fn test_regexes() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(
+            rep(r#"#"a\d+\"b""#, &mut env),
+            Ok(r#"#"a\d+\"b""#.to_string())
+        );
+        rep(r#"(def! re (re-pattern "a\\\"b\\\\\""))"#, &mut env).unwrap();
+        assert_eq!(rep("re", &mut env), Ok(r#"#"a\"b\\\"""#.to_string()));
+        assert_eq!(
+            rep(
+                "(= (pr-str re) (pr-str (read-string (pr-str re))))",
+                &mut env
+            ),
+            Ok("true".to_string())
+        );
+        assert_eq!(
+            rep(
+                r#"(re-find (read-string (pr-str re)) "xa\"b\\\"")"#,
+                &mut env
+            ),
+            Ok(r#""a\"b\\\"""#.to_string())
+        );
+        assert_eq!(
+            rep(r#"(= #"a+" (re-pattern "a+"))"#, &mut env),
+            Ok("true".to_string())
+        );
+        assert_eq!(rep(r#"(regex? #"a")"#, &mut env), Ok("true".to_string()));
+        assert!(rep(r#"(re-pattern "(")"#, &mut env).is_err());
+        assert!(rep(r#"#"(""#, &mut env).is_err());
+        assert_eq!(
+            rep(r#"(re-find #"\d+" "ab 12 34")"#, &mut env),
+            Ok(r#""12""#.to_string())
+        );
+        assert_eq!(
+            rep(r#"(re-find #"(\w)(\d)?" "a")"#, &mut env),
+            Ok(r#"["a" "a" nil]"#.to_string())
+        );
+        assert_eq!(
+            rep(r#"(re-find #"\d" "abc")"#, &mut env),
+            Ok("nil".to_string())
+        );
+        assert_eq!(
+            rep(r#"(re-matches #"a|ab" "ab")"#, &mut env),
+            Ok(r#""ab""#.to_string())
+        );
+        assert_eq!(
+            rep(r#"(re-matches #"\d+" "12a")"#, &mut env),
+            Ok("nil".to_string())
+        );
+        assert_eq!(
+            rep(r#"(re-seq #"(\w)=(\d)" "a=1 b=2")"#, &mut env),
+            Ok(r#"(["a=1" "a" "1"] ["b=2" "b" "2"])"#.to_string())
+        );
+        assert_eq!(
+            rep(r#"(re-seq #"\d" "abc")"#, &mut env),
+            Ok("nil".to_string())
+        );
+        assert_eq!(
+            rep(
+                r#"(re-groups #"(?P<year>\d{4})-(?P<month>\d\d)(-(?P<day>\d\d))?" "on 2024-05")"#,
+                &mut env
+            ),
+            Ok(r#"{:year "2024" :month "05" :day nil}"#.to_string())
+        );
+        assert_eq!(
+            rep(r#"(re-groups #"x" "abc")"#, &mut env),
+            Ok("nil".to_string())
+        );
+        assert_eq!(
+            rep(r#"(split "a1b22c333" #"\d+")"#, &mut env),
+            Ok(r#"["a" "b" "c"]"#.to_string())
+        );
+        assert_eq!(
+            rep(r#"(split "a1b22c333" #"\d+" 2)"#, &mut env),
+            Ok(r#"["a" "b22c333"]"#.to_string())
+        );
+        assert_eq!(
+            rep(r#"(replace "a1b22" #"(\d+)" "<$1>")"#, &mut env),
+            Ok(r#""a<1>b<22>""#.to_string())
+        );
+        assert_eq!(
+            rep(
+                r#"(replace "a1b22" #"\d+" (fn* [m] (str (count m))))"#,
+                &mut env
+            ),
+            Ok(r#""a1b2""#.to_string())
+        );
+        assert_eq!(
+            rep(
+                r#"(replace-first "k=v x=y" #"(\w)=(\w)" (fn* [m] (str (nth m 2) "=" (nth m 1))))"#,
+                &mut env
+            ),
+            Ok(r#""v=k x=y""#.to_string())
+        );
+        assert!(rep(r#"(replace "a" #"a" (fn* [m] 1))"#, &mut env).is_err());
+        rep("(def! calls (atom 0))", &mut env).unwrap();
+        assert!(rep(
+            r#"(replace "aaa" #"a" (fn* [m] (do (swap! calls inc) (throw "stop"))))"#,
+            &mut env
+        )
+        .is_err());
+        assert_eq!(rep("@calls", &mut env), Ok("1".to_string()));
+        assert!(rep(r#"(re-find "a" "a")"#, &mut env).is_err());
+    }
+
+    #[test]
+    fn test_unicode_strings() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(count \"héllo\")", &mut env), Ok("5".to_string()));
+        assert_eq!(rep("(nth \"héllo\" 1)", &mut env), Ok("\\é".to_string()));
+        assert_eq!(
+            rep("(subs \"日本語\" 1 2)", &mut env),
+            Ok("\"本\"".to_string())
+        );
+        assert_eq!(rep("(seq \"añ\")", &mut env), Ok("(\\a \\ñ)".to_string()));
+        assert_eq!(rep("(count \"👍🏽e\u{301}\")", &mut env), Ok("4".to_string()));
+        assert_eq!(
+            rep("(grapheme-count \"👍🏽e\u{301}\")", &mut env),
+            Ok("2".to_string())
+        );
+        assert_eq!(
+            rep("(graphemes \"a👍🏽e\u{301}\")", &mut env),
+            Ok("[\"a\" \"👍🏽\" \"e\u{301}\"]".to_string())
+        );
+        assert_eq!(rep("(graphemes \"\")", &mut env), Ok("[]".to_string()));
+        assert!(rep("(grapheme-count 1)", &mut env).is_err());
+    }
 }
@@ -0,0 +1,391 @@
+use crate::interpreter::Arity;
+use crate::number::Number;
+use crate::types::MalValueType::{
+    False, Float, Int, Keyword, List, Map, Nil, Set, SortedMap, SortedSet, Str, True, Vector,
+};
+use crate::types::{MalError, MalList, MalMap, MalResult, MalValue, MalVector};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Converts a mal value into a Rust value, failing if the value has the wrong
+/// type. `'a` lets conversions such as `&str` borrow from the value.
+pub trait FromMal<'a>: Sized {
+    fn from_mal(value: &'a MalValue) -> Result<Self, MalError>;
+}
+
+pub trait IntoMal {
+    fn into_mal(self) -> MalValue;
+}
+
+fn expected(description: &str) -> MalError {
+    MalError::RustFunction(format!("Expected {}", description))
+}
+
+impl<'a> FromMal<'a> for MalValue {
+    fn from_mal(value: &'a MalValue) -> Result<Self, MalError> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoMal for MalValue {
+    fn into_mal(self) -> MalValue {
+        self
+    }
+}
+
+impl IntoMal for () {
+    fn into_mal(self) -> MalValue {
+        MalValue::nil()
+    }
+}
+
+impl<'a> FromMal<'a> for i64 {
+    fn from_mal(value: &'a MalValue) -> Result<Self, MalError> {
+        match *value.mal_type {
+            Int(val) => Ok(val),
+            _ => Err(expected("an integer")),
+        }
+    }
+}
+
+impl IntoMal for i64 {
+    fn into_mal(self) -> MalValue {
+        MalValue::new(Int(self))
+    }
+}
+
+/// Accepts any number, converting integers and ratios to the nearest float.
+impl<'a> FromMal<'a> for f64 {
+    fn from_mal(value: &'a MalValue) -> Result<Self, MalError> {
+        Number::from_value(value)
+            .map(|number| number.to_f64())
+            .ok_or_else(|| expected("a number"))
+    }
+}
+
+impl IntoMal for f64 {
+    fn into_mal(self) -> MalValue {
+        MalValue::new(Float(self))
+    }
+}
+
+impl<'a> FromMal<'a> for bool {
+    fn from_mal(value: &'a MalValue) -> Result<Self, MalError> {
+        match *value.mal_type {
+            True => Ok(true),
+            False => Ok(false),
+            _ => Err(expected("a boolean")),
+        }
+    }
+}
+
+impl IntoMal for bool {
+    fn into_mal(self) -> MalValue {
+        MalValue::new_boolean(self)
+    }
+}
+
+impl<'a> FromMal<'a> for &'a str {
+    fn from_mal(value: &'a MalValue) -> Result<Self, MalError> {
+        match *value.mal_type {
+            Str(ref s) => Ok(s),
+            _ => Err(expected("a string")),
+        }
+    }
+}
+
+impl<'a> FromMal<'a> for String {
+    fn from_mal(value: &'a MalValue) -> Result<Self, MalError> {
+        <&str>::from_mal(value).map(str::to_string)
+    }
+}
+
+impl IntoMal for &str {
+    fn into_mal(self) -> MalValue {
+        MalValue::new(Str(self.to_string()))
+    }
+}
+
+impl IntoMal for String {
+    fn into_mal(self) -> MalValue {
+        MalValue::new(Str(self))
+    }
+}
+
+/// Nil converts to `None`, and any other value to `Some`.
+impl<'a, T: FromMal<'a>> FromMal<'a> for Option<T> {
+    fn from_mal(value: &'a MalValue) -> Result<Self, MalError> {
+        match *value.mal_type {
+            Nil => Ok(None),
+            _ => T::from_mal(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoMal> IntoMal for Option<T> {
+    fn into_mal(self) -> MalValue {
+        self.map_or_else(MalValue::nil, IntoMal::into_mal)
+    }
+}
+
+/// Accepts lists, vectors, sets and nil. Lazy sequences must be realized into
+/// one of these first, since their elements cannot be borrowed.
+impl<'a, T: FromMal<'a>> FromMal<'a> for Vec<T> {
+    fn from_mal(value: &'a MalValue) -> Result<Self, MalError> {
+        match *value.mal_type {
+            List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) => {
+                vec.iter().map(T::from_mal).collect()
+            }
+            Set(ref mal_set) => mal_set.iter().map(T::from_mal).collect(),
+            SortedSet(ref sorted_set) => sorted_set.iter().map(T::from_mal).collect(),
+            Nil => Ok(Vec::new()),
+            _ => Err(expected("a list, a vector or a set")),
+        }
+    }
+}
+
+impl<T: IntoMal> IntoMal for Vec<T> {
+    fn into_mal(self) -> MalValue {
+        MalValue::new_vector(self.into_iter().map(IntoMal::into_mal).collect::<Vec<_>>())
+    }
+}
+
+/// Keys may be strings or keywords, which are taken by name.
+impl<'a, T: FromMal<'a>> FromMal<'a> for HashMap<String, T> {
+    fn from_mal(value: &'a MalValue) -> Result<Self, MalError> {
+        let entry = |(key, val): (&'a MalValue, &'a MalValue)| match *key.mal_type {
+            Str(ref name) | Keyword(ref name) => Ok((name.clone(), T::from_mal(val)?)),
+            _ => Err(expected("map keys to be strings or keywords")),
+        };
+
+        match *value.mal_type {
+            Map(ref mal_map) => mal_map.iter().map(entry).collect(),
+            SortedMap(ref sorted_map) => sorted_map.iter().map(entry).collect(),
+            Nil => Ok(HashMap::new()),
+            _ => Err(expected("a map")),
+        }
+    }
+}
+
+/// Keys become strings. They are inserted in sorted order, since a
+/// `HashMap` iterates in a different order from run to run and mal maps keep
+/// their insertion order.
+impl<T: IntoMal> IntoMal for HashMap<String, T> {
+    fn into_mal(self) -> MalValue {
+        let mut entries: Vec<_> = self.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let arguments: Vec<_> = entries
+            .into_iter()
+            .flat_map(|(key, val)| vec![key.into_mal(), val.into_mal()])
+            .collect();
+
+        MalValue::new_map(MalMap::from_arguments(&arguments).expect("Map keys are distinct"))
+    }
+}
+
+/// Tuples convert from and to lists or vectors of the same length.
+macro_rules! impl_tuple_conversions {
+    ($len:expr; $($name:ident),+) => {
+        impl<'a, $($name: FromMal<'a>),+> FromMal<'a> for ($($name,)+) {
+            fn from_mal(value: &'a MalValue) -> Result<Self, MalError> {
+                match *value.mal_type {
+                    List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. })
+                        if vec.len() == $len =>
+                    {
+                        let mut elements = vec.iter();
+                        Ok(($($name::from_mal(elements.next().unwrap())?,)+))
+                    }
+                    _ => Err(expected(&format!("a list or a vector of {} elements", $len))),
+                }
+            }
+        }
+
+        impl<$($name: IntoMal),+> IntoMal for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn into_mal(self) -> MalValue {
+                let ($($name,)+) = self;
+                MalValue::new_vector(vec![$($name.into_mal()),+])
+            }
+        }
+    };
+}
+
+impl_tuple_conversions!(1; A);
+impl_tuple_conversions!(2; A, B);
+impl_tuple_conversions!(3; A, B, C);
+impl_tuple_conversions!(4; A, B, C, D);
+
+/// A Rust function that can be called from mal with its arguments converted
+/// by `FromMal` and its result by `IntoMal`. It is implemented for functions
+/// of up to 6 arguments that return a `Result` whose error can be displayed.
+///
+/// Arguments must own their data (`String` rather than `&str`), since they are
+/// converted anew for each call. Calling it with other than `ARITY` arguments
+/// is an error.
+pub trait NativeFn<Args>: 'static {
+    const ARITY: usize;
+
+    fn call_native(&self, name: &str, args: &[MalValue]) -> MalResult;
+}
+
+fn argument_error(name: &str, index: usize, err: MalError) -> MalError {
+    match err {
+        MalError::RustFunction(message) => {
+            MalError::RustFunction(format!("{}: argument {}: {}", name, index + 1, message))
+        }
+        other => other,
+    }
+}
+
+macro_rules! impl_native_fn {
+    ($arity:expr; $($arg:ident),*) => {
+        impl<Func, Res, Err, $($arg),*> NativeFn<($($arg,)*)> for Func
+        where
+            Func: Fn($($arg),*) -> Result<Res, Err> + 'static,
+            Res: IntoMal,
+            Err: fmt::Display,
+            $($arg: for<'a> FromMal<'a>),*
+        {
+            const ARITY: usize = $arity;
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call_native(&self, name: &str, args: &[MalValue]) -> MalResult {
+                Arity::Exactly($arity).check(name, args)?;
+
+                let mut args = args.iter().enumerate();
+                $(
+                    let $arg = {
+                        let (index, arg) = args.next().unwrap();
+                        $arg::from_mal(arg).map_err(|err| argument_error(name, index, err))?
+                    };
+                )*
+
+                self($($arg),*)
+                    .map(IntoMal::into_mal)
+                    .map_err(|err| MalError::RustFunction(format!("{}: {}", name, err)))
+            }
+        }
+    };
+}
+
+impl_native_fn!(0;);
+impl_native_fn!(1; A);
+impl_native_fn!(2; A, B);
+impl_native_fn!(3; A, B, C);
+impl_native_fn!(4; A, B, C, D);
+impl_native_fn!(5; A, B, C, D, E);
+impl_native_fn!(6; A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(n: i64) -> MalValue {
+        MalValue::new(Int(n))
+    }
+
+    fn string(s: &str) -> MalValue {
+        MalValue::new(Str(s.to_string()))
+    }
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(i64::from_mal(&int(3)), Ok(3));
+        assert!(i64::from_mal(&string("3")).is_err());
+        assert_eq!(f64::from_mal(&int(3)), Ok(3.0));
+        assert_eq!(f64::from_mal(&MalValue::new(Float(0.5))), Ok(0.5));
+        assert_eq!(bool::from_mal(&MalValue::new_boolean(true)), Ok(true));
+        assert!(bool::from_mal(&MalValue::nil()).is_err());
+        assert_eq!(<&str>::from_mal(&string("a")), Ok("a"));
+        assert_eq!(String::from_mal(&string("a")), Ok("a".to_string()));
+
+        assert_eq!(3i64.into_mal(), int(3));
+        assert_eq!(0.5.into_mal(), MalValue::new(Float(0.5)));
+        assert_eq!("a".into_mal(), string("a"));
+        assert_eq!(().into_mal(), MalValue::nil());
+    }
+
+    #[test]
+    fn test_options_and_collections() {
+        assert_eq!(Option::<i64>::from_mal(&MalValue::nil()), Ok(None));
+        assert_eq!(Option::<i64>::from_mal(&int(1)), Ok(Some(1)));
+        assert_eq!(None::<i64>.into_mal(), MalValue::nil());
+
+        let list = MalValue::new_list(vec![int(1), int(2)]);
+        assert_eq!(Vec::<i64>::from_mal(&list), Ok(vec![1, 2]));
+        assert!(Vec::<String>::from_mal(&list).is_err());
+        assert_eq!(
+            vec!["a", "b"].into_mal(),
+            MalValue::new_vector(vec![string("a"), string("b")])
+        );
+
+        let map = MalValue::new_map(
+            MalMap::from_arguments(&[MalValue::new(Keyword("a".to_string())), int(1)]).unwrap(),
+        );
+        let mut expected = HashMap::new();
+        expected.insert("a".to_string(), 1);
+        assert_eq!(HashMap::<String, i64>::from_mal(&map), Ok(expected.clone()));
+        assert_eq!(
+            expected.into_mal(),
+            MalValue::new_map(MalMap::from_arguments(&[string("a"), int(1)]).unwrap())
+        );
+
+        let unordered: HashMap<_, _> = (0..20).map(|n| (format!("k{:02}", n), n)).collect();
+        assert_eq!(
+            crate::printer::pr_str(&unordered.into_mal(), true),
+            format!(
+                "{{{}}}",
+                (0..20)
+                    .map(|n| format!("\"k{:02}\" {}", n, n))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )
+        );
+
+        assert_eq!(
+            <(i64, String)>::from_mal(&MalValue::new_vector(vec![int(1), string("x")])),
+            Ok((1, "x".to_string()))
+        );
+        assert!(<(i64, i64)>::from_mal(&list.clone()).is_ok());
+        assert!(<(i64,)>::from_mal(&list).is_err());
+        assert_eq!(
+            (1i64, true).into_mal(),
+            MalValue::new_vector(vec![int(1), MalValue::new_boolean(true)])
+        );
+    }
+
+    #[test]
+    fn test_native_fn() {
+        fn repeat_str(s: String, n: i64) -> Result<Vec<String>, String> {
+            if n < 0 {
+                return Err("count must not be negative".to_string());
+            }
+
+            Ok(vec![s; n as usize])
+        }
+
+        assert_eq!(
+            repeat_str.call_native("repeat-str", &[string("a"), int(2)]),
+            Ok(MalValue::new_vector(vec![string("a"), string("a")]))
+        );
+        assert_eq!(
+            repeat_str.call_native("repeat-str", &[string("a"), string("2")]),
+            Err(MalError::RustFunction(
+                "repeat-str: argument 2: Expected an integer".to_string()
+            ))
+        );
+        assert_eq!(
+            repeat_str.call_native("repeat-str", &[string("a"), int(-1)]),
+            Err(MalError::RustFunction(
+                "repeat-str: count must not be negative".to_string()
+            ))
+        );
+        assert_eq!(
+            repeat_str.call_native("repeat-str", &[string("a")]),
+            Err(MalError::RustFunction(
+                "repeat-str: Expected 2 arguments, got 1".to_string()
+            ))
+        );
+    }
+}
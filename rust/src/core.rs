@@ -1,16 +1,27 @@
 use crate::env::Env;
-use crate::printer::pr_str;
-use crate::reader::read_str;
+use crate::number::{self, Number};
+use crate::printer::{pr_str, try_pr_str};
+use crate::reader::{read_all, read_str_with_file};
+use crate::seq::{map_entry, Seqable};
 use crate::types::MalValueType::{
-    Atom, False, Keyword, List, MalFunc, Map, Nil, Number, RustFunc, Str, Symbol, True, Vector,
+    Atom, BigInt, Char, False, Float, Int, Keyword, LazySeq, List, MalFunc, Map, Nil, Ratio,
+    Reduced, Regex, RustFunc, Set, SortedMap, SortedSet, Str, Symbol, True, Vector,
 };
-use crate::types::{MalError, MalList, MalMap, MalResult, MalValue, MalVector};
+use crate::types::{
+    MalError, MalLazySeq, MalList, MalMap, MalRegex, MalResult, MalSet, MalSortedMap, MalSortedSet,
+    MalValue, MalVector,
+};
+use num_traits::ToPrimitive;
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::error::Error;
 use std::fs;
+use std::iter::once;
 use std::slice;
 use std::time::{SystemTime, UNIX_EPOCH};
+use unicode_segmentation::UnicodeSegmentation;
 
 pub fn ns(env: &Env) -> Vec<(&'static str, MalValue)> {
     vec![
@@ -18,6 +29,46 @@ pub fn ns(env: &Env) -> Vec<(&'static str, MalValue)> {
         ("-", MalValue::new_rust_func(subtract, env)),
         ("*", MalValue::new_rust_func(multiply, env)),
         ("/", MalValue::new_rust_func(divide, env)),
+        ("inc", MalValue::new_rust_func(inc, env)),
+        ("dec", MalValue::new_rust_func(dec, env)),
+        ("quot", MalValue::new_rust_func(quot, env)),
+        ("rem", MalValue::new_rust_func(rem, env)),
+        ("mod", MalValue::new_rust_func(modulo, env)),
+        ("max", MalValue::new_rust_func(max, env)),
+        ("min", MalValue::new_rust_func(min, env)),
+        ("sqrt", MalValue::new_rust_func(sqrt, env)),
+        ("pow", MalValue::new_rust_func(pow, env)),
+        ("exp", MalValue::new_rust_func(exp, env)),
+        ("log", MalValue::new_rust_func(log, env)),
+        ("log10", MalValue::new_rust_func(log10, env)),
+        ("sin", MalValue::new_rust_func(sin, env)),
+        ("cos", MalValue::new_rust_func(cos, env)),
+        ("tan", MalValue::new_rust_func(tan, env)),
+        ("asin", MalValue::new_rust_func(asin, env)),
+        ("acos", MalValue::new_rust_func(acos, env)),
+        ("atan", MalValue::new_rust_func(atan, env)),
+        ("atan2", MalValue::new_rust_func(atan2, env)),
+        ("floor", MalValue::new_rust_func(floor, env)),
+        ("ceil", MalValue::new_rust_func(ceil, env)),
+        ("round", MalValue::new_rust_func(round, env)),
+        ("abs", MalValue::new_rust_func(abs, env)),
+        ("signum", MalValue::new_rust_func(signum, env)),
+        ("PI", MalValue::new(Float(std::f64::consts::PI))),
+        ("E", MalValue::new(Float(std::f64::consts::E))),
+        ("bit-and", MalValue::new_rust_func(bit_and, env)),
+        ("bit-or", MalValue::new_rust_func(bit_or, env)),
+        ("bit-xor", MalValue::new_rust_func(bit_xor, env)),
+        ("bit-not", MalValue::new_rust_func(bit_not, env)),
+        (
+            "bit-shift-left",
+            MalValue::new_rust_func(bit_shift_left, env),
+        ),
+        (
+            "bit-shift-right",
+            MalValue::new_rust_func(bit_shift_right, env),
+        ),
+        ("nan?", MalValue::new_rust_func(is_nan, env)),
+        ("infinite?", MalValue::new_rust_func(is_infinite, env)),
         ("prn", MalValue::new_rust_func(prn, env)),
         ("println", MalValue::new_rust_func(mal_println, env)),
         ("pr-str", MalValue::new_rust_func(mal_pr_str, env)),
@@ -33,12 +84,14 @@ pub fn ns(env: &Env) -> Vec<(&'static str, MalValue)> {
         ("rest", MalValue::new_rust_func(rest, env)),
         ("conj", MalValue::new_rust_func(conj, env)),
         ("=", MalValue::new_rust_func(equals, env)),
+        ("not=", MalValue::new_rust_func(not_equals, env)),
         ("<", MalValue::new_rust_func(lt, env)),
         ("<=", MalValue::new_rust_func(lte, env)),
         (">", MalValue::new_rust_func(gt, env)),
         (">=", MalValue::new_rust_func(gte, env)),
         ("read-string", MalValue::new_rust_func(read_string, env)),
         ("slurp", MalValue::new_rust_func(slurp, env)),
+        ("load-file", MalValue::new_rust_func(load_file, env)),
         ("eval", MalValue::new_rust_func(mal_eval, env)),
         ("atom", MalValue::new_rust_func(atom, env)),
         ("atom?", MalValue::new_rust_func(is_atom, env)),
@@ -64,6 +117,21 @@ pub fn ns(env: &Env) -> Vec<(&'static str, MalValue)> {
         ("dissoc", MalValue::new_rust_func(dissoc, env)),
         ("get", MalValue::new_rust_func(get, env)),
         ("contains?", MalValue::new_rust_func(contains, env)),
+        ("hash-set", MalValue::new_rust_func(hash_set, env)),
+        ("set", MalValue::new_rust_func(set, env)),
+        ("set?", MalValue::new_rust_func(is_set, env)),
+        ("disj", MalValue::new_rust_func(disj, env)),
+        ("union", MalValue::new_rust_func(union, env)),
+        ("intersection", MalValue::new_rust_func(intersection, env)),
+        ("difference", MalValue::new_rust_func(difference, env)),
+        ("sorted-map", MalValue::new_rust_func(sorted_map, env)),
+        ("sorted-map-by", MalValue::new_rust_func(sorted_map_by, env)),
+        ("sorted-set", MalValue::new_rust_func(sorted_set, env)),
+        ("sorted-set-by", MalValue::new_rust_func(sorted_set_by, env)),
+        ("sorted?", MalValue::new_rust_func(is_sorted, env)),
+        ("compare", MalValue::new_rust_func(mal_compare, env)),
+        ("subseq", MalValue::new_rust_func(subseq, env)),
+        ("rsubseq", MalValue::new_rust_func(rsubseq, env)),
         ("keys", MalValue::new_rust_func(keys, env)),
         ("vals", MalValue::new_rust_func(vals, env)),
         ("readline", MalValue::new_rust_func(readline, env)),
@@ -71,38 +139,87 @@ pub fn ns(env: &Env) -> Vec<(&'static str, MalValue)> {
         ("with-meta", MalValue::new_rust_func(with_meta, env)),
         ("string?", MalValue::new_rust_func(is_string, env)),
         ("number?", MalValue::new_rust_func(is_number, env)),
+        ("char?", MalValue::new_rust_func(is_char, env)),
+        ("char", MalValue::new_rust_func(char, env)),
+        ("subs", MalValue::new_rust_func(subs, env)),
+        ("split", MalValue::new_rust_func(split, env)),
+        ("split-lines", MalValue::new_rust_func(split_lines, env)),
+        ("graphemes", MalValue::new_rust_func(graphemes, env)),
+        (
+            "grapheme-count",
+            MalValue::new_rust_func(grapheme_count, env),
+        ),
+        ("join", MalValue::new_rust_func(join, env)),
+        ("replace", MalValue::new_rust_func(replace, env)),
+        ("replace-first", MalValue::new_rust_func(replace_first, env)),
+        ("upper-case", MalValue::new_rust_func(upper_case, env)),
+        ("lower-case", MalValue::new_rust_func(lower_case, env)),
+        ("capitalize", MalValue::new_rust_func(capitalize, env)),
+        ("trim", MalValue::new_rust_func(trim, env)),
+        ("triml", MalValue::new_rust_func(triml, env)),
+        ("trimr", MalValue::new_rust_func(trimr, env)),
+        ("starts-with?", MalValue::new_rust_func(starts_with, env)),
+        ("ends-with?", MalValue::new_rust_func(ends_with, env)),
+        ("includes?", MalValue::new_rust_func(includes, env)),
+        ("index-of", MalValue::new_rust_func(index_of, env)),
+        ("last-index-of", MalValue::new_rust_func(last_index_of, env)),
+        ("blank?", MalValue::new_rust_func(is_blank, env)),
+        ("reverse-str", MalValue::new_rust_func(reverse_str, env)),
+        ("pad-left", MalValue::new_rust_func(pad_left, env)),
+        ("pad-right", MalValue::new_rust_func(pad_right, env)),
+        ("regex?", MalValue::new_rust_func(is_regex, env)),
+        ("re-pattern", MalValue::new_rust_func(re_pattern, env)),
+        ("re-find", MalValue::new_rust_func(re_find, env)),
+        ("re-matches", MalValue::new_rust_func(re_matches, env)),
+        ("re-seq", MalValue::new_rust_func(re_seq, env)),
+        ("re-groups", MalValue::new_rust_func(re_groups, env)),
+        ("int", MalValue::new_rust_func(int, env)),
+        ("bigint?", MalValue::new_rust_func(is_bigint, env)),
+        ("numerator", MalValue::new_rust_func(numerator, env)),
+        ("denominator", MalValue::new_rust_func(denominator, env)),
+        ("rationalize", MalValue::new_rust_func(rationalize, env)),
         ("fn?", MalValue::new_rust_func(is_fn, env)),
         ("macro?", MalValue::new_rust_func(is_macro, env)),
         ("time-ms", MalValue::new_rust_func(time_ms, env)),
         ("seq", MalValue::new_rust_func(seq, env)),
+        ("filter", MalValue::new_rust_func(filter, env)),
+        ("take", MalValue::new_rust_func(take, env)),
+        ("drop", MalValue::new_rust_func(drop, env)),
+        ("range", MalValue::new_rust_func(range, env)),
+        ("iterate", MalValue::new_rust_func(iterate, env)),
+        ("repeat", MalValue::new_rust_func(repeat, env)),
+        ("cycle", MalValue::new_rust_func(cycle, env)),
+        ("realized?", MalValue::new_rust_func(is_realized, env)),
+        ("remove", MalValue::new_rust_func(remove, env)),
+        ("take-while", MalValue::new_rust_func(take_while, env)),
+        ("drop-while", MalValue::new_rust_func(drop_while, env)),
+        ("partition", MalValue::new_rust_func(partition, env)),
+        ("interleave", MalValue::new_rust_func(interleave, env)),
+        ("distinct", MalValue::new_rust_func(distinct, env)),
+        ("mapcat", MalValue::new_rust_func(mapcat, env)),
+        ("reduce", MalValue::new_rust_func(reduce, env)),
+        ("reduced", MalValue::new_rust_func(reduced, env)),
+        ("reduced?", MalValue::new_rust_func(is_reduced, env)),
+        ("reverse", MalValue::new_rust_func(reverse, env)),
+        ("sort", MalValue::new_rust_func(sort, env)),
+        ("sort-by", MalValue::new_rust_func(sort_by, env)),
+        ("group-by", MalValue::new_rust_func(group_by, env)),
+        ("frequencies", MalValue::new_rust_func(frequencies, env)),
+        ("some", MalValue::new_rust_func(some, env)),
+        ("every?", MalValue::new_rust_func(every, env)),
+        ("into", MalValue::new_rust_func(into, env)),
     ]
 }
 
-static mut EVAL_FUNC: fn(ast: &MalValue, env: &mut Env) -> MalResult = dummy_eval;
-
-fn dummy_eval(_: &MalValue, _: &mut Env) -> MalResult {
-    panic!("core EVAL_FUNC was not set. You must call core::set_eval_func().")
-}
-
-pub fn set_eval_func(func: fn(ast: &MalValue, env: &mut Env) -> MalResult) {
-    unsafe {
-        EVAL_FUNC = func;
-    }
-}
-
-fn core_eval(ast: &MalValue, env: &mut Env) -> MalResult {
-    unsafe { EVAL_FUNC(ast, env) }
-}
-
-fn core_apply(function: &MalValue, args: &[MalValue], _env: &mut Env) -> MalResult {
+pub(crate) fn core_apply(function: &MalValue, args: &[MalValue]) -> MalResult {
     match *function.mal_type {
         RustFunc(ref rust_function) => {
             Ok((rust_function.func)(&args, &mut rust_function.env.clone())?)
         }
         MalFunc(ref mal_func) => {
             let mut func_env =
-                Env::with_binds(Some(&mal_func.outer_env), &mal_func.parameters, &args)?;
-            core_eval(&mal_func.body, &mut func_env)
+                Env::with_binds(Some(&mal_func.outer_env), &mal_func.parameters, args)?;
+            func_env.eval(&mal_func.body)
         }
         _ => Err(MalError::RustFunction("Expected function.".to_string())),
     }
@@ -134,39 +251,330 @@ fn arg_count_gte(args: &[MalValue], min_args: usize) -> Result<(), MalError> {
     Ok(())
 }
 
-fn get_number_arg(arg: &MalValue) -> Result<f64, MalError> {
-    if let Number(n) = *arg.mal_type {
-        Ok(n)
-    } else {
-        Err(MalError::RustFunction(
-            "Argument must be a number".to_string(),
-        ))
+/// Accepts `Int`s and `BigInt`s small enough to be an `Int`. Errors name the
+/// builtin and the position of the argument, `index` being 0-based.
+fn get_int_arg(name: &str, index: usize, arg: &MalValue) -> Result<i64, MalError> {
+    match Number::from_value(arg) {
+        Some(Number::Int(n)) => Ok(n),
+        Some(Number::BigInt(n)) => n.to_i64().ok_or_else(|| {
+            MalError::RustFunction(format!("{}: argument {} is too large", name, index + 1))
+        }),
+        _ => Err(MalError::RustFunction(format!(
+            "{}: argument {} must be an integer",
+            name,
+            index + 1
+        ))),
     }
 }
 
 fn add(args: &[MalValue], _env: &mut Env) -> MalResult {
-    eval_arithmetic_operation(args, |a, b| a + b)
+    eval_arithmetic_operation("+", args, Some(Number::Int(0)), Number::add)
 }
 
 fn subtract(args: &[MalValue], _env: &mut Env) -> MalResult {
-    eval_arithmetic_operation(args, |a, b| a - b)
+    arg_count_gte(args, 1)?;
+
+    let init = if args.len() == 1 {
+        Some(Number::Int(0))
+    } else {
+        None
+    };
+
+    eval_arithmetic_operation("-", args, init, Number::sub)
 }
 
 fn multiply(args: &[MalValue], _env: &mut Env) -> MalResult {
-    eval_arithmetic_operation(args, |a, b| a * b)
+    eval_arithmetic_operation("*", args, Some(Number::Int(1)), Number::mul)
 }
 
 fn divide(args: &[MalValue], _env: &mut Env) -> MalResult {
-    eval_arithmetic_operation(args, |a, b| a / b)
+    arg_count_gte(args, 1)?;
+
+    let init = if args.len() == 1 {
+        Some(Number::Int(1))
+    } else {
+        None
+    };
+
+    eval_arithmetic_operation("/", args, init, Number::div)
+}
+
+/// Folds `op` over the arguments from left to right, starting with `init` if
+/// given and with the first argument otherwise. At each step both operands
+/// are promoted to the wider of their two numeric types (see `Number`).
+fn eval_arithmetic_operation(
+    name: &str,
+    args: &[MalValue],
+    init: Option<Number>,
+    op: fn(&Number, &Number) -> Result<Number, MalError>,
+) -> MalResult {
+    let mut numbers = args
+        .iter()
+        .enumerate()
+        .map(|(index, arg)| Number::from_arg(name, index, arg));
+
+    let mut result = match init {
+        Some(init) => init,
+        None => numbers.next().unwrap()?,
+    };
+
+    for number in numbers {
+        result = op(&result, &number?)?;
+    }
+
+    Ok(result.into_value())
+}
+
+fn eval_binary_operation(
+    name: &str,
+    args: &[MalValue],
+    op: fn(&Number, &Number) -> Result<Number, MalError>,
+) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    let arg_1 = Number::from_arg(name, 0, &args[0])?;
+    let arg_2 = Number::from_arg(name, 1, &args[1])?;
+
+    Ok(op(&arg_1, &arg_2)?.into_value())
+}
+
+fn inc(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(Number::from_arg("inc", 0, &args[0])?
+        .add(&Number::Int(1))?
+        .into_value())
+}
+
+fn dec(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(Number::from_arg("dec", 0, &args[0])?
+        .sub(&Number::Int(1))?
+        .into_value())
+}
+
+fn quot(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_binary_operation("quot", args, Number::quot)
+}
+
+fn rem(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_binary_operation("rem", args, Number::rem)
+}
+
+fn modulo(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_binary_operation("mod", args, Number::modulo)
+}
+
+fn float_function(name: &str, args: &[MalValue], f: fn(f64) -> f64) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    let number = Number::from_arg(name, 0, &args[0])?;
+
+    Ok(MalValue::new(Float(f(number.to_f64()))))
+}
+
+fn sqrt(args: &[MalValue], _env: &mut Env) -> MalResult {
+    float_function("sqrt", args, f64::sqrt)
+}
+
+fn pow(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_binary_operation("pow", args, Number::pow)
+}
+
+fn exp(args: &[MalValue], _env: &mut Env) -> MalResult {
+    float_function("exp", args, f64::exp)
+}
+
+fn log(args: &[MalValue], _env: &mut Env) -> MalResult {
+    float_function("log", args, f64::ln)
+}
+
+fn log10(args: &[MalValue], _env: &mut Env) -> MalResult {
+    float_function("log10", args, f64::log10)
+}
+
+fn sin(args: &[MalValue], _env: &mut Env) -> MalResult {
+    float_function("sin", args, f64::sin)
+}
+
+fn cos(args: &[MalValue], _env: &mut Env) -> MalResult {
+    float_function("cos", args, f64::cos)
 }
 
-fn eval_arithmetic_operation(args: &[MalValue], op: fn(f64, f64) -> f64) -> MalResult {
+fn tan(args: &[MalValue], _env: &mut Env) -> MalResult {
+    float_function("tan", args, f64::tan)
+}
+
+fn asin(args: &[MalValue], _env: &mut Env) -> MalResult {
+    float_function("asin", args, f64::asin)
+}
+
+fn acos(args: &[MalValue], _env: &mut Env) -> MalResult {
+    float_function("acos", args, f64::acos)
+}
+
+fn atan(args: &[MalValue], _env: &mut Env) -> MalResult {
+    float_function("atan", args, f64::atan)
+}
+
+fn atan2(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    let y = Number::from_arg("atan2", 0, &args[0])?.to_f64();
+    let x = Number::from_arg("atan2", 1, &args[1])?.to_f64();
+
+    Ok(MalValue::new(Float(y.atan2(x))))
+}
+
+fn floor(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(Number::from_arg("floor", 0, &args[0])?.floor().into_value())
+}
+
+fn ceil(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(Number::from_arg("ceil", 0, &args[0])?.ceil().into_value())
+}
+
+fn round(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(Number::from_arg("round", 0, &args[0])?.round().into_value())
+}
+
+fn abs(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(Number::from_arg("abs", 0, &args[0])?.abs()?.into_value())
+}
+
+fn signum(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(Number::from_arg("signum", 0, &args[0])?
+        .signum()
+        .into_value())
+}
+
+fn eval_bitwise_operation(name: &str, args: &[MalValue], op: fn(i64, i64) -> i64) -> MalResult {
+    arg_count_gte(args, 2)?;
+
+    let mut result = get_int_arg(name, 0, &args[0])?;
+    for (index, arg) in args.iter().enumerate().skip(1) {
+        result = op(result, get_int_arg(name, index, arg)?);
+    }
+
+    Ok(MalValue::new(Int(result)))
+}
+
+fn bit_and(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_bitwise_operation("bit-and", args, |a, b| a & b)
+}
+
+fn bit_or(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_bitwise_operation("bit-or", args, |a, b| a | b)
+}
+
+fn bit_xor(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_bitwise_operation("bit-xor", args, |a, b| a ^ b)
+}
+
+fn bit_not(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new(Int(!get_int_arg("bit-not", 0, &args[0])?)))
+}
+
+fn eval_shift(name: &str, args: &[MalValue], op: fn(i64, u32) -> i64) -> MalResult {
     arg_count_eq(args, 2)?;
 
-    let arg_1 = get_number_arg(&args[0])?;
-    let arg_2 = get_number_arg(&args[1])?;
+    let value = get_int_arg(name, 0, &args[0])?;
+    let shift = get_int_arg(name, 1, &args[1])?;
+
+    if !(0..64).contains(&shift) {
+        return Err(MalError::RustFunction(format!(
+            "Shift amount must be between 0 and 63, got {}",
+            shift
+        )));
+    }
+
+    Ok(MalValue::new(Int(op(value, shift as u32))))
+}
+
+fn bit_shift_left(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_shift("bit-shift-left", args, |value, shift| value << shift)
+}
+
+fn bit_shift_right(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_shift("bit-shift-right", args, |value, shift| value >> shift)
+}
+
+fn is_nan(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new_boolean(matches!(
+        Number::from_arg("nan?", 0, &args[0])?,
+        Number::Float(n) if n.is_nan()
+    )))
+}
+
+fn is_infinite(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new_boolean(matches!(
+        Number::from_arg("infinite?", 0, &args[0])?,
+        Number::Float(n) if n.is_infinite()
+    )))
+}
+
+/// Returns the greatest (or least) argument as given, without converting it
+/// to a common numeric type. NaN is returned if any argument is NaN.
+fn extreme_number(name: &str, args: &[MalValue], wanted: Ordering) -> MalResult {
+    arg_count_gte(args, 1)?;
+
+    let mut result = &args[0];
+    let mut result_number = Number::from_arg(name, 0, result)?;
+
+    for (index, arg) in args.iter().enumerate().skip(1) {
+        let number = Number::from_arg(name, index, arg)?;
+
+        match number.partial_cmp(&result_number) {
+            Some(ordering) if ordering == wanted => {
+                result = arg;
+                result_number = number;
+            }
+            Some(_) => {}
+            None => return Ok(MalValue::new(Float(f64::NAN))),
+        }
+    }
+
+    Ok(result.clone())
+}
+
+fn max(args: &[MalValue], _env: &mut Env) -> MalResult {
+    extreme_number("max", args, Ordering::Greater)
+}
+
+fn min(args: &[MalValue], _env: &mut Env) -> MalResult {
+    extreme_number("min", args, Ordering::Less)
+}
+
+/// Checks that every pair of consecutive arguments satisfies `test`.
+fn compare_numbers(name: &str, args: &[MalValue], test: fn(Ordering) -> bool) -> MalResult {
+    arg_count_gte(args, 1)?;
+
+    let numbers = args
+        .iter()
+        .enumerate()
+        .map(|(index, arg)| Number::from_arg(name, index, arg))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    Ok(MalValue::new(Number(op(arg_1, arg_2))))
+    Ok(MalValue::new_boolean(numbers.windows(2).all(
+        |pair| matches!(pair[0].partial_cmp(&pair[1]), Some(ordering) if test(ordering)),
+    )))
 }
 
 fn list(args: &[MalValue], _env: &mut Env) -> MalResult {
@@ -186,31 +594,28 @@ fn is_list(args: &[MalValue], _env: &mut Env) -> MalResult {
 fn cons(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 2)?;
 
-    match *args[1].mal_type {
-        List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) => {
-            let mut new_vec = Vec::with_capacity(vec.len() + 1);
-            new_vec.push(args[0].clone());
-            new_vec.extend_from_slice(vec);
-
-            Ok(MalValue::new_list(new_vec))
-        }
-        _ => Err(MalError::RustFunction("Invalid 2nd argument".to_string())),
+    if args[1].is_lazy_seq() {
+        return Ok(lazy_cons(args[0].clone(), args[1].clone()));
     }
+
+    let mut new_vec = args[1].seq_elements()?;
+    new_vec.push_front(args[0].clone());
+
+    Ok(MalValue::new_list(new_vec))
 }
 
-fn concat(args: &[MalValue], _env: &mut Env) -> MalResult {
-    let mut reult_vec = Vec::new();
+fn concat(args: &[MalValue], env: &mut Env) -> MalResult {
+    if args.iter().any(MalValue::is_lazy_seq) {
+        return Ok(lazy_seq(concat_step, args.to_vec(), env));
+    }
+
+    let mut result_vec = im_rc::Vector::new();
 
     for arg in args {
-        match *arg.mal_type {
-            List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) => {
-                reult_vec.extend_from_slice(vec);
-            }
-            _ => Err(MalError::RustFunction("Invalid argument".to_string()))?,
-        }
+        result_vec.append(arg.seq_elements()?);
     }
 
-    Ok(MalValue::new_list(reult_vec))
+    Ok(MalValue::new_list(result_vec))
 }
 
 fn empty(args: &[MalValue], _env: &mut Env) -> MalResult {
@@ -218,20 +623,14 @@ fn empty(args: &[MalValue], _env: &mut Env) -> MalResult {
 
     match *args[0].mal_type {
         List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) => {
-            if vec.is_empty() {
-                Ok(MalValue::new(True))
-            } else {
-                Ok(MalValue::new(False))
-            }
-        }
-        Str(ref s) => {
-            if s.is_empty() {
-                Ok(MalValue::new(True))
-            } else {
-                Ok(MalValue::new(False))
-            }
+            Ok(MalValue::new_boolean(vec.is_empty()))
         }
-        _ => Err(MalError::RustFunction("Invalid argument".to_string())),
+        Str(ref s) => Ok(MalValue::new_boolean(s.is_empty())),
+        Map(ref mal_map) => Ok(MalValue::new_boolean(mal_map.is_empty())),
+        Set(ref mal_set) => Ok(MalValue::new_boolean(mal_set.is_empty())),
+        SortedMap(ref sorted_map) => Ok(MalValue::new_boolean(sorted_map.is_empty())),
+        SortedSet(ref sorted_set) => Ok(MalValue::new_boolean(sorted_set.is_empty())),
+        _ => Ok(MalValue::new_boolean(args[0].first_rest()?.is_none())),
     }
 }
 
@@ -240,26 +639,39 @@ fn count(args: &[MalValue], _env: &mut Env) -> MalResult {
 
     match *args[0].mal_type {
         List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) => {
-            Ok(MalValue::new(Number(vec.len() as f64)))
+            Ok(MalValue::new(Int(vec.len() as i64)))
         }
-        Str(ref s) => Ok(MalValue::new(Number(s.len() as f64))),
-        Nil => Ok(MalValue::new(Number(0.))),
-        _ => Err(MalError::RustFunction("Invalid argument".to_string())),
+        Str(ref s) => Ok(MalValue::new(Int(s.chars().count() as i64))),
+        Map(ref mal_map) => Ok(MalValue::new(Int(mal_map.len() as i64))),
+        Set(ref mal_set) => Ok(MalValue::new(Int(mal_set.len() as i64))),
+        SortedMap(ref sorted_map) => Ok(MalValue::new(Int(sorted_map.len() as i64))),
+        SortedSet(ref sorted_set) => Ok(MalValue::new(Int(sorted_set.len() as i64))),
+        _ => Ok(MalValue::new(Int(args[0].seq_elements()?.len() as i64))),
     }
 }
 
 fn nth(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 2)?;
 
-    let index = get_number_arg(&args[1])?;
+    let index = get_int_arg("nth", 1, &args[1])?;
 
-    if let List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) = *args[0].mal_type {
-        vec.get(index as usize)
-            .cloned()
-            .ok_or_else(|| MalError::RustFunction("nth: index out of range".to_string()))
-    } else {
-        Err(MalError::RustFunction("Invalid argument".to_string()))
+    if index < 0 {
+        return Err(MalError::RustFunction(
+            "nth: index out of range".to_string(),
+        ));
+    }
+
+    match *args[0].mal_type {
+        List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) => {
+            vec.get(index as usize).cloned()
+        }
+        Str(ref s) => s
+            .chars()
+            .nth(index as usize)
+            .map(|c| MalValue::new(Char(c))),
+        _ => args[0].seq_iter().nth(index as usize).transpose()?,
     }
+    .ok_or_else(|| MalError::RustFunction("nth: index out of range".to_string()))
 }
 
 fn first(args: &[MalValue], _env: &mut Env) -> MalResult {
@@ -269,25 +681,18 @@ fn first(args: &[MalValue], _env: &mut Env) -> MalResult {
         List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) => {
             Ok(vec.get(0).cloned().unwrap_or_else(MalValue::nil))
         }
-        Nil => Ok(MalValue::nil()),
-        _ => Err(MalError::RustFunction("Invalid argument".to_string())),
+        _ => Ok(args[0]
+            .first_rest()?
+            .map_or_else(MalValue::nil, |(first, _)| first)),
     }
 }
 
 fn rest(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
-    match *args[0].mal_type {
-        List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) => {
-            Ok(if vec.is_empty() {
-                MalValue::new_list(Vec::new())
-            } else {
-                MalValue::new_list(Vec::from(&vec[1..]))
-            })
-        }
-        Nil => Ok(MalValue::new_list(Vec::new())),
-        _ => Err(MalError::RustFunction("Invalid argument".to_string())),
-    }
+    Ok(args[0]
+        .first_rest()?
+        .map_or_else(|| MalValue::new_list(Vec::new()), |(_, rest)| rest))
 }
 
 fn conj(args: &[MalValue], _env: &mut Env) -> MalResult {
@@ -295,95 +700,120 @@ fn conj(args: &[MalValue], _env: &mut Env) -> MalResult {
 
     match *args[0].mal_type {
         List(MalList { ref vec, .. }) => {
-            let mut new_vec = Vec::with_capacity(vec.len() + args.len() - 1);
-            let start_vec: Vec<MalValue> = args[1..].iter().rev().cloned().collect();
-            new_vec.extend_from_slice(&start_vec);
-            new_vec.extend_from_slice(vec);
+            let mut new_vec = vec.clone();
+            for arg in &args[1..] {
+                new_vec.push_front(arg.clone());
+            }
 
             Ok(MalValue::new_list(new_vec))
         }
+        LazySeq(_) => Ok(args[1..]
+            .iter()
+            .fold(args[0].clone(), |seq, arg| lazy_cons(arg.clone(), seq))),
         Vector(MalVector { ref vec, .. }) => {
-            let mut new_vec = Vec::with_capacity(vec.len() + args.len() - 1);
-            new_vec.extend_from_slice(vec);
-            new_vec.extend_from_slice(&args[1..]);
+            let mut new_vec = vec.clone();
+            new_vec.extend(args[1..].iter().cloned());
 
             Ok(MalValue::new_vector(new_vec))
         }
+        Set(ref mal_set) => Ok(MalValue::new_set(mal_set.conj(&args[1..]))),
+        SortedSet(ref sorted_set) => Ok(MalValue::new_sorted_set(
+            sorted_set.conj(&args[1..], &mut sorted_comparator(&sorted_set.comparator))?,
+        )),
+        Map(ref mal_map) => Ok(MalValue::new_map(
+            mal_map.assoc(&map_entries_to_arguments(&args[1..])?)?,
+        )),
+        SortedMap(ref sorted_map) => Ok(MalValue::new_sorted_map(sorted_map.assoc(
+            &map_entries_to_arguments(&args[1..])?,
+            &mut sorted_comparator(&sorted_map.comparator),
+        )?)),
+        Nil => Ok(MalValue::new_list(
+            args[1..].iter().rev().cloned().collect::<Vec<_>>(),
+        )),
         _ => Err(MalError::RustFunction("Invalid argument".to_string())),
     }
 }
 
 fn equals(args: &[MalValue], _env: &mut Env) -> MalResult {
-    arg_count_eq(args, 2)?;
+    arg_count_gte(args, 1)?;
 
-    Ok(MalValue::new_boolean(args[0] == args[1]))
+    Ok(MalValue::new_boolean(
+        args.windows(2).all(|pair| pair[0] == pair[1]),
+    ))
 }
 
-fn lt(args: &[MalValue], _env: &mut Env) -> MalResult {
-    arg_count_eq(args, 2)?;
+fn not_equals(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_gte(args, 1)?;
 
-    let arg_1 = get_number_arg(&args[0])?;
-    let arg_2 = get_number_arg(&args[1])?;
+    Ok(MalValue::new_boolean(
+        !args.windows(2).all(|pair| pair[0] == pair[1]),
+    ))
+}
 
-    Ok(MalValue::new_boolean(arg_1 < arg_2))
+fn lt(args: &[MalValue], _env: &mut Env) -> MalResult {
+    compare_numbers("<", args, Ordering::is_lt)
 }
 
 fn lte(args: &[MalValue], _env: &mut Env) -> MalResult {
-    arg_count_eq(args, 2)?;
-
-    let arg_1 = get_number_arg(&args[0])?;
-    let arg_2 = get_number_arg(&args[1])?;
-
-    Ok(MalValue::new_boolean(arg_1 <= arg_2))
+    compare_numbers("<=", args, Ordering::is_le)
 }
 
 fn gt(args: &[MalValue], _env: &mut Env) -> MalResult {
-    arg_count_eq(args, 2)?;
-
-    let arg_1 = get_number_arg(&args[0])?;
-    let arg_2 = get_number_arg(&args[1])?;
-
-    Ok(MalValue::new_boolean(arg_1 > arg_2))
+    compare_numbers(">", args, Ordering::is_gt)
 }
 
 fn gte(args: &[MalValue], _env: &mut Env) -> MalResult {
-    arg_count_eq(args, 2)?;
-
-    let arg_1 = get_number_arg(&args[0])?;
-    let arg_2 = get_number_arg(&args[1])?;
-
-    Ok(MalValue::new_boolean(arg_1 >= arg_2))
+    compare_numbers(">=", args, Ordering::is_ge)
 }
 
-fn pr_strs(strs: &[MalValue], print_readably: bool) -> Vec<String> {
-    strs.iter().map(|arg| pr_str(arg, print_readably)).collect()
+fn pr_strs(strs: &[MalValue], print_readably: bool) -> Result<Vec<String>, MalError> {
+    strs.iter()
+        .map(|arg| try_pr_str(arg, print_readably))
+        .collect()
 }
 
 fn prn(args: &[MalValue], _env: &mut Env) -> MalResult {
-    println!("{}", pr_strs(args, true).join(" "));
+    println!("{}", pr_strs(args, true)?.join(" "));
 
     Ok(MalValue::nil())
 }
 
 fn mal_println(args: &[MalValue], _env: &mut Env) -> MalResult {
-    println!("{}", pr_strs(args, false).join(" "));
+    println!("{}", pr_strs(args, false)?.join(" "));
 
     Ok(MalValue::nil())
 }
 
 fn mal_pr_str(args: &[MalValue], _env: &mut Env) -> MalResult {
-    Ok(MalValue::new(Str(pr_strs(args, true).join(" "))))
+    Ok(MalValue::new(Str(pr_strs(args, true)?.join(" "))))
 }
 
 fn mal_str(args: &[MalValue], _env: &mut Env) -> MalResult {
-    Ok(MalValue::new(Str(pr_strs(args, false).join(""))))
+    Ok(MalValue::new(Str(pr_strs(args, false)?.join(""))))
 }
 
 fn read_string(args: &[MalValue], _env: &mut Env) -> MalResult {
-    arg_count_eq(args, 1)?;
+    arg_count_gte(args, 1)?;
+
+    if args.len() > 2 {
+        return Err(MalError::RustFunction(format!(
+            "Expected 1 or 2 arguments, got {}",
+            args.len()
+        )));
+    }
+
+    let file = match args.get(1).map(|arg| &*arg.mal_type) {
+        None | Some(Nil) => None,
+        Some(Str(ref file)) => Some(file.as_str()),
+        _ => {
+            return Err(MalError::RustFunction(
+                "read_string expects file name to be of type String".to_string(),
+            ))
+        }
+    };
 
     if let Str(ref arg) = *args[0].mal_type {
-        read_str(arg)
+        read_str_with_file(arg, file)
     } else {
         Err(MalError::RustFunction(
             "read_string expects argument to be of type String".to_string(),
@@ -406,10 +836,48 @@ fn slurp(args: &[MalValue], _env: &mut Env) -> MalResult {
     }
 }
 
+fn load_file(args: &[MalValue], env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    let file_name = if let Str(ref arg) = *args[0].mal_type {
+        arg
+    } else {
+        return Err(MalError::RustFunction(
+            "load-file expects argument to be of type String".to_string(),
+        ));
+    };
+
+    eval_file(file_name, env)
+}
+
+/// Evaluates the top-level forms of a file in order, returning the value of
+/// the last one.
+pub(crate) fn eval_file(file_name: &str, env: &mut Env) -> MalResult {
+    let file_content = fs::read_to_string(file_name)
+        .map_err(|e| MalError::RustFunction(format!("load-file: {}", e)))?;
+
+    let mut result = MalValue::nil();
+
+    for (i, form) in read_all(&file_content, Some(file_name)).enumerate() {
+        let form = form?;
+
+        result = env.eval(&form).map_err(|err| {
+            let context = match form.pos {
+                Some(ref pos) => format!("in top-level form #{} at {}", i + 1, pos),
+                None => format!("in top-level form #{} of {}", i + 1, file_name),
+            };
+
+            err.at(&form).with_context(context)
+        })?;
+    }
+
+    Ok(result)
+}
+
 fn mal_eval(args: &[MalValue], env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
-    core_eval(&args[0], env)
+    env.eval(&args[0])
 }
 
 fn atom(args: &[MalValue], _env: &mut Env) -> MalResult {
@@ -427,12 +895,12 @@ fn is_atom(args: &[MalValue], _env: &mut Env) -> MalResult {
 fn deref_atom(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
-    if let Atom(ref val) = *args[0].mal_type {
-        Ok(val.borrow().clone())
-    } else {
-        Err(MalError::RustFunction(
+    match *args[0].mal_type {
+        Atom(ref val) => Ok(val.borrow().clone()),
+        Reduced(ref val) => Ok(val.clone()),
+        _ => Err(MalError::RustFunction(
             "Invalid argument. Expected atom.".to_string(),
-        ))
+        )),
     }
 }
 
@@ -449,7 +917,7 @@ fn reset_atom(args: &[MalValue], _env: &mut Env) -> MalResult {
     }
 }
 
-fn swap_atom(args: &[MalValue], env: &mut Env) -> MalResult {
+fn swap_atom(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_gte(args, 2)?;
 
     let atom = if let Atom(ref val) = *args[0].mal_type {
@@ -470,7 +938,7 @@ fn swap_atom(args: &[MalValue], env: &mut Env) -> MalResult {
     apply_args.push(atom.borrow().clone());
     apply_args.extend_from_slice(&args[2..]);
 
-    let result = core_apply(&args[1], &apply_args, env)?;
+    let result = core_apply(&args[1], &apply_args)?;
 
     atom.replace(result.clone());
     Ok(result)
@@ -522,47 +990,40 @@ fn is_symbol(args: &[MalValue], _env: &mut Env) -> MalResult {
     }
 }
 
-fn apply(args: &[MalValue], env: &mut Env) -> MalResult {
+fn apply(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_gte(args, 2)?;
 
-    let last_args_list = args.last().unwrap();
+    let last_args = args.last().unwrap().seq_elements()?;
 
-    if let List(MalList {
-        vec: ref last_args, ..
-    })
-    | Vector(MalVector {
-        vec: ref last_args, ..
-    }) = *last_args_list.mal_type
-    {
-        let mut vec = Vec::with_capacity(args.len() + last_args.len() - 2);
-        vec.extend_from_slice(&args[1..args.len() - 1]);
-        vec.extend_from_slice(&last_args);
+    let mut vec = Vec::with_capacity(args.len() + last_args.len() - 2);
+    vec.extend_from_slice(&args[1..args.len() - 1]);
+    vec.extend(last_args.iter().cloned());
 
-        core_apply(&args[0], &vec, env)
-    } else {
-        Err(MalError::RustFunction(
-            "Invalid argument. Last argument of apply must be a list or vector.".to_string(),
-        ))
-    }
+    core_apply(&args[0], &vec)
 }
 
 fn map(args: &[MalValue], env: &mut Env) -> MalResult {
-    arg_count_eq(args, 2)?;
+    arg_count_gte(args, 2)?;
+
+    if args[1..].iter().any(MalValue::is_lazy_seq) {
+        return Ok(lazy_seq(map_step, args.to_vec(), env));
+    }
 
     let function = &args[0];
+    let colls = args[1..]
+        .iter()
+        .map(Seqable::seq_elements)
+        .collect::<Result<Vec<_>, _>>()?;
+    let len = colls.iter().map(im_rc::Vector::len).min().unwrap_or(0);
 
-    if let List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) = *args[1].mal_type {
-        let result_vec: Result<_, _> = vec
-            .iter()
-            .map(|elem| core_apply(function, slice::from_ref(elem), env))
-            .collect();
+    let result_vec: Result<Vec<_>, _> = (0..len)
+        .map(|i| {
+            let function_args: Vec<_> = colls.iter().map(|coll| coll[i].clone()).collect();
+            core_apply(function, &function_args)
+        })
+        .collect();
 
-        Ok(MalValue::new_list(result_vec?))
-    } else {
-        Err(MalError::RustFunction(
-            "Invalid argument. Second argument of map must be a list or vector.".to_string(),
-        ))
-    }
+    Ok(MalValue::new_list(result_vec?))
 }
 
 fn symbol(args: &[MalValue], _env: &mut Env) -> MalResult {
@@ -616,7 +1077,7 @@ fn is_vector(args: &[MalValue], _env: &mut Env) -> MalResult {
 fn is_sequential(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
-    if let List(_) | Vector(_) = *args[0].mal_type {
+    if let List(_) | Vector(_) | LazySeq(_) = *args[0].mal_type {
         Ok(MalValue::new_boolean(true))
     } else {
         Ok(MalValue::new_boolean(false))
@@ -630,7 +1091,7 @@ fn hash_map(args: &[MalValue], _env: &mut Env) -> MalResult {
 fn is_map(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
-    if let Map(_) = *args[0].mal_type {
+    if let Map(_) | SortedMap(_) = *args[0].mal_type {
         Ok(MalValue::new_boolean(true))
     } else {
         Ok(MalValue::new_boolean(false))
@@ -640,24 +1101,28 @@ fn is_map(args: &[MalValue], _env: &mut Env) -> MalResult {
 fn assoc(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_gte(args, 1)?;
 
-    if let Map(ref mal_map) = *args[0].mal_type {
-        Ok(MalValue::new_map(mal_map.assoc(&args[1..])?))
-    } else {
-        Err(MalError::RustFunction(
+    match *args[0].mal_type {
+        Map(ref mal_map) => Ok(MalValue::new_map(mal_map.assoc(&args[1..])?)),
+        SortedMap(ref sorted_map) => Ok(MalValue::new_sorted_map(
+            sorted_map.assoc(&args[1..], &mut sorted_comparator(&sorted_map.comparator))?,
+        )),
+        _ => Err(MalError::RustFunction(
             "First argument must be a hash map.".to_string(),
-        ))
+        )),
     }
 }
 
 fn dissoc(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_gte(args, 1)?;
 
-    if let Map(ref mal_map) = *args[0].mal_type {
-        Ok(MalValue::new_map(mal_map.dissoc(&args[1..])?))
-    } else {
-        Err(MalError::RustFunction(
+    match *args[0].mal_type {
+        Map(ref mal_map) => Ok(MalValue::new_map(mal_map.dissoc(&args[1..])?)),
+        SortedMap(ref sorted_map) => Ok(MalValue::new_sorted_map(
+            sorted_map.dissoc(&args[1..], &mut sorted_comparator(&sorted_map.comparator))?,
+        )),
+        _ => Err(MalError::RustFunction(
             "First argument must be a hash map.".to_string(),
-        ))
+        )),
     }
 }
 
@@ -666,6 +1131,9 @@ fn get(args: &[MalValue], _env: &mut Env) -> MalResult {
 
     match *args[0].mal_type {
         Map(ref mal_map) => Ok(mal_map.get(&args[1])),
+        SortedMap(ref sorted_map) => {
+            sorted_map.get(&args[1], &mut sorted_comparator(&sorted_map.comparator))
+        }
         Nil => Ok(MalValue::nil()),
         _ => Err(MalError::RustFunction(
             "First argument must be a hash map.".to_string(),
@@ -678,37 +1146,384 @@ fn contains(args: &[MalValue], _env: &mut Env) -> MalResult {
 
     match *args[0].mal_type {
         Map(ref mal_map) => Ok(MalValue::new_boolean(mal_map.contains(&args[1]))),
+        Set(ref mal_set) => Ok(MalValue::new_boolean(mal_set.contains(&args[1]))),
+        SortedMap(ref sorted_map) => Ok(MalValue::new_boolean(
+            sorted_map.contains(&args[1], &mut sorted_comparator(&sorted_map.comparator))?,
+        )),
+        SortedSet(ref sorted_set) => Ok(MalValue::new_boolean(
+            sorted_set.contains(&args[1], &mut sorted_comparator(&sorted_set.comparator))?,
+        )),
         Nil => Ok(MalValue::new_boolean(false)),
         _ => Err(MalError::RustFunction(
-            "First argument must be a hash map.".to_string(),
+            "First argument must be a hash map or set.".to_string(),
         )),
     }
 }
 
-fn keys(args: &[MalValue], _env: &mut Env) -> MalResult {
+fn hash_set(args: &[MalValue], _env: &mut Env) -> MalResult {
+    Ok(MalValue::new_set(MalSet::from_values(args)))
+}
+
+fn set(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
-    if let Map(ref mal_map) = *args[0].mal_type {
-        let keys = mal_map.iter().map(|(key, _)| key.clone()).collect();
-        Ok(MalValue::new_list(keys))
-    } else {
-        Err(MalError::RustFunction(
-            "Argument must be a hash map.".to_string(),
-        ))
+    match *args[0].mal_type {
+        Set(ref mal_set) => Ok(MalValue::new_set(mal_set.clone_with_meta(MalValue::nil()))),
+        _ => Ok(MalValue::new_set(MalSet::from_values(
+            &args[0].seq_elements()?,
+        ))),
+    }
+}
+
+fn is_set(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new_boolean(matches!(
+        *args[0].mal_type,
+        Set(_) | SortedSet(_)
+    )))
+}
+
+/// Takes a hash set or a sorted set as a hash set.
+fn get_set_arg(arg: &MalValue) -> Result<MalSet, MalError> {
+    match *arg.mal_type {
+        Set(ref mal_set) => Ok(mal_set.clone_with_meta(MalValue::nil())),
+        SortedSet(ref sorted_set) => Ok(MalSet::from_values(sorted_set.iter())),
+        _ => Err(MalError::RustFunction(
+            "Argument must be a set.".to_string(),
+        )),
+    }
+}
+
+fn disj(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_gte(args, 1)?;
+
+    if let SortedSet(ref sorted_set) = *args[0].mal_type {
+        return Ok(MalValue::new_sorted_set(sorted_set.disj(
+            &args[1..],
+            &mut sorted_comparator(&sorted_set.comparator),
+        )?));
+    }
+
+    Ok(MalValue::new_set(get_set_arg(&args[0])?.disj(&args[1..])))
+}
+
+fn union(args: &[MalValue], _env: &mut Env) -> MalResult {
+    if args.is_empty() {
+        return Ok(MalValue::new_set(MalSet::new()));
+    }
+
+    fold_sets(args, MalSet::union)
+}
+
+fn intersection(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_gte(args, 1)?;
+
+    fold_sets(args, MalSet::intersection)
+}
+
+fn difference(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_gte(args, 1)?;
+
+    fold_sets(args, MalSet::difference)
+}
+
+/// Combines the sets with `op`. The result is of the same type as the first
+/// set, and a sorted set keeps its ordering.
+fn fold_sets(args: &[MalValue], op: fn(&MalSet, &MalSet) -> MalSet) -> MalResult {
+    let result = args[1..]
+        .iter()
+        .try_fold(get_set_arg(&args[0])?, |acc, arg| {
+            Ok(op(&acc, &get_set_arg(arg)?))
+        })?;
+
+    match *args[0].mal_type {
+        SortedSet(ref sorted_set) => Ok(MalValue::new_sorted_set(
+            MalSortedSet::new(sorted_set.comparator.clone()).conj(
+                result.iter(),
+                &mut sorted_comparator(&sorted_set.comparator),
+            )?,
+        )),
+        _ => Ok(MalValue::new_set(result)),
     }
 }
 
+fn keys(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    let keys: Vec<_> = match *args[0].mal_type {
+        Map(ref mal_map) => mal_map.iter().map(|(key, _)| key.clone()).collect(),
+        SortedMap(ref sorted_map) => sorted_map.iter().map(|(key, _)| key.clone()).collect(),
+        _ => {
+            return Err(MalError::RustFunction(
+                "Argument must be a hash map.".to_string(),
+            ))
+        }
+    };
+
+    Ok(MalValue::new_list(keys))
+}
+
 fn vals(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
-    if let Map(ref mal_map) = *args[0].mal_type {
-        let vals = mal_map.iter().map(|(_, val)| val.clone()).collect();
-        Ok(MalValue::new_list(vals))
+    let vals: Vec<_> = match *args[0].mal_type {
+        Map(ref mal_map) => mal_map.iter().map(|(_, val)| val.clone()).collect(),
+        SortedMap(ref sorted_map) => sorted_map.iter().map(|(_, val)| val.clone()).collect(),
+        _ => {
+            return Err(MalError::RustFunction(
+                "Argument must be a hash map.".to_string(),
+            ))
+        }
+    };
+
+    Ok(MalValue::new_list(vals))
+}
+
+fn sorted_map(args: &[MalValue], _env: &mut Env) -> MalResult {
+    new_sorted_map(MalValue::nil(), args)
+}
+
+fn sorted_map_by(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_gte(args, 1)?;
+
+    new_sorted_map(args[0].clone(), &args[1..])
+}
+
+fn new_sorted_map(comparator: MalValue, args: &[MalValue]) -> MalResult {
+    let sorted_map =
+        MalSortedMap::new(comparator.clone()).assoc(args, &mut sorted_comparator(&comparator))?;
+
+    Ok(MalValue::new_sorted_map(sorted_map))
+}
+
+fn sorted_set(args: &[MalValue], _env: &mut Env) -> MalResult {
+    new_sorted_set(MalValue::nil(), args)
+}
+
+fn sorted_set_by(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_gte(args, 1)?;
+
+    new_sorted_set(args[0].clone(), &args[1..])
+}
+
+fn new_sorted_set(comparator: MalValue, args: &[MalValue]) -> MalResult {
+    let sorted_set =
+        MalSortedSet::new(comparator.clone()).conj(args, &mut sorted_comparator(&comparator))?;
+
+    Ok(MalValue::new_sorted_set(sorted_set))
+}
+
+fn is_sorted(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new_boolean(matches!(
+        *args[0].mal_type,
+        SortedMap(_) | SortedSet(_)
+    )))
+}
+
+fn mal_compare(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    Ok(MalValue::new(Int(compare(&args[0], &args[1])? as i64)))
+}
+
+/// A total order over values, used by `compare` and by sorted collections.
+/// Values of different types are ordered by type: nil, booleans, numbers,
+/// characters, strings, keywords, symbols and sequential collections.
+/// Sequential collections are ordered by length and then element by element.
+pub fn compare(a: &MalValue, b: &MalValue) -> Result<Ordering, MalError> {
+    let rank_order = compare_rank(a)?.cmp(&compare_rank(b)?);
+    if rank_order != Ordering::Equal {
+        return Ok(rank_order);
+    }
+
+    match (&*a.mal_type, &*b.mal_type) {
+        (Str(a), Str(b)) | (Keyword(a), Keyword(b)) | (Symbol(a), Symbol(b)) => Ok(a.cmp(b)),
+        (Char(a), Char(b)) => Ok(a.cmp(b)),
+        (List(MalList { vec: a, .. }), List(MalList { vec: b, .. }))
+        | (List(MalList { vec: a, .. }), Vector(MalVector { vec: b, .. }))
+        | (Vector(MalVector { vec: a, .. }), List(MalList { vec: b, .. }))
+        | (Vector(MalVector { vec: a, .. }), Vector(MalVector { vec: b, .. })) => {
+            if a.len() != b.len() {
+                return Ok(a.len().cmp(&b.len()));
+            }
+
+            for (a, b) in a.iter().zip(b.iter()) {
+                let order = compare(a, b)?;
+                if order != Ordering::Equal {
+                    return Ok(order);
+                }
+            }
+
+            Ok(Ordering::Equal)
+        }
+        _ => match (Number::from_value(a), Number::from_value(b)) {
+            (Some(a), Some(b)) => Ok(a
+                .partial_cmp(&b)
+                .unwrap_or_else(|| a.to_f64().is_nan().cmp(&b.to_f64().is_nan()))),
+            // nil and booleans, whose ranks already order them.
+            _ => Ok(Ordering::Equal),
+        },
+    }
+}
+
+fn compare_rank(mal_value: &MalValue) -> Result<u8, MalError> {
+    match *mal_value.mal_type {
+        Nil => Ok(0),
+        False => Ok(1),
+        True => Ok(2),
+        Int(_) | BigInt(_) | Ratio(_) | Float(_) => Ok(3),
+        Char(_) => Ok(4),
+        Str(_) => Ok(5),
+        Keyword(_) => Ok(6),
+        Symbol(_) => Ok(7),
+        List(_) | Vector(_) => Ok(8),
+        _ => Err(MalError::RustFunction(format!(
+            "Cannot compare {}",
+            pr_str(mal_value, true)
+        ))),
+    }
+}
+
+/// Orders two values with the comparator of a sorted collection, which is
+/// either nil (meaning `compare`) or a function. Like in Clojure, the
+/// function may return a number or act as a "less than" predicate.
+fn compare_with(comparator: &MalValue, a: &MalValue, b: &MalValue) -> Result<Ordering, MalError> {
+    if let Nil = *comparator.mal_type {
+        return compare(a, b);
+    }
+
+    let result = core_apply(comparator, &[a.clone(), b.clone()])?;
+
+    match *result.mal_type {
+        True => Ok(Ordering::Less),
+        Nil | False => {
+            let reversed = core_apply(comparator, &[b.clone(), a.clone()])?;
+
+            Ok(match *reversed.mal_type {
+                Nil | False => Ordering::Equal,
+                _ => Ordering::Greater,
+            })
+        }
+        _ => Number::from_value(&result)
+            .ok_or_else(|| {
+                MalError::RustFunction("Comparator must return a boolean or a number".to_string())
+            })?
+            .partial_cmp(&Number::Int(0))
+            .ok_or_else(|| MalError::RustFunction("Comparator returned NaN".to_string())),
+    }
+}
+
+fn sorted_comparator(
+    comparator: &MalValue,
+) -> impl FnMut(&MalValue, &MalValue) -> Result<Ordering, MalError> + '_ {
+    move |a, b| compare_with(comparator, a, b)
+}
+
+fn subseq(args: &[MalValue], _env: &mut Env) -> MalResult {
+    let elements = subseq_elements(args)?;
+
+    Ok(if elements.is_empty() {
+        MalValue::nil()
     } else {
-        Err(MalError::RustFunction(
-            "Argument must be a hash map.".to_string(),
-        ))
+        MalValue::new_list(elements)
+    })
+}
+
+fn rsubseq(args: &[MalValue], _env: &mut Env) -> MalResult {
+    let mut elements = subseq_elements(args)?;
+    elements.reverse();
+
+    Ok(if elements.is_empty() {
+        MalValue::nil()
+    } else {
+        MalValue::new_list(elements)
+    })
+}
+
+/// Implements `(subseq coll test key)` and
+/// `(subseq coll start-test start-key end-test end-key)`, where the tests
+/// are `<`, `<=`, `>` or `>=`.
+fn subseq_elements(args: &[MalValue]) -> Result<Vec<MalValue>, MalError> {
+    if args.len() != 3 && args.len() != 5 {
+        return Err(MalError::RustFunction(format!(
+            "subseq expected 3 or 5 arguments, got {}",
+            args.len()
+        )));
+    }
+
+    let bounds = args[1..]
+        .chunks(2)
+        .map(|pair| Ok((subseq_test(&pair[0])?, &pair[1])))
+        .collect::<Result<Vec<_>, MalError>>()?;
+
+    match *args[0].mal_type {
+        SortedMap(ref sorted_map) => {
+            let mut cmp = sorted_comparator(&sorted_map.comparator);
+            let (from, to) = subseq_range(&bounds, sorted_map.len(), |key| {
+                sorted_map.search(key, &mut cmp)
+            })?;
+
+            Ok(sorted_map.range(from, to).map(map_entry).collect())
+        }
+        SortedSet(ref sorted_set) => {
+            let mut cmp = sorted_comparator(&sorted_set.comparator);
+            let (from, to) = subseq_range(&bounds, sorted_set.len(), |value| {
+                sorted_set.search(value, &mut cmp)
+            })?;
+
+            Ok(sorted_set.range(from, to).cloned().collect())
+        }
+        _ => Err(MalError::RustFunction(
+            "First argument must be a sorted map or set.".to_string(),
+        )),
+    }
+}
+
+/// Like in Clojure, a subseq test is applied to the result of comparing an
+/// element with the key, and 0. Returns whether the test gives a lower bound
+/// (`>` or `>=`) and whether it includes the key.
+fn subseq_test(test: &MalValue) -> Result<(bool, bool), MalError> {
+    let holds = |order: i64| -> Result<bool, MalError> {
+        let args = [MalValue::new(Int(order)), MalValue::new(Int(0))];
+        let result = core_apply(test, &args)?;
+
+        Ok(!matches!(*result.mal_type, Nil | False))
+    };
+
+    match (holds(-1)?, holds(0)?, holds(1)?) {
+        (false, inclusive, true) => Ok((true, inclusive)),
+        (true, inclusive, false) => Ok((false, inclusive)),
+        _ => Err(MalError::RustFunction(
+            "subseq tests must be one of <, <=, > or >=".to_string(),
+        )),
+    }
+}
+
+fn subseq_range(
+    bounds: &[((bool, bool), &MalValue)],
+    len: usize,
+    mut search: impl FnMut(&MalValue) -> Result<Result<usize, usize>, MalError>,
+) -> Result<(usize, usize), MalError> {
+    let (mut from, mut to) = (0, len);
+
+    for &((is_lower, inclusive), key) in bounds {
+        let index = match search(key)? {
+            Ok(index) if inclusive == is_lower => index,
+            Ok(index) => index + 1,
+            Err(index) => index,
+        };
+
+        if is_lower {
+            from = from.max(index);
+        } else {
+            to = to.min(index);
+        }
     }
+
+    Ok((from, to.max(from)))
 }
 
 fn readline(args: &[MalValue], _env: &mut Env) -> MalResult {
@@ -748,53 +1563,1346 @@ fn is_string(args: &[MalValue], _env: &mut Env) -> MalResult {
     Ok(MalValue::new_boolean(args[0].is_string()))
 }
 
-fn is_number(args: &[MalValue], _env: &mut Env) -> MalResult {
+fn is_char(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
-    Ok(MalValue::new_boolean(args[0].is_number()))
+    Ok(MalValue::new_boolean(args[0].is_char()))
 }
 
-fn is_fn(args: &[MalValue], _env: &mut Env) -> MalResult {
+fn char(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
-    Ok(MalValue::new_boolean(args[0].is_function()))
+    match *args[0].mal_type {
+        Char(_) => Ok(args[0].clone()),
+        Int(n) => u32::try_from(n)
+            .ok()
+            .and_then(std::char::from_u32)
+            .map(|c| MalValue::new(Char(c)))
+            .ok_or_else(|| MalError::RustFunction(format!("Value out of range for char: {}", n))),
+        _ => Err(MalError::RustFunction(
+            "Argument must be a char or an integer".to_string(),
+        )),
+    }
 }
 
-fn is_macro(args: &[MalValue], _env: &mut Env) -> MalResult {
-    arg_count_eq(args, 1)?;
+fn get_str_arg(arg: &MalValue) -> Result<&str, MalError> {
+    if let Str(ref s) = *arg.mal_type {
+        Ok(s)
+    } else {
+        Err(MalError::RustFunction(
+            "Argument must be a string".to_string(),
+        ))
+    }
+}
 
-    Ok(MalValue::new_boolean(args[0].is_macro()))
+/// Accepts a string or a char, for arguments that are searched for or
+/// inserted into a string.
+fn get_text_arg(arg: &MalValue) -> Result<String, MalError> {
+    match *arg.mal_type {
+        Str(ref s) => Ok(s.clone()),
+        Char(c) => Ok(c.to_string()),
+        _ => Err(MalError::RustFunction(
+            "Argument must be a string or a char".to_string(),
+        )),
+    }
 }
 
-fn time_ms(args: &[MalValue], _env: &mut Env) -> MalResult {
-    arg_count_eq(args, 0)?;
+fn arg_count_between(args: &[MalValue], min_args: usize, max_args: usize) -> Result<(), MalError> {
+    if args.len() < min_args || args.len() > max_args {
+        return Err(MalError::RustFunction(format!(
+            "Expected {} to {} arguments, got {}",
+            min_args,
+            max_args,
+            args.len()
+        )));
+    }
 
-    let millis = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|_| MalError::RustFunction("Could not calculate the current time.".to_string()))?
-        .as_millis();
+    Ok(())
+}
 
-    Ok(MalValue::new(Number(millis as f64)))
+/// String indices count chars, not bytes. Returns `None` if `index` is past
+/// the end of `s`.
+fn char_to_byte_index(s: &str, index: usize) -> Option<usize> {
+    s.char_indices()
+        .map(|(i, _)| i)
+        .chain(once(s.len()))
+        .nth(index)
 }
 
-fn seq(args: &[MalValue], _env: &mut Env) -> MalResult {
-    arg_count_eq(args, 1)?;
+fn byte_to_char_index(s: &str, index: usize) -> usize {
+    s[..index].chars().count()
+}
 
-    match *args[0].mal_type {
-        List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) if vec.is_empty() => {
-            Ok(MalValue::nil())
-        }
-        List(_) => Ok(args[0].clone()),
-        Vector(ref mal_vec) => Ok(MalValue::new_list(mal_vec.vec.clone())),
-        Str(ref str_val) if str_val.is_empty() => Ok(MalValue::nil()),
-        Str(ref str_val) => {
-            let chars = str_val
-                .chars()
+fn subs(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_between(args, 2, 3)?;
+
+    let s = get_str_arg(&args[0])?;
+    let start = get_int_arg("subs", 1, &args[1])?;
+    let end = match args.get(2) {
+        Some(end) => Some(get_int_arg("subs", 2, end)?),
+        None => None,
+    };
+
+    let byte_index = |index: i64| {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| char_to_byte_index(s, index))
+    };
+
+    match (byte_index(start), end.map_or(Some(s.len()), byte_index)) {
+        (Some(start), Some(end)) if start <= end => {
+            Ok(MalValue::new(Str(s[start..end].to_string())))
+        }
+        _ => Err(MalError::RustFunction(
+            "subs: index out of range".to_string(),
+        )),
+    }
+}
+
+fn new_str_vector<'a>(strs: impl Iterator<Item = &'a str>) -> MalValue {
+    MalValue::new_vector(
+        strs.map(|s| MalValue::new(Str(s.to_string())))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// The separator may be a string, a char or a regex. Trailing empty strings
+/// are removed unless a limit is given. An empty string separator splits the
+/// string into its chars, ignoring the limit.
+fn split(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_between(args, 2, 3)?;
+
+    let s = get_str_arg(&args[0])?;
+    let limit = match args.get(2) {
+        Some(limit) => Some(get_int_arg("split", 2, limit)?),
+        None => None,
+    };
+
+    if let Regex(ref re) = *args[1].mal_type {
+        return Ok(match limit {
+            Some(limit) if limit > 0 => new_str_vector(re.splitn(s, limit as usize)),
+            _ => new_str_vector(without_trailing_empty_strs(re.split(s).collect())),
+        });
+    }
+
+    let separator = get_text_arg(&args[1])?;
+
+    if separator.is_empty() {
+        return Ok(MalValue::new_vector(
+            s.chars()
                 .map(|c| MalValue::new(Str(c.to_string())))
-                .collect();
-            Ok(MalValue::new_list(chars))
+                .collect::<Vec<_>>(),
+        ));
+    }
+
+    match limit {
+        Some(limit) if limit > 0 => {
+            Ok(new_str_vector(s.splitn(limit as usize, separator.as_str())))
         }
-        Nil => Ok(MalValue::nil()),
-        _ => Err(MalError::RustFunction("Invalid argument".to_string())),
+        _ => Ok(new_str_vector(without_trailing_empty_strs(
+            s.split(separator.as_str()).collect(),
+        ))),
+    }
+}
+
+fn without_trailing_empty_strs(mut parts: Vec<&str>) -> impl Iterator<Item = &str> {
+    while parts.len() > 1 && parts.last() == Some(&"") {
+        parts.pop();
+    }
+
+    parts.into_iter()
+}
+
+fn split_lines(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(new_str_vector(get_str_arg(&args[0])?.lines()))
+}
+
+/// Splits a string into extended grapheme clusters, so that a character made
+/// of several chars (like an emoji with a skin tone modifier) is kept whole.
+fn graphemes(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(new_str_vector(get_str_arg(&args[0])?.graphemes(true)))
+}
+
+fn grapheme_count(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new(Int(
+        get_str_arg(&args[0])?.graphemes(true).count() as i64,
+    )))
+}
+
+fn join(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_between(args, 1, 2)?;
+
+    let (separator, coll) = match args.len() {
+        1 => (String::new(), &args[0]),
+        _ => (get_text_arg(&args[0])?, &args[1]),
+    };
+
+    let strs = coll
+        .seq_elements()?
+        .iter()
+        .map(|value| try_pr_str(value, false))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(MalValue::new(Str(strs.join(&separator))))
+}
+
+fn replace(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_replace(args, None)
+}
+
+fn replace_first(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_replace(args, Some(1))
+}
+
+/// Replaces up to `limit` matches, or all of them if `limit` is `None`. A regex
+/// pattern's replacement may refer to groups with `$1` or `${name}`, or be a
+/// function that is called with each match, as `re-find` would return it.
+fn eval_replace(args: &[MalValue], limit: Option<usize>) -> MalResult {
+    arg_count_eq(args, 3)?;
+
+    let s = get_str_arg(&args[0])?;
+
+    if let Regex(ref re) = *args[1].mal_type {
+        return regex_replace(re, s, &args[2], limit.unwrap_or(0));
+    }
+
+    let pattern = get_text_arg(&args[1])?;
+    let replacement = get_text_arg(&args[2])?;
+
+    Ok(MalValue::new(Str(match limit {
+        Some(limit) => s.replacen(&pattern, &replacement, limit),
+        None => s.replace(&pattern, &replacement),
+    })))
+}
+
+/// A `limit` of 0 replaces all matches, as in `Regex::replacen`.
+fn regex_replace(re: &regex::Regex, s: &str, replacement: &MalValue, limit: usize) -> MalResult {
+    if !replacement.is_function() {
+        let replacement = get_text_arg(replacement)?;
+
+        return Ok(MalValue::new(Str(re
+            .replacen(s, limit, replacement.as_str())
+            .into_owned())));
+    }
+
+    let limit = if limit == 0 { usize::MAX } else { limit };
+    let mut result = String::new();
+    let mut last_end = 0;
+
+    for captures in re.captures_iter(s).take(limit) {
+        let whole = captures.get(0).unwrap();
+        let text = core_apply(replacement, &[regex_match(&captures)])?;
+
+        result.push_str(&s[last_end..whole.start()]);
+        result.push_str(&get_text_arg(&text)?);
+        last_end = whole.end();
     }
+
+    result.push_str(&s[last_end..]);
+
+    Ok(MalValue::new(Str(result)))
+}
+
+fn get_regex_arg(arg: &MalValue) -> Result<&MalRegex, MalError> {
+    if let Regex(ref re) = *arg.mal_type {
+        Ok(re)
+    } else {
+        Err(MalError::RustFunction(
+            "Argument must be a regex".to_string(),
+        ))
+    }
+}
+
+/// A match is returned as the matched string if the regex has no groups, or
+/// else as a vector of the whole match followed by each group (nil if the
+/// group did not participate).
+fn regex_match(captures: &regex::Captures) -> MalValue {
+    if captures.len() == 1 {
+        regex_group(captures.get(0))
+    } else {
+        MalValue::new_vector(captures.iter().map(regex_group).collect::<Vec<_>>())
+    }
+}
+
+fn regex_group(group: Option<regex::Match>) -> MalValue {
+    group.map_or_else(MalValue::nil, |m| {
+        MalValue::new(Str(m.as_str().to_string()))
+    })
+}
+
+fn is_regex(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new_boolean(matches!(*args[0].mal_type, Regex(_))))
+}
+
+fn re_pattern(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    if let Regex(_) = *args[0].mal_type {
+        return Ok(args[0].clone());
+    }
+
+    regex::Regex::new(get_str_arg(&args[0])?)
+        .map(|re| MalValue::new(Regex(re.into())))
+        .map_err(|err| MalError::RustFunction(format!("re-pattern: {}", err)))
+}
+
+fn re_find(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    let re = get_regex_arg(&args[0])?;
+    let s = get_str_arg(&args[1])?;
+
+    Ok(re
+        .captures(s)
+        .map_or_else(MalValue::nil, |captures| regex_match(&captures)))
+}
+
+/// Like `re-find`, but the regex must match the whole string.
+fn re_matches(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    let re = get_regex_arg(&args[0])?;
+    let s = get_str_arg(&args[1])?;
+
+    let anchored = re
+        .anchored()
+        .map_err(|err| MalError::RustFunction(format!("re-matches: {}", err)))?;
+
+    Ok(anchored
+        .captures(s)
+        .map_or_else(MalValue::nil, |captures| regex_match(&captures)))
+}
+
+fn re_seq(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    let re = get_regex_arg(&args[0])?;
+    let s = get_str_arg(&args[1])?;
+
+    let matches: Vec<_> = re
+        .captures_iter(s)
+        .map(|captures| regex_match(&captures))
+        .collect();
+
+    Ok(if matches.is_empty() {
+        MalValue::nil()
+    } else {
+        MalValue::new_list(matches)
+    })
+}
+
+/// Returns the named groups of the first match as a map from keywords to the
+/// matched strings (nil for groups that did not participate).
+fn re_groups(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    let re = get_regex_arg(&args[0])?;
+    let s = get_str_arg(&args[1])?;
+
+    let captures = match re.captures(s) {
+        Some(captures) => captures,
+        None => return Ok(MalValue::nil()),
+    };
+
+    let mut entries = Vec::new();
+    for name in re.capture_names().flatten() {
+        entries.push(MalValue::new(Keyword(name.to_string())));
+        entries.push(regex_group(captures.name(name)));
+    }
+
+    Ok(MalValue::new_map(MalMap::from_arguments(&entries)?))
+}
+
+fn eval_string_function(args: &[MalValue], f: fn(&str) -> String) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new(Str(f(get_str_arg(&args[0])?))))
+}
+
+fn upper_case(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_string_function(args, str::to_uppercase)
+}
+
+fn lower_case(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_string_function(args, str::to_lowercase)
+}
+
+fn capitalize(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_string_function(args, |s| {
+        let mut chars = s.chars();
+
+        match chars.next() {
+            Some(first) => first
+                .to_uppercase()
+                .chain(chars.as_str().to_lowercase().chars())
+                .collect(),
+            None => String::new(),
+        }
+    })
+}
+
+fn trim(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_string_function(args, |s| s.trim().to_string())
+}
+
+fn triml(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_string_function(args, |s| s.trim_start().to_string())
+}
+
+fn trimr(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_string_function(args, |s| s.trim_end().to_string())
+}
+
+fn reverse_str(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_string_function(args, |s| s.chars().rev().collect())
+}
+
+fn eval_string_test(args: &[MalValue], test: fn(&str, &str) -> bool) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    let s = get_str_arg(&args[0])?;
+    let value = get_text_arg(&args[1])?;
+
+    Ok(MalValue::new_boolean(test(s, &value)))
+}
+
+fn starts_with(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_string_test(args, |s, value| s.starts_with(value))
+}
+
+fn ends_with(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_string_test(args, |s, value| s.ends_with(value))
+}
+
+fn includes(args: &[MalValue], _env: &mut Env) -> MalResult {
+    eval_string_test(args, |s, value| s.contains(value))
+}
+
+/// Returns the index, in chars, of the first occurrence of the value at or
+/// after the optional starting index, or nil if there is none.
+fn index_of(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_between(args, 2, 3)?;
+
+    let s = get_str_arg(&args[0])?;
+    let value = get_text_arg(&args[1])?;
+    let from = match args.get(2) {
+        Some(from) => char_to_byte_index(s, get_int_arg("index-of", 2, from)?.max(0) as usize)
+            .unwrap_or(s.len()),
+        None => 0,
+    };
+
+    Ok(s[from..]
+        .find(value.as_str())
+        .map_or_else(MalValue::nil, |i| {
+            MalValue::new(Int(byte_to_char_index(s, from + i) as i64))
+        }))
+}
+
+/// Like `index-of`, but finds the last occurrence at or before the optional
+/// starting index.
+fn last_index_of(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_between(args, 2, 3)?;
+
+    let s = get_str_arg(&args[0])?;
+    let value = get_text_arg(&args[1])?;
+    let from = match args.get(2) {
+        Some(from) if get_int_arg("last-index-of", 2, from)? < 0 => return Ok(MalValue::nil()),
+        Some(from) => char_to_byte_index(s, get_int_arg("last-index-of", 2, from)? as usize)
+            .unwrap_or(s.len()),
+        None => s.len(),
+    };
+
+    // An occurrence starting at or before `from` ends at or before `end`.
+    let mut end = (from + value.len()).min(s.len());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    Ok(s[..end]
+        .rfind(value.as_str())
+        .map_or_else(MalValue::nil, |i| {
+            MalValue::new(Int(byte_to_char_index(s, i) as i64))
+        }))
+}
+
+fn is_blank(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    match *args[0].mal_type {
+        Nil => Ok(MalValue::new_boolean(true)),
+        _ => Ok(MalValue::new_boolean(
+            get_str_arg(&args[0])?.trim().is_empty(),
+        )),
+    }
+}
+
+/// Returns the padding needed to make `s` `width` chars long, repeating the
+/// optional pad string (a space by default).
+fn padding(name: &str, args: &[MalValue]) -> Result<String, MalError> {
+    arg_count_between(args, 2, 3)?;
+
+    let s = get_str_arg(&args[0])?;
+    let width = get_int_arg(name, 1, &args[1])?;
+    let pad = match args.get(2) {
+        Some(pad) => get_text_arg(pad)?,
+        None => " ".to_string(),
+    };
+
+    if pad.is_empty() {
+        return Err(MalError::RustFunction(
+            "Padding must not be empty".to_string(),
+        ));
+    }
+
+    let missing = (width.max(0) as usize).saturating_sub(s.chars().count());
+
+    Ok(pad.chars().cycle().take(missing).collect())
+}
+
+fn pad_left(args: &[MalValue], _env: &mut Env) -> MalResult {
+    let padding = padding("pad-left", args)?;
+
+    Ok(MalValue::new(Str(padding + get_str_arg(&args[0])?)))
+}
+
+fn pad_right(args: &[MalValue], _env: &mut Env) -> MalResult {
+    let padding = padding("pad-right", args)?;
+
+    Ok(MalValue::new(Str(
+        get_str_arg(&args[0])?.to_string() + &padding
+    )))
+}
+
+/// Converts a char to its code point, or truncates a number to an `Int`.
+fn int(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    if let Char(c) = *args[0].mal_type {
+        return Ok(MalValue::new(Int(i64::from(u32::from(c)))));
+    }
+
+    let truncated = match Number::from_arg("int", 0, &args[0])? {
+        Number::Float(val) if (i64::MIN as f64..-(i64::MIN as f64)).contains(&val) => {
+            Number::Int(val as i64)
+        }
+        Number::Ratio(val) => Number::from_bigint(val.to_integer()),
+        other => other,
+    };
+
+    match truncated {
+        Number::Int(n) => Ok(MalValue::new(Int(n))),
+        _ => Err(MalError::RustFunction(format!(
+            "Value out of range for int: {}",
+            pr_str(&args[0], true)
+        ))),
+    }
+}
+
+fn is_number(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new_boolean(args[0].is_number()))
+}
+
+fn is_bigint(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new_boolean(matches!(
+        *args[0].mal_type,
+        BigInt(_)
+    )))
+}
+
+fn numerator(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(number::numerator(&Number::from_arg("numerator", 0, &args[0])?)?.into_value())
+}
+
+fn denominator(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(number::denominator(&Number::from_arg("denominator", 0, &args[0])?)?.into_value())
+}
+
+fn rationalize(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    match Number::from_arg("rationalize", 0, &args[0])? {
+        Number::Float(val) => Ok(number::rationalize(val)?.into_value()),
+        exact => Ok(exact.into_value()),
+    }
+}
+
+fn is_fn(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new_boolean(args[0].is_function()))
+}
+
+fn is_macro(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new_boolean(args[0].is_macro()))
+}
+
+fn time_ms(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 0)?;
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| MalError::RustFunction("Could not calculate the current time.".to_string()))?
+        .as_millis();
+
+    Ok(MalValue::new(Int(millis as i64)))
+}
+
+fn seq(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    args[0].to_seq()
+}
+
+/// Wraps a step function in an unrealized lazy sequence. When realized, the
+/// step is called with `args` and must return a sequence, usually a cell built
+/// with `lazy_cons` whose rest is the next `lazy_seq`.
+fn lazy_seq(
+    step: fn(&[MalValue], &mut Env) -> MalResult,
+    args: Vec<MalValue>,
+    env: &Env,
+) -> MalValue {
+    MalValue::new_lazy_seq(MalLazySeq::new(MalValue::new_rust_func(step, env), args))
+}
+
+fn lazy_cons(first: MalValue, rest: MalValue) -> MalValue {
+    MalValue::new_lazy_seq(MalLazySeq::cons(first, rest))
+}
+
+fn is_truthy(value: &MalValue) -> bool {
+    !matches!(*value.mal_type, Nil | False)
+}
+
+fn concat_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    for (i, arg) in args.iter().enumerate() {
+        if let Some((first, rest)) = arg.first_rest()? {
+            let mut colls = vec![rest];
+            colls.extend_from_slice(&args[i + 1..]);
+
+            return Ok(lazy_cons(first, lazy_seq(concat_step, colls, env)));
+        }
+    }
+
+    Ok(MalValue::nil())
+}
+
+fn map_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    let mut function_args = Vec::with_capacity(args.len() - 1);
+    let mut next_args = vec![args[0].clone()];
+
+    for coll in &args[1..] {
+        match coll.first_rest()? {
+            Some((first, rest)) => {
+                function_args.push(first);
+                next_args.push(rest);
+            }
+            None => return Ok(MalValue::nil()),
+        }
+    }
+
+    Ok(lazy_cons(
+        core_apply(&args[0], &function_args)?,
+        lazy_seq(map_step, next_args, env),
+    ))
+}
+
+fn filter(args: &[MalValue], env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    Ok(lazy_seq(
+        filter_step,
+        vec![
+            args[0].clone(),
+            args[1].clone(),
+            MalValue::new_boolean(true),
+        ],
+        env,
+    ))
+}
+
+fn remove(args: &[MalValue], env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    Ok(lazy_seq(
+        filter_step,
+        vec![
+            args[0].clone(),
+            args[1].clone(),
+            MalValue::new_boolean(false),
+        ],
+        env,
+    ))
+}
+
+/// `args` holds the predicate, the collection and whether to keep the elements
+/// that satisfy the predicate (`filter`) or the ones that don't (`remove`).
+fn filter_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    let keep = is_truthy(&args[2]);
+    let mut coll = args[1].clone();
+
+    while let Some((first, rest)) = coll.first_rest()? {
+        if is_truthy(&core_apply(&args[0], slice::from_ref(&first))?) == keep {
+            return Ok(lazy_cons(
+                first,
+                lazy_seq(
+                    filter_step,
+                    vec![args[0].clone(), rest, args[2].clone()],
+                    env,
+                ),
+            ));
+        }
+
+        coll = rest;
+    }
+
+    Ok(MalValue::nil())
+}
+
+fn take(args: &[MalValue], env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+    get_int_arg("take", 0, &args[0])?;
+
+    Ok(lazy_seq(take_step, args.to_vec(), env))
+}
+
+fn take_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    let n = get_int_arg("take", 0, &args[0])?;
+
+    if n <= 0 {
+        return Ok(MalValue::nil());
+    }
+
+    match args[1].first_rest()? {
+        Some((first, rest)) => Ok(lazy_cons(
+            first,
+            lazy_seq(take_step, vec![MalValue::new(Int(n - 1)), rest], env),
+        )),
+        None => Ok(MalValue::nil()),
+    }
+}
+
+fn drop(args: &[MalValue], env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+    get_int_arg("drop", 0, &args[0])?;
+
+    Ok(lazy_seq(drop_step, args.to_vec(), env))
+}
+
+fn drop_step(args: &[MalValue], _env: &mut Env) -> MalResult {
+    let mut coll = args[1].clone();
+
+    for _ in 0..get_int_arg("drop", 0, &args[0])? {
+        coll = match coll.first_rest()? {
+            Some((_, rest)) => rest,
+            None => return Ok(MalValue::nil()),
+        };
+    }
+
+    Ok(coll)
+}
+
+fn range(args: &[MalValue], env: &mut Env) -> MalResult {
+    let zero = MalValue::new(Int(0));
+    let one = MalValue::new(Int(1));
+
+    let (start, end, step) = match args.len() {
+        0 => (zero, MalValue::nil(), one),
+        1 => (zero, args[0].clone(), one),
+        2 => (args[0].clone(), args[1].clone(), one),
+        3 => (args[0].clone(), args[1].clone(), args[2].clone()),
+        _ => {
+            return Err(MalError::RustFunction(format!(
+                "Expected at most 3 arguments, got {}",
+                args.len()
+            )))
+        }
+    };
+
+    for (index, arg) in args.iter().enumerate() {
+        Number::from_arg("range", index, arg)?;
+    }
+
+    Ok(lazy_seq(range_step, vec![start, end, step], env))
+}
+
+fn range_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    let start = Number::from_arg("range", 0, &args[0])?;
+    let step = Number::from_arg("range", 2, &args[2])?;
+
+    if !args[1].is_nil() {
+        let end = Number::from_arg("range", 1, &args[1])?;
+
+        let done = match step.partial_cmp(&Number::Int(0)) {
+            Some(Ordering::Greater) => start.partial_cmp(&end) != Some(Ordering::Less),
+            Some(Ordering::Less) => start.partial_cmp(&end) != Some(Ordering::Greater),
+            _ => start.partial_cmp(&end) == Some(Ordering::Equal),
+        };
+
+        if done {
+            return Ok(MalValue::nil());
+        }
+    }
+
+    let next = start.add(&step)?.into_value();
+
+    Ok(lazy_cons(
+        args[0].clone(),
+        lazy_seq(
+            range_step,
+            vec![next, args[1].clone(), args[2].clone()],
+            env,
+        ),
+    ))
+}
+
+fn iterate(args: &[MalValue], env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    Ok(lazy_cons(
+        args[1].clone(),
+        lazy_seq(iterate_step, args.to_vec(), env),
+    ))
+}
+
+fn iterate_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    let next = core_apply(&args[0], &args[1..])?;
+
+    Ok(lazy_cons(
+        next.clone(),
+        lazy_seq(iterate_step, vec![args[0].clone(), next], env),
+    ))
+}
+
+fn repeat(args: &[MalValue], env: &mut Env) -> MalResult {
+    match args.len() {
+        1 => Ok(lazy_seq(
+            repeat_step,
+            vec![args[0].clone(), MalValue::nil()],
+            env,
+        )),
+        2 => {
+            get_int_arg("repeat", 0, &args[0])?;
+
+            Ok(lazy_seq(
+                repeat_step,
+                vec![args[1].clone(), args[0].clone()],
+                env,
+            ))
+        }
+        _ => Err(MalError::RustFunction(format!(
+            "Expected 1 or 2 arguments, got {}",
+            args.len()
+        ))),
+    }
+}
+
+/// `args` holds the repeated value and how many times to repeat it, with nil
+/// meaning forever.
+fn repeat_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    let remaining = if args[1].is_nil() {
+        MalValue::nil()
+    } else {
+        let n = get_int_arg("repeat", 0, &args[1])?;
+        if n <= 0 {
+            return Ok(MalValue::nil());
+        }
+
+        MalValue::new(Int(n - 1))
+    };
+
+    Ok(lazy_cons(
+        args[0].clone(),
+        lazy_seq(repeat_step, vec![args[0].clone(), remaining], env),
+    ))
+}
+
+fn cycle(args: &[MalValue], env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+    args[0].first_rest()?;
+
+    Ok(lazy_seq(
+        cycle_step,
+        vec![args[0].clone(), args[0].clone()],
+        env,
+    ))
+}
+
+/// `args` holds the whole collection and what is left of the current cycle.
+fn cycle_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    let next = match args[1].first_rest()? {
+        None => args[0].first_rest()?,
+        next => next,
+    };
+
+    match next {
+        Some((first, rest)) => Ok(lazy_cons(
+            first,
+            lazy_seq(cycle_step, vec![args[0].clone(), rest], env),
+        )),
+        None => Ok(MalValue::nil()),
+    }
+}
+
+fn is_realized(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    if let LazySeq(ref lazy_seq) = *args[0].mal_type {
+        Ok(MalValue::new_boolean(lazy_seq.is_realized()))
+    } else {
+        Err(MalError::RustFunction(
+            "Argument must be a lazy sequence".to_string(),
+        ))
+    }
+}
+
+fn take_while(args: &[MalValue], env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    Ok(lazy_seq(take_while_step, args.to_vec(), env))
+}
+
+fn take_while_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    match args[1].first_rest()? {
+        Some((first, rest)) if is_truthy(&core_apply(&args[0], slice::from_ref(&first))?) => {
+            Ok(lazy_cons(
+                first,
+                lazy_seq(take_while_step, vec![args[0].clone(), rest], env),
+            ))
+        }
+        _ => Ok(MalValue::nil()),
+    }
+}
+
+fn drop_while(args: &[MalValue], env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    Ok(lazy_seq(drop_while_step, args.to_vec(), env))
+}
+
+fn drop_while_step(args: &[MalValue], _env: &mut Env) -> MalResult {
+    let mut coll = args[1].clone();
+
+    while let Some((first, rest)) = coll.first_rest()? {
+        if !is_truthy(&core_apply(&args[0], &[first])?) {
+            break;
+        }
+
+        coll = rest;
+    }
+
+    Ok(coll)
+}
+
+fn partition(args: &[MalValue], env: &mut Env) -> MalResult {
+    let step_args = match args.len() {
+        2 => vec![args[0].clone(), args[0].clone(), args[1].clone()],
+        3 => args.to_vec(),
+        4 => vec![
+            args[0].clone(),
+            args[1].clone(),
+            args[3].clone(),
+            args[2].clone(),
+        ],
+        _ => {
+            return Err(MalError::RustFunction(format!(
+                "Expected 2 to 4 arguments, got {}",
+                args.len()
+            )))
+        }
+    };
+
+    if get_int_arg("partition", 0, &step_args[0])? <= 0
+        || get_int_arg("partition", 1, &step_args[1])? <= 0
+    {
+        return Err(MalError::RustFunction(
+            "partition size and step must be positive".to_string(),
+        ));
+    }
+
+    Ok(lazy_seq(partition_step, step_args, env))
+}
+
+/// `args` holds the partition size, the step, the collection and, optionally,
+/// the padding used to complete the last partition.
+fn partition_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    let n = get_int_arg("partition", 0, &args[0])? as usize;
+    let step = get_int_arg("partition", 1, &args[1])?;
+
+    let mut partition = args[2].seq_iter().take(n).collect::<Result<Vec<_>, _>>()?;
+
+    if partition.len() < n {
+        return match args.get(3) {
+            Some(pad) if !partition.is_empty() => {
+                for value in pad.seq_iter().take(n - partition.len()) {
+                    partition.push(value?);
+                }
+
+                Ok(MalValue::new_list(vec![MalValue::new_list(partition)]))
+            }
+            _ => Ok(MalValue::nil()),
+        };
+    }
+
+    let mut next_args = args.to_vec();
+    for _ in 0..step {
+        next_args[2] = match next_args[2].first_rest()? {
+            Some((_, rest)) => rest,
+            None => break,
+        };
+    }
+
+    Ok(lazy_cons(
+        MalValue::new_list(partition),
+        lazy_seq(partition_step, next_args, env),
+    ))
+}
+
+fn interleave(args: &[MalValue], env: &mut Env) -> MalResult {
+    Ok(lazy_seq(interleave_step, args.to_vec(), env))
+}
+
+fn interleave_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    if args.is_empty() {
+        return Ok(MalValue::nil());
+    }
+
+    let mut firsts = Vec::with_capacity(args.len());
+    let mut rests = Vec::with_capacity(args.len());
+
+    for coll in args {
+        match coll.first_rest()? {
+            Some((first, rest)) => {
+                firsts.push(first);
+                rests.push(rest);
+            }
+            None => return Ok(MalValue::nil()),
+        }
+    }
+
+    Ok(firsts
+        .into_iter()
+        .rev()
+        .fold(lazy_seq(interleave_step, rests, env), |seq, first| {
+            lazy_cons(first, seq)
+        }))
+}
+
+fn distinct(args: &[MalValue], env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(lazy_seq(
+        distinct_step,
+        vec![args[0].clone(), MalValue::new_set(MalSet::new())],
+        env,
+    ))
+}
+
+/// `args` holds the collection and the set of elements already returned.
+fn distinct_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    let seen = get_set_arg(&args[1])?;
+    let mut coll = args[0].clone();
+
+    while let Some((first, rest)) = coll.first_rest()? {
+        if !seen.contains(&first) {
+            let seen = MalValue::new_set(seen.conj(slice::from_ref(&first)));
+
+            return Ok(lazy_cons(
+                first,
+                lazy_seq(distinct_step, vec![rest, seen], env),
+            ));
+        }
+
+        coll = rest;
+    }
+
+    Ok(MalValue::nil())
+}
+
+fn mapcat(args: &[MalValue], env: &mut Env) -> MalResult {
+    let colls = map(args, env)?;
+
+    Ok(lazy_seq(cat_step, vec![colls, MalValue::nil()], env))
+}
+
+/// `args` holds the remaining collections and what is left of the current one.
+fn cat_step(args: &[MalValue], env: &mut Env) -> MalResult {
+    let mut colls = args[0].clone();
+    let mut current = args[1].clone();
+
+    loop {
+        if let Some((first, rest)) = current.first_rest()? {
+            return Ok(lazy_cons(first, lazy_seq(cat_step, vec![colls, rest], env)));
+        }
+
+        match colls.first_rest()? {
+            Some((next, rest)) => {
+                current = next;
+                colls = rest;
+            }
+            None => return Ok(MalValue::nil()),
+        }
+    }
+}
+
+fn reduce(args: &[MalValue], _env: &mut Env) -> MalResult {
+    let (init, coll) = match args.len() {
+        2 => match args[1].first_rest()? {
+            Some(first_rest) => first_rest,
+            None => return core_apply(&args[0], &[]),
+        },
+        3 => (args[1].clone(), args[2].clone()),
+        _ => {
+            return Err(MalError::RustFunction(format!(
+                "Expected 2 or 3 arguments, got {}",
+                args.len()
+            )))
+        }
+    };
+
+    let mut result = init;
+
+    for value in coll.seq_iter() {
+        result = core_apply(&args[0], &[result, value?])?;
+
+        if let Reduced(ref val) = *result.mal_type {
+            return Ok(val.clone());
+        }
+    }
+
+    Ok(result)
+}
+
+fn reduced(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new_reduced(args[0].clone()))
+}
+
+fn is_reduced(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new_boolean(matches!(
+        *args[0].mal_type,
+        Reduced(_)
+    )))
+}
+
+fn reverse(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    Ok(MalValue::new_list(
+        args[0]
+            .seq_elements()?
+            .into_iter()
+            .rev()
+            .collect::<im_rc::Vector<_>>(),
+    ))
+}
+
+fn sort(args: &[MalValue], _env: &mut Env) -> MalResult {
+    let (comparator, coll) = match args.len() {
+        1 => (MalValue::nil(), &args[0]),
+        2 => (args[0].clone(), &args[1]),
+        _ => {
+            return Err(MalError::RustFunction(format!(
+                "Expected 1 or 2 arguments, got {}",
+                args.len()
+            )))
+        }
+    };
+
+    let values = sort_values(coll.seq_elements()?.into_iter().collect(), &mut |a, b| {
+        compare_with(&comparator, a, b)
+    })?;
+
+    Ok(MalValue::new_list(values))
+}
+
+fn sort_by(args: &[MalValue], _env: &mut Env) -> MalResult {
+    let (comparator, coll) = match args.len() {
+        2 => (MalValue::nil(), &args[1]),
+        3 => (args[1].clone(), &args[2]),
+        _ => {
+            return Err(MalError::RustFunction(format!(
+                "Expected 2 or 3 arguments, got {}",
+                args.len()
+            )))
+        }
+    };
+
+    let keyed_values = coll
+        .seq_elements()?
+        .into_iter()
+        .map(|value| Ok((core_apply(&args[0], slice::from_ref(&value))?, value)))
+        .collect::<Result<Vec<_>, MalError>>()?;
+    let keyed_values = sort_values(keyed_values, &mut |(a, _), (b, _)| {
+        compare_with(&comparator, a, b)
+    })?;
+
+    Ok(MalValue::new_list(
+        keyed_values
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// A stable merge sort with a fallible comparison, which stops at the first
+/// error. Unlike `slice::sort_by`, it cannot panic when a mal comparator is
+/// not a total order; the result is then in some unspecified order.
+fn sort_values<T>(
+    mut values: Vec<T>,
+    cmp: &mut impl FnMut(&T, &T) -> Result<Ordering, MalError>,
+) -> Result<Vec<T>, MalError> {
+    if values.len() <= 1 {
+        return Ok(values);
+    }
+
+    let right = values.split_off(values.len() / 2);
+    let mut left = sort_values(values, cmp)?.into_iter().peekable();
+    let mut right = sort_values(right, cmp)?.into_iter().peekable();
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        let next = if cmp(r, l)? == Ordering::Less {
+            right.next()
+        } else {
+            left.next()
+        };
+        merged.extend(next);
+    }
+
+    merged.extend(left);
+    merged.extend(right);
+
+    Ok(merged)
+}
+
+fn group_by(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    let mut groups = MalMap::new();
+
+    for value in args[1].seq_iter() {
+        let value = value?;
+        let key = core_apply(&args[0], slice::from_ref(&value))?;
+
+        let group = match *groups.get(&key).mal_type {
+            Vector(MalVector { ref vec, .. }) => {
+                let mut vec = vec.clone();
+                vec.push_back(value);
+                vec
+            }
+            _ => im_rc::Vector::unit(value),
+        };
+
+        groups = groups.assoc(&[key, MalValue::new_vector(group)])?;
+    }
+
+    Ok(MalValue::new_map(groups))
+}
+
+fn frequencies(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    let mut counts = MalMap::new();
+
+    for value in args[0].seq_iter() {
+        let value = value?;
+
+        let count = match *counts.get(&value).mal_type {
+            Int(n) => n + 1,
+            _ => 1,
+        };
+
+        counts = counts.assoc(&[value, MalValue::new(Int(count))])?;
+    }
+
+    Ok(MalValue::new_map(counts))
+}
+
+fn some(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    for value in args[1].seq_iter() {
+        let result = core_apply(&args[0], &[value?])?;
+
+        if is_truthy(&result) {
+            return Ok(result);
+        }
+    }
+
+    Ok(MalValue::nil())
+}
+
+fn every(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    for value in args[1].seq_iter() {
+        if !is_truthy(&core_apply(&args[0], &[value?])?) {
+            return Ok(MalValue::new_boolean(false));
+        }
+    }
+
+    Ok(MalValue::new_boolean(true))
+}
+
+fn into(args: &[MalValue], env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    let values = args[1].seq_elements()?;
+
+    if values.is_empty() {
+        return Ok(args[0].clone());
+    }
+
+    let mut conj_args = Vec::with_capacity(values.len() + 1);
+    conj_args.push(args[0].clone());
+    conj_args.extend(values);
+
+    conj(&conj_args, env)
+}
+
+/// Flattens `[key value]` vectors and maps into the key/value arguments
+/// taken by `assoc`.
+fn map_entries_to_arguments(entries: &[MalValue]) -> Result<Vec<MalValue>, MalError> {
+    let mut arguments = Vec::with_capacity(entries.len() * 2);
+
+    for entry in entries {
+        match *entry.mal_type {
+            Vector(MalVector { ref vec, .. }) if vec.len() == 2 => {
+                arguments.extend(vec.iter().cloned())
+            }
+            Map(_) | SortedMap(_) => {
+                for map_entry in entry.seq_iter() {
+                    arguments.extend(map_entry?.seq_elements()?);
+                }
+            }
+            Nil => {}
+            _ => {
+                return Err(MalError::RustFunction(
+                    "Map entries must be vectors of a key and a value".to_string(),
+                ))
+            }
+        }
+    }
+
+    Ok(arguments)
 }
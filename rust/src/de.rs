@@ -0,0 +1,469 @@
+use crate::printer::pr_str;
+use crate::reader::read_str;
+use crate::seq::Seqable;
+use crate::serde_error::SerdeError;
+use crate::types::MalValue;
+use crate::types::MalValueType::{
+    BigInt, Char, False, Float, Int, Keyword, LazySeq, List, Map, Nil, Ratio, Set, SortedMap,
+    SortedSet, Str, Symbol, True, Vector,
+};
+use num_traits::ToPrimitive;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+/// The most elements realized from a lazy sequence, so that deserializing an
+/// infinite one such as `(range)` fails instead of running forever.
+const MAX_LAZY_SEQ_LEN: usize = 1 << 20;
+
+/// Converts a mal value, such as one read from a configuration file, into a
+/// Rust value. Map keys may be keywords or strings; both match struct fields
+/// by name. Lazy sequences are realized, up to `MAX_LAZY_SEQ_LEN` elements.
+pub fn from_value<T: DeserializeOwned>(value: &MalValue) -> Result<T, SerdeError> {
+    T::deserialize(Deserializer::new(value.clone()))
+}
+
+/// Reads a single form with `reader::read_str` and converts it, without
+/// evaluating it.
+pub fn from_str<T: DeserializeOwned>(program: &str) -> Result<T, SerdeError> {
+    from_value(&read_str(program)?)
+}
+
+pub struct Deserializer {
+    value: MalValue,
+}
+
+impl Deserializer {
+    pub fn new(value: MalValue) -> Deserializer {
+        Deserializer { value }
+    }
+}
+
+fn custom_error(message: String) -> SerdeError {
+    <SerdeError as de::Error>::custom(message)
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match *self.value.mal_type {
+            Nil => visitor.visit_unit(),
+            True => visitor.visit_bool(true),
+            False => visitor.visit_bool(false),
+            Int(val) => visitor.visit_i64(val),
+            BigInt(ref val) => match (val.to_u64(), val.to_i128()) {
+                (Some(val), _) => visitor.visit_u64(val),
+                (None, Some(val)) => visitor.visit_i128(val),
+                _ => Err(custom_error(format!("Integer is too large: {}", val))),
+            },
+            Ratio(ref val) => visitor.visit_f64(val.to_f64().unwrap_or(f64::NAN)),
+            Float(val) => visitor.visit_f64(val),
+            Char(val) => visitor.visit_char(val),
+            Str(ref s) | Keyword(ref s) | Symbol(ref s) => visitor.visit_str(s),
+            LazySeq(_) => {
+                let elements = self
+                    .value
+                    .seq_iter()
+                    .take(MAX_LAZY_SEQ_LEN + 1)
+                    .collect::<Result<im_rc::Vector<_>, _>>()?;
+
+                if elements.len() > MAX_LAZY_SEQ_LEN {
+                    return Err(custom_error(format!(
+                        "Lazy sequence has more than {} elements",
+                        MAX_LAZY_SEQ_LEN
+                    )));
+                }
+
+                visit_elements(elements, visitor)
+            }
+            List(_) | Vector(_) | Set(_) | SortedSet(_) => {
+                visit_elements(self.value.seq_elements()?, visitor)
+            }
+            Map(ref mal_map) => visitor.visit_map(MapDeserializer::new(mal_map.iter())),
+            SortedMap(ref sorted_map) => visitor.visit_map(MapDeserializer::new(sorted_map.iter())),
+            _ => Err(custom_error(format!(
+                "Cannot deserialize {}",
+                pr_str(&self.value, true)
+            ))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match *self.value.mal_type {
+            Nil => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants are written as keywords or strings, and other variants
+    /// as a map with a single entry from the variant name to its content.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match *self.value.mal_type {
+            Str(ref name) | Keyword(ref name) => visitor.visit_enum(EnumDeserializer {
+                variant: name.clone(),
+                value: None,
+            }),
+            Map(ref mal_map) if mal_map.len() == 1 => {
+                let (key, value) = mal_map.iter().next().unwrap();
+                visitor.visit_enum(EnumDeserializer::from_entry(key, value))
+            }
+            SortedMap(ref sorted_map) if sorted_map.len() == 1 => {
+                let (key, value) = sorted_map.iter().next().unwrap();
+                visitor.visit_enum(EnumDeserializer::from_entry(key, value))
+            }
+            _ => Err(custom_error(format!(
+                "Expected a keyword, a string or a map with a single entry, got {}",
+                pr_str(&self.value, true)
+            ))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+fn visit_elements<'de, V: Visitor<'de>>(
+    elements: im_rc::Vector<MalValue>,
+    visitor: V,
+) -> Result<V::Value, SerdeError> {
+    let mut seq = SeqDeserializer::new(elements);
+    let result = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(result)
+}
+
+/// Names map keys in error paths.
+fn key_name(key: &MalValue) -> String {
+    match *key.mal_type {
+        Str(ref name) | Keyword(ref name) => name.clone(),
+        _ => pr_str(key, true),
+    }
+}
+
+struct SeqDeserializer {
+    elements: std::vec::IntoIter<MalValue>,
+    index: usize,
+}
+
+impl SeqDeserializer {
+    fn new(elements: im_rc::Vector<MalValue>) -> SeqDeserializer {
+        SeqDeserializer {
+            elements: elements.into_iter().collect::<Vec<_>>().into_iter(),
+            index: 0,
+        }
+    }
+
+    /// Fails if the visitor did not consume every element, as happens when a
+    /// tuple is deserialized from a longer sequence.
+    fn end(self) -> Result<(), SerdeError> {
+        match self.elements.len() {
+            0 => Ok(()),
+            remaining => Err(de::Error::invalid_length(
+                self.index + remaining,
+                &format!("{} elements", self.index).as_str(),
+            )),
+        }
+    }
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, SerdeError> {
+        let value = match self.elements.next() {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        let index = self.index;
+        self.index += 1;
+
+        seed.deserialize(Deserializer::new(value))
+            .map(Some)
+            .map_err(|err| err.at_index(index))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+struct MapDeserializer {
+    entries: std::vec::IntoIter<(MalValue, MalValue)>,
+    value: Option<(String, MalValue)>,
+}
+
+impl MapDeserializer {
+    fn new<'a>(entries: impl Iterator<Item = (&'a MalValue, &'a MalValue)>) -> MapDeserializer {
+        MapDeserializer {
+            entries: entries
+                .map(|(key, val)| (key.clone(), val.clone()))
+                .collect::<Vec<_>>()
+                .into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SerdeError> {
+        let (key, value) = match self.entries.next() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let name = key_name(&key);
+        self.value = Some((name.clone(), value));
+
+        seed.deserialize(Deserializer::new(key))
+            .map(Some)
+            .map_err(|err| err.in_field(name))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SerdeError> {
+        let (name, value) = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+
+        seed.deserialize(Deserializer::new(value))
+            .map_err(|err| err.in_field(name))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: Option<MalValue>,
+}
+
+impl EnumDeserializer {
+    fn from_entry(key: &MalValue, value: &MalValue) -> EnumDeserializer {
+        EnumDeserializer {
+            variant: key_name(key),
+            value: Some(value.clone()),
+        }
+    }
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = SerdeError;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantDeserializer), SerdeError> {
+        let name: de::value::StringDeserializer<SerdeError> =
+            self.variant.clone().into_deserializer();
+        let variant = seed.deserialize(name)?;
+
+        Ok((
+            variant,
+            VariantDeserializer {
+                variant: self.variant,
+                value: self.value,
+            },
+        ))
+    }
+}
+
+struct VariantDeserializer {
+    variant: String,
+    value: Option<MalValue>,
+}
+
+impl VariantDeserializer {
+    fn content(self) -> Result<(String, Deserializer), SerdeError> {
+        match self.value {
+            Some(value) => Ok((self.variant, Deserializer::new(value))),
+            None => Err(custom_error(format!(
+                "Variant {} expects content",
+                self.variant
+            ))),
+        }
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        match self.value {
+            None => Ok(()),
+            Some(ref value) if value.is_nil() => Ok(()),
+            Some(_) => Err(custom_error(format!(
+                "Variant {} expects no content",
+                self.variant
+            ))),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, SerdeError> {
+        let (variant, content) = self.content()?;
+
+        seed.deserialize(content)
+            .map_err(|err| err.in_field(variant))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        let (variant, content) = self.content()?;
+
+        de::Deserializer::deserialize_seq(content, visitor).map_err(|err| err.in_field(variant))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        let (variant, content) = self.content()?;
+
+        de::Deserializer::deserialize_map(content, visitor).map_err(|err| err.in_field(variant))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Interpreter;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Server {
+        host: String,
+        port: u16,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Mode {
+        Fast,
+        Limited(u32),
+        Custom { level: i8 },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        servers: Vec<Server>,
+        modes: Vec<Mode>,
+        ratio: f64,
+        retries: Option<u8>,
+        limits: HashMap<String, (i64, i64)>,
+    }
+
+    #[test]
+    fn test_from_str() {
+        let config: Config = from_str(
+            r#"{:name "prod"
+                "servers" [{:host "a" :port 80 :tags [:web]} {:host "b" :port 8080}]
+                :modes [:Fast {:Limited 3} {"Custom" {:level -1}}]
+                :ratio 1/2
+                :retries nil
+                :limits {:cpu [1 4]}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                name: "prod".to_string(),
+                servers: vec![
+                    Server {
+                        host: "a".to_string(),
+                        port: 80,
+                        tags: vec!["web".to_string()],
+                    },
+                    Server {
+                        host: "b".to_string(),
+                        port: 8080,
+                        tags: vec![],
+                    },
+                ],
+                modes: vec![Mode::Fast, Mode::Limited(3), Mode::Custom { level: -1 }],
+                ratio: 0.5,
+                retries: None,
+                limits: vec![("cpu".to_string(), (1, 4))].into_iter().collect(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_errors_give_the_path() {
+        let err = from_str::<Vec<Server>>(r#"[{:host "a" :port 80} {:host "b" :port 70000}]"#)
+            .unwrap_err();
+        assert_eq!(err.path(), "[1].port");
+        assert!(err.to_string().ends_with(" at [1].port"));
+
+        let err = from_str::<Vec<Server>>(r#"[{:host "a"}]"#).unwrap_err();
+        assert_eq!(err.path(), "[0]");
+        assert!(err.message().contains("port"));
+
+        let err = from_str::<HashMap<String, Mode>>(r#"{:m {:Custom {:level "x"}}}"#).unwrap_err();
+        assert_eq!(err.path(), "m.Custom.level");
+
+        assert!(from_str::<(i64, i64)>("[1 2 3]").is_err());
+        assert!(from_str::<i64>("(").is_err());
+    }
+
+    #[test]
+    fn test_evaluated_values() {
+        let mut interpreter = Interpreter::new();
+        let mut eval = |program: &str| interpreter.eval_str(program).unwrap();
+
+        assert_eq!(
+            from_value::<Mode>(&eval("(sorted-map :Limited 3)")),
+            Ok(Mode::Limited(3))
+        );
+        assert_eq!(
+            from_value::<Vec<i64>>(&eval("(take 3 (range))")),
+            Ok(vec![0, 1, 2])
+        );
+
+        let err = from_value::<Vec<i64>>(&eval("(range)")).unwrap_err();
+        assert!(err.message().starts_with("Lazy sequence has more than"));
+    }
+}
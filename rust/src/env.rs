@@ -1,5 +1,7 @@
+use crate::eval;
 use crate::types::{MalError, MalResult, MalValue};
 use core::fmt;
+use im_rc::Vector;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -7,10 +9,20 @@ use std::rc::Rc;
 #[derive(Debug, Clone, PartialEq)]
 pub struct Env(Rc<EnvImpl>);
 
-#[derive(PartialEq)]
+/// The evaluator used by builtins such as `eval`, `swap!` and `map` that need
+/// to call back into mal code.
+pub type EvalFunc = fn(ast: &MalValue, env: &mut Env) -> MalResult;
+
 struct EnvImpl {
     data: RefCell<HashMap<String, MalValue>>,
     outer: Option<Env>,
+    eval_func: EvalFunc,
+}
+
+impl PartialEq for EnvImpl {
+    fn eq(&self, other: &EnvImpl) -> bool {
+        self.data == other.data && self.outer == other.outer
+    }
 }
 
 impl fmt::Debug for EnvImpl {
@@ -22,28 +34,41 @@ impl fmt::Debug for EnvImpl {
     }
 }
 
-fn create_env(outer: Option<&Env>) -> Env {
+/// Nested environments inherit the evaluator of their outer environment.
+fn create_env(outer: Option<&Env>, eval_func: EvalFunc) -> Env {
     Env(Rc::new(EnvImpl {
         data: RefCell::new(HashMap::new()),
         outer: outer.cloned(),
+        eval_func,
     }))
 }
 
 impl Env {
     pub fn new() -> Env {
-        create_env(None)
+        Env::with_eval_func(eval::eval)
+    }
+
+    /// Creates a root environment whose builtins evaluate mal code with
+    /// `eval_func` instead of the library's evaluator.
+    pub fn with_eval_func(eval_func: EvalFunc) -> Env {
+        create_env(None, eval_func)
     }
 
     pub fn with_outer_env(outer: &Env) -> Env {
-        create_env(Some(outer))
+        create_env(Some(outer), outer.0.eval_func)
     }
 
-    pub fn with_binds<S: AsRef<str>>(
+    pub fn with_binds<'a, S: AsRef<str>>(
         outer: Option<&Env>,
         binds: &[S],
-        exprs: &[MalValue],
+        exprs: impl IntoIterator<Item = &'a MalValue>,
     ) -> Result<Env, MalError> {
-        let mut env = create_env(outer);
+        let mut env = match outer {
+            Some(outer) => Env::with_outer_env(outer),
+            None => Env::new(),
+        };
+
+        let mut exprs = exprs.into_iter();
 
         for (i, bind) in binds.iter().enumerate() {
             if bind.as_ref() == "&" {
@@ -55,7 +80,7 @@ impl Env {
 
                 env.set(
                     binds[i + 1].as_ref(),
-                    MalValue::new_list(exprs[i..].to_vec()),
+                    MalValue::new_list(exprs.by_ref().cloned().collect::<Vector<_>>()),
                 );
 
                 break;
@@ -63,13 +88,17 @@ impl Env {
 
             env.set(
                 bind.as_ref(),
-                exprs.get(i).cloned().unwrap_or_else(MalValue::nil),
+                exprs.next().cloned().unwrap_or_else(MalValue::nil),
             )
         }
 
         Ok(env)
     }
 
+    pub fn eval(&mut self, ast: &MalValue) -> MalResult {
+        (self.0.eval_func)(ast, self)
+    }
+
     pub fn set(&mut self, symbol_key: &str, val: MalValue) {
         self.0.data.borrow_mut().insert(symbol_key.to_string(), val);
     }
@@ -101,7 +130,7 @@ impl Default for Env {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::MalValueType::{Number, Str};
+    use crate::types::MalValueType::{Int, Str};
 
     fn undefined_symbol_err(symbol_key: &str) -> MalResult {
         Err(MalError::UndefinedSymbol(symbol_key.to_string()))
@@ -143,8 +172,8 @@ mod tests {
 
     #[test]
     fn test_symbol_hiding() {
-        let val1 = MalValue::new(Number(1.));
-        let val2 = MalValue::new(Number(2.));
+        let val1 = MalValue::new(Int(1));
+        let val2 = MalValue::new(Int(2));
 
         let mut env1 = Env::new();
         env1.set("sym1", val1.clone());
@@ -173,7 +202,7 @@ mod tests {
         let val1 = MalValue::new(Str("abc".to_string()));
         env1.set("sym", val1.clone());
 
-        let val2 = MalValue::new(Number(1.));
+        let val2 = MalValue::new(Int(1));
         let env2 = Env::with_binds(Some(&env1), &["sym"], &[val2.clone()]).unwrap();
 
         let env3 = Env::with_outer_env(&env1);
@@ -184,7 +213,7 @@ mod tests {
 
     #[test]
     fn test_with_binds() {
-        let val1 = MalValue::new(Number(1.));
+        let val1 = MalValue::new(Int(1));
         let val2 = MalValue::new(Str("abc".to_string()));
 
         let env = Env::with_binds(
@@ -200,7 +229,7 @@ mod tests {
 
     #[test]
     fn test_with_binds_extra_exprs() {
-        let val1 = MalValue::new(Number(1.));
+        let val1 = MalValue::new(Int(1));
         let val2 = MalValue::new(Str("abc".to_string()));
         let val3 = MalValue::new(Str("xyz".to_string()));
 
@@ -217,7 +246,7 @@ mod tests {
 
     #[test]
     fn test_with_binds_extra_binds() {
-        let val1 = MalValue::new(Number(1.));
+        let val1 = MalValue::new(Int(1));
         let val2 = MalValue::new(Str("abc".to_string()));
 
         let env = Env::with_binds(
@@ -235,9 +264,9 @@ mod tests {
 
     #[test]
     fn test_with_binds_variadic() {
-        let val1 = MalValue::new(Number(1.));
+        let val1 = MalValue::new(Int(1));
         let val2 = MalValue::new(Str("abc".to_string()));
-        let val3 = MalValue::new(Number(2.));
+        let val3 = MalValue::new(Int(2));
 
         let env = Env::with_binds(
             None,
@@ -252,9 +281,9 @@ mod tests {
 
     #[test]
     fn test_with_binds_variadic_only() {
-        let val1 = MalValue::new(Number(1.));
+        let val1 = MalValue::new(Int(1));
         let val2 = MalValue::new(Str("abc".to_string()));
-        let val3 = MalValue::new(Number(2.));
+        let val3 = MalValue::new(Int(2));
 
         let env = Env::with_binds(
             None,
@@ -268,4 +297,18 @@ mod tests {
             Ok(MalValue::new_list(vec![val1, val2, val3,]))
         );
     }
+
+    #[test]
+    fn test_eval_func_is_inherited() {
+        fn eval_to_zero(_: &MalValue, _: &mut Env) -> MalResult {
+            Ok(MalValue::new(Int(0)))
+        }
+
+        let root = Env::with_eval_func(eval_to_zero);
+        let mut inner = Env::with_binds(Some(&Env::with_outer_env(&root)), &["a"], &[]).unwrap();
+        let ast = MalValue::new(Str("abc".to_string()));
+
+        assert_eq!(inner.eval(&ast), Ok(MalValue::new(Int(0))));
+        assert_eq!(Env::new().eval(&ast), Ok(ast));
+    }
 }
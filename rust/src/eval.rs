@@ -0,0 +1,523 @@
+use crate::env::Env;
+use crate::eval::ApplyOkResult::{Return, TailCall};
+use crate::types::MalValueType;
+use crate::types::MalValueType::{List, MalFunc, Map, Nil, RustFunc, Set, Str, Symbol, Vector};
+use crate::types::{MalError, MalLazySeq, MalList, MalMap, MalResult, MalSet, MalValue, MalVector};
+use std::iter::once;
+
+enum ApplyOkResult {
+    Return(MalValue),
+    TailCall(MalValue, Env),
+}
+
+type ApplyResult = Result<ApplyOkResult, MalError>;
+
+pub fn eval(ast: &MalValue, env: &mut Env) -> MalResult {
+    let mut cur_ast = ast.clone();
+    let mut cur_env = env.clone();
+
+    loop {
+        cur_ast = macroexpand(&cur_ast, env).map_err(|err| err.at(&cur_ast))?;
+
+        match *cur_ast.mal_type {
+            List(ref mal_list) if mal_list.vec.is_empty() => return Ok(cur_ast.clone()),
+            List(MalList { vec: ref list, .. }) => {
+                let first_arg = &list[0];
+
+                let apply_result = match *first_arg.mal_type {
+                    Symbol(ref name) if name == "def!" => {
+                        apply_special_form_def(list, &mut cur_env)
+                    }
+                    Symbol(ref name) if name == "let*" => apply_special_form_let(list, &cur_env),
+                    Symbol(ref name) if name == "fn*" => apply_special_form_fn(list, &cur_env),
+                    Symbol(ref name) if name == "do" => apply_special_form_do(list, &mut cur_env),
+                    Symbol(ref name) if name == "if" => apply_special_form_if(list, &mut cur_env),
+                    Symbol(ref name) if name == "quote" => {
+                        apply_special_form_quote(list, &mut cur_env)
+                    }
+                    Symbol(ref name) if name == "quasiquote" => {
+                        apply_special_form_quasiquote(list, &mut cur_env)
+                    }
+                    Symbol(ref name) if name == "defmacro!" => {
+                        apply_special_form_defmacro(list, &mut cur_env)
+                    }
+                    Symbol(ref name) if name == "macroexpand" => {
+                        apply_special_form_macroexpand(list, &mut cur_env)
+                    }
+                    Symbol(ref name) if name == "try*" => {
+                        apply_special_form_try(list, &mut cur_env)
+                    }
+                    Symbol(ref name) if name == "lazy-seq" => {
+                        apply_special_form_lazy_seq(list, &cur_env)
+                    }
+                    _ => apply_ast(list, &mut cur_env),
+                }
+                .map_err(|err| err.at(&cur_ast))?;
+
+                match apply_result {
+                    Return(mal_value) => return Ok(mal_value),
+                    TailCall(mal_val, new_env) => {
+                        cur_ast = mal_val;
+                        cur_env = new_env;
+                    }
+                }
+            }
+            _ => return eval_ast(&cur_ast, &mut cur_env).map_err(|err| err.at(&cur_ast)),
+        };
+    }
+}
+
+fn eval_ast(ast: &MalValue, env: &mut Env) -> MalResult {
+    match *ast.mal_type {
+        Symbol(ref s) => env.get(&s),
+        List(ref mal_list) => Ok(MalValue::new_list(eval_ast_seq(&mal_list.vec, env)?)),
+        Vector(ref mal_vec) => Ok(MalValue::new_vector(eval_ast_seq(&mal_vec.vec, env)?)),
+        Map(ref mal_map) => eval_map(mal_map, env),
+        Set(ref mal_set) => {
+            let elems: Vec<_> = mal_set.iter().cloned().collect();
+            Ok(MalValue::new_set(MalSet::from_values(&eval_ast_seq(
+                &elems, env,
+            )?)))
+        }
+        _ => Ok(ast.clone()),
+    }
+}
+
+fn eval_ast_seq<'a>(
+    seq: impl IntoIterator<Item = &'a MalValue>,
+    env: &mut Env,
+) -> Result<Vec<MalValue>, MalError> {
+    seq.into_iter().map(|mal_val| eval(mal_val, env)).collect()
+}
+
+fn eval_map(mal_map: &MalMap, env: &mut Env) -> MalResult {
+    let map_args: Result<Vec<_>, _> = mal_map
+        .iter()
+        .flat_map(|(key, val)| once(eval(key, env)).chain(once(eval(val, env))))
+        .collect();
+
+    Ok(MalValue::new(Map(MalMap::from_arguments(
+        map_args?.as_slice(),
+    )?)))
+}
+
+fn apply_ast(list: &im_rc::Vector<MalValue>, env: &mut Env) -> ApplyResult {
+    let evaluated_list = eval_ast_seq(list, env)?;
+    let (function, args) = evaluated_list
+        .split_first()
+        .expect("Evaluation of non-empty list resulted in empty list.");
+
+    match *function.mal_type {
+        RustFunc(ref rust_function) => Ok(Return((rust_function.func)(
+            args,
+            &mut rust_function.env.clone(),
+        )?)),
+        MalFunc(ref mal_func) => {
+            let func_env = Env::with_binds(Some(&mal_func.outer_env), &mal_func.parameters, args)?;
+            Ok(TailCall(mal_func.body.clone(), func_env))
+        }
+        _ => Err(MalError::Evaluation(
+            "First element of a list must evaluate to a function.".to_string(),
+        )),
+    }
+}
+
+fn get_macro_function(ast: &MalValue, env: &Env) -> Option<MalValue> {
+    if let List(MalList { ref vec, .. }) = *ast.mal_type {
+        let first = vec.get(0)?;
+
+        if let Symbol(ref symbol) = *first.mal_type {
+            let val = env.get(symbol).ok()?;
+
+            if let MalFunc(ref function) = *val.mal_type {
+                if function.is_macro {
+                    return Some(val);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+pub fn macroexpand(ast: &MalValue, env: &mut Env) -> MalResult {
+    let mut ast: MalValue = ast.clone();
+
+    while let Some(ref macro_val) = get_macro_function(&ast, env) {
+        if let MalFunc(ref function) = *macro_val.mal_type {
+            if let List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) = *ast.mal_type
+            {
+                let mut macro_env = Env::with_binds(
+                    Some(&function.outer_env),
+                    &function.parameters,
+                    vec.iter().skip(1),
+                )?;
+
+                ast = eval(&function.body, &mut macro_env)?;
+            } else {
+                unreachable!()
+            }
+        } else {
+            unreachable!()
+        }
+    }
+
+    Ok(ast.clone())
+}
+
+fn apply_special_form_def(list: &im_rc::Vector<MalValue>, env: &mut Env) -> ApplyResult {
+    if list.len() != 3 {
+        return Err(MalError::SpecialForm(format!(
+            "def! expected 2 arguments, got {}",
+            list.len() - 1
+        )));
+    }
+
+    let arg1 = if let Symbol(ref symbol) = *list[1].mal_type {
+        Ok(symbol)
+    } else {
+        Err(MalError::SpecialForm(
+            "def! first argument must be a valid symbol name".to_string(),
+        ))
+    }?;
+
+    let arg2 = eval(&list[2], env)?;
+
+    env.set(arg1.as_str(), arg2.clone());
+
+    Ok(Return(arg2))
+}
+
+fn apply_special_form_let(list: &im_rc::Vector<MalValue>, env: &Env) -> ApplyResult {
+    if list.len() != 3 {
+        return Err(MalError::SpecialForm(format!(
+            "let* expected 2 arguments, got {}",
+            list.len() - 1
+        )));
+    }
+
+    let bindings = match *list[1].mal_type {
+        List(MalList {
+            vec: ref bindings, ..
+        })
+        | Vector(MalVector {
+            vec: ref bindings, ..
+        }) => Ok(bindings),
+        _ => Err(MalError::SpecialForm(
+            "let* first argument must be a list or a vector".to_string(),
+        )),
+    }?;
+
+    if bindings.len() % 2 != 0 {
+        return Err(MalError::SpecialForm(
+            "let* bindings list must have an even number of elements".to_string(),
+        ));
+    }
+
+    let mut inner_env = Env::with_outer_env(env);
+
+    for i in (0..bindings.len()).step_by(2) {
+        let binding_name = if let Symbol(ref symbol) = *bindings[i].mal_type {
+            Ok(symbol)
+        } else {
+            Err(MalError::SpecialForm(
+                "let* odd numbered elements of binding list must be valid symbol names".to_string(),
+            ))
+        }?;
+
+        let binding_expr = eval(&bindings[i + 1], &mut inner_env)?;
+
+        inner_env.set(binding_name.as_str(), binding_expr);
+    }
+
+    Ok(TailCall(list[2].clone(), inner_env))
+}
+
+fn apply_special_form_fn(list: &im_rc::Vector<MalValue>, env: &Env) -> ApplyResult {
+    if list.len() != 3 {
+        return Err(MalError::SpecialForm(format!(
+            "fn* expected 2 arguments, got {}",
+            list.len() - 1
+        )));
+    }
+
+    let bindings = match *list[1].mal_type {
+        List(MalList {
+            vec: ref bindings, ..
+        })
+        | Vector(MalVector {
+            vec: ref bindings, ..
+        }) => Ok(bindings),
+        _ => Err(MalError::SpecialForm(
+            "fn* first argument must be a list or a vector".to_string(),
+        )),
+    }?;
+
+    let parameters: Result<Vec<String>, _> = bindings
+        .iter()
+        .map(|val| {
+            if let Symbol(ref symbol) = *val.mal_type {
+                Ok(symbol.clone())
+            } else {
+                Err(MalError::SpecialForm(
+                    "fn*! first argument must be a sequence of valid symbol names".to_string(),
+                ))
+            }
+        })
+        .collect();
+
+    Ok(Return(MalValue::new_mal_func(
+        list[2].clone(),
+        parameters?,
+        env.clone(),
+    )))
+}
+
+/// The body is evaluated once, the first time the sequence is used, and must
+/// return a sequence or nil.
+fn apply_special_form_lazy_seq(list: &im_rc::Vector<MalValue>, env: &Env) -> ApplyResult {
+    let body = MalValue::new_list(
+        once(MalValue::new(Symbol("do".to_string())))
+            .chain(list.iter().skip(1).cloned())
+            .collect::<Vec<_>>(),
+    );
+
+    Ok(Return(MalValue::new_lazy_seq(MalLazySeq::new(
+        MalValue::new_mal_func(body, vec![], env.clone()),
+        vec![],
+    ))))
+}
+
+fn apply_special_form_do(list: &im_rc::Vector<MalValue>, env: &mut Env) -> ApplyResult {
+    if list.len() == 1 {
+        return Ok(Return(MalValue::nil()));
+    }
+
+    for expr in list.iter().skip(1).take(list.len() - 2) {
+        eval(expr, env)?;
+    }
+
+    Ok(TailCall(list.back().unwrap().clone(), env.clone()))
+}
+
+fn apply_special_form_if(list: &im_rc::Vector<MalValue>, env: &mut Env) -> ApplyResult {
+    if list.len() < 3 || list.len() > 4 {
+        return Err(MalError::SpecialForm(format!(
+            "if expected 2 or 3 arguments, got {}",
+            list.len() - 1
+        )));
+    }
+
+    let test_result = eval(&list[1], env)?;
+
+    match *test_result.mal_type {
+        MalValueType::False | Nil => {
+            if list.len() == 4 {
+                Ok(TailCall(list[3].clone(), env.clone()))
+            } else {
+                Ok(Return(MalValue::nil()))
+            }
+        }
+        _ => Ok(TailCall(list[2].clone(), env.clone())),
+    }
+}
+
+fn apply_special_form_quote(list: &im_rc::Vector<MalValue>, _env: &mut Env) -> ApplyResult {
+    if list.len() != 2 {
+        return Err(MalError::SpecialForm(format!(
+            "quote expects 1 argument, got {}",
+            list.len() - 1
+        )));
+    }
+
+    Ok(Return(list[1].clone()))
+}
+
+fn apply_special_form_quasiquote(list: &im_rc::Vector<MalValue>, env: &mut Env) -> ApplyResult {
+    if list.len() != 2 {
+        return Err(MalError::SpecialForm(format!(
+            "quasiquote expects 1 argument, got {}",
+            list.len() - 1
+        )));
+    }
+
+    Ok(TailCall(quasiquote(&list[1])?, env.clone()))
+}
+
+fn quasiquote(ast: &MalValue) -> MalResult {
+    match *ast.mal_type {
+        MalValueType::List(MalList { ref vec, .. })
+        | MalValueType::Vector(MalVector { ref vec, .. })
+            if !vec.is_empty() =>
+        {
+            let elem0 = &vec[0];
+            match *elem0.mal_type {
+                Symbol(ref s) if s == "unquote" => {
+                    if vec.len() != 2 {
+                        Err(MalError::SpecialForm(format!(
+                            "unquote expects 1 argument, got {}",
+                            vec.len() - 1
+                        )))
+                    } else {
+                        Ok(vec[1].clone())
+                    }
+                }
+                MalValueType::List(MalList {
+                    vec: ref inner_vec, ..
+                })
+                | MalValueType::Vector(MalVector {
+                    vec: ref inner_vec, ..
+                }) if !inner_vec.is_empty() => match *inner_vec[0].mal_type {
+                    Symbol(ref s) if s == "splice-unquote" => {
+                        if inner_vec.len() != 2 {
+                            Err(MalError::SpecialForm(format!(
+                                "splice-unquote expects 1 argument, got {}",
+                                inner_vec.len() - 1
+                            )))
+                        } else {
+                            Ok(MalValue::new_list(vec![
+                                MalValue::new(Symbol("concat".to_string())),
+                                inner_vec[1].clone(),
+                                quasiquote(&MalValue::new_list(vec.skip(1)))?,
+                            ]))
+                        }
+                    }
+                    _ => Ok(MalValue::new_list(vec![
+                        MalValue::new(Symbol("cons".to_string())),
+                        quasiquote(elem0)?,
+                        quasiquote(&MalValue::new_list(vec.skip(1)))?,
+                    ])),
+                },
+                _ => Ok(MalValue::new_list(vec![
+                    MalValue::new(Symbol("cons".to_string())),
+                    quasiquote(elem0)?,
+                    quasiquote(&MalValue::new_list(vec.skip(1)))?,
+                ])),
+            }
+        }
+        _ => Ok(MalValue::new_list(vec![
+            MalValue::new(Symbol("quote".to_string())),
+            ast.clone(),
+        ])),
+    }
+}
+
+fn apply_special_form_defmacro(list: &im_rc::Vector<MalValue>, env: &mut Env) -> ApplyResult {
+    if list.len() != 3 {
+        return Err(MalError::SpecialForm(format!(
+            "defmacro! expected 2 arguments, got {}",
+            list.len() - 1
+        )));
+    }
+
+    let arg1 = if let Symbol(ref symbol) = *list[1].mal_type {
+        Ok(symbol)
+    } else {
+        Err(MalError::SpecialForm(
+            "defmacro! first argument must be a valid symbol name".to_string(),
+        ))
+    }?;
+
+    let arg2 = eval(&list[2], env)?;
+
+    let macro_val = if let MalFunc(ref mal_function) = *arg2.mal_type {
+        MalValue::new_mal_macro(
+            mal_function.body.clone(),
+            mal_function.parameters.clone(),
+            mal_function.outer_env.clone(),
+        )
+    } else {
+        Err(MalError::SpecialForm(
+            "defmacro! second argument must evaluate to a function".to_string(),
+        ))?
+    };
+
+    env.set(arg1.as_str(), macro_val.clone());
+
+    Ok(Return(macro_val))
+}
+
+fn apply_special_form_macroexpand(list: &im_rc::Vector<MalValue>, env: &mut Env) -> ApplyResult {
+    if list.len() != 2 {
+        return Err(MalError::SpecialForm(format!(
+            "macroexpand expected 1 arguments, got {}",
+            list.len() - 1
+        )));
+    }
+
+    let expanded = macroexpand(&list[1], env)?;
+
+    Ok(Return(expanded))
+}
+
+fn apply_special_form_try(list: &im_rc::Vector<MalValue>, env: &mut Env) -> ApplyResult {
+    if list.len() != 3 {
+        return Err(MalError::SpecialForm(format!(
+            "try* expected 2 arguments, got {}",
+            list.len() - 1
+        )));
+    }
+
+    let exception_symbol;
+    let catch_expression;
+
+    if let List(MalList {
+        vec: ref catch_vec, ..
+    }) = *list[2].mal_type
+    {
+        if catch_vec.is_empty() {
+            return Err(MalError::SpecialForm(
+                "try* second argument must be a non-empty list".to_string(),
+            ));
+        }
+
+        match *catch_vec[0].mal_type {
+            Symbol(ref s) if s == "catch*" => {}
+            _ => {
+                return Err(MalError::SpecialForm(
+                    "try* second argument must start with symbol 'catch*'".to_string(),
+                ));
+            }
+        }
+
+        if catch_vec.len() != 3 {
+            return Err(MalError::SpecialForm(format!(
+                "catch* expected 2 arguments, got {}",
+                catch_vec.len() - 1
+            )));
+        }
+
+        exception_symbol = if let Symbol(ref s) = *catch_vec[1].mal_type {
+            s
+        } else {
+            return Err(MalError::SpecialForm(
+                "catch* first argument must be a symbol".to_string(),
+            ));
+        };
+
+        catch_expression = &catch_vec[2];
+    } else {
+        return Err(MalError::SpecialForm(
+            "try* second argument must be a non-empty list".to_string(),
+        ));
+    }
+
+    let try_result = eval(&list[1], env);
+
+    if try_result.is_ok() {
+        return Ok(Return(try_result.unwrap()));
+    }
+
+    let mal_error = try_result.err().unwrap().root_cause();
+
+    let exception = if let MalError::Exception(ref exception_val) = mal_error {
+        exception_val.clone()
+    } else {
+        MalValue::new(Str(mal_error.to_string()))
+    };
+
+    let mut catch_env = Env::with_outer_env(env);
+    catch_env.set(exception_symbol, exception);
+
+    Ok(Return(eval(catch_expression, &mut catch_env)?))
+}
@@ -0,0 +1,380 @@
+use crate::convert::NativeFn;
+use crate::core;
+use crate::env::Env;
+use crate::eval::eval;
+use crate::reader::read_all;
+use crate::types::MalValueType::{Int, Keyword, RustFunc, Str};
+use crate::types::{MalError, MalMap, MalResult, MalValue, RustFn, RustFunction};
+use std::rc::Rc;
+
+/// Forms evaluated in every new interpreter, after the core functions are
+/// installed.
+const BOOTSTRAP_FORMS: &[&str] = &[
+    r#"(def! *gensym-counter* (atom 0))"#,
+    r#"(def! gensym (fn* [] (symbol (str "G__" (swap! *gensym-counter* (fn* [x] (+ 1 x)))))))"#,
+    "(def! not (fn* (a) (if a false true)))",
+    r#"(defmacro! cond (fn* (& xs) (if (> (count xs) 0) (list 'if (first xs) (if (> (count xs) 1) (nth xs 1) (throw "odd number of forms to cond")) (cons 'cond (rest (rest xs)))))))"#,
+    r#"(defmacro! or (fn* (& xs) (if (empty? xs) nil (if (= 1 (count xs)) (first xs) (let* (condvar (gensym)) `(let* (~condvar ~(first xs)) (if ~condvar ~condvar (or ~@(rest xs)))))))))"#,
+];
+
+/// A mal interpreter that can be embedded in a Rust program.
+///
+/// Each interpreter owns a root environment with the core functions, the
+/// bootstrap macros, `*host-language*` and `*ARGV*` defined.
+///
+/// ```
+/// use malrs::Interpreter;
+/// use malrs::types::MalValue;
+/// use malrs::types::MalValueType::Int;
+///
+/// let mut interpreter = Interpreter::new();
+/// interpreter.define("x", MalValue::new(Int(20)));
+/// interpreter.eval_str("(def! add (fn* [a b] (+ a b)))").unwrap();
+///
+/// let result = interpreter.call("add", &[MalValue::new(Int(22)), MalValue::new(Int(20))]);
+/// assert_eq!(result, interpreter.eval_str("(add x 22)"));
+/// ```
+pub struct Interpreter {
+    env: Env,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::with_args::<&str>(&[])
+    }
+
+    /// Creates an interpreter whose `*ARGV*` holds `args`.
+    pub fn with_args<S: AsRef<str>>(args: &[S]) -> Interpreter {
+        let mut env = Env::new();
+
+        env.set("*host-language*", MalValue::new(Str("rust".to_string())));
+
+        env.set(
+            "*ARGV*",
+            MalValue::new_list(
+                args.iter()
+                    .map(|arg| MalValue::new(Str(arg.as_ref().to_string())))
+                    .collect::<Vec<_>>(),
+            ),
+        );
+
+        for (name, val) in core::ns(&env) {
+            env.set(name, val);
+        }
+
+        let mut interpreter = Interpreter { env };
+
+        for form in BOOTSTRAP_FORMS {
+            interpreter
+                .eval_str(form)
+                .unwrap_or_else(|err| panic!("Error in bootstrap form {}: {}", form, err));
+        }
+
+        interpreter
+    }
+
+    /// The root environment, where `def!` at the top level defines symbols.
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    /// Evaluates every form in `program`, returning the value of the last one,
+    /// or nil if there are none.
+    pub fn eval_str(&mut self, program: &str) -> MalResult {
+        let mut result = MalValue::nil();
+
+        for form in read_all(program, None) {
+            result = eval(&form?, &mut self.env)?;
+        }
+
+        Ok(result)
+    }
+
+    pub fn eval_value(&mut self, ast: &MalValue) -> MalResult {
+        eval(ast, &mut self.env)
+    }
+
+    /// Evaluates the forms in a file, as `load-file` does.
+    pub fn load_file(&mut self, path: &str) -> MalResult {
+        core::eval_file(path, &mut self.env)
+    }
+
+    pub fn define(&mut self, name: &str, value: MalValue) {
+        self.env.set(name, value);
+    }
+
+    /// Defines `name` as a builtin that calls `func`, which may capture state
+    /// from the host. Calls with a number of arguments outside `arity` fail
+    /// without reaching `func`.
+    ///
+    /// The builtin's metadata holds its name and arity, as in
+    /// `{:name "f" :min-args 1 :max-args 2}`. `:max-args` is nil if the
+    /// function is variadic.
+    pub fn register<F>(&mut self, name: &str, arity: Arity, func: F)
+    where
+        F: Fn(&[MalValue], &mut Env) -> MalResult + 'static,
+    {
+        let func_name = name.to_string();
+        let checked_func: RustFn = Rc::new(move |args: &[MalValue], env: &mut Env| {
+            arity.check(&func_name, args)?;
+            func(args, env)
+        });
+
+        let (min_args, max_args) = arity.bounds();
+        let meta = MalMap::from_arguments(&[
+            MalValue::new(Keyword("name".to_string())),
+            MalValue::new(Str(name.to_string())),
+            MalValue::new(Keyword("min-args".to_string())),
+            MalValue::new(Int(min_args as i64)),
+            MalValue::new(Keyword("max-args".to_string())),
+            max_args.map_or_else(MalValue::nil, |max| MalValue::new(Int(max as i64))),
+        ])
+        .expect("Metadata keys are distinct");
+
+        self.define(
+            name,
+            MalValue::new(RustFunc(RustFunction {
+                func: checked_func,
+                env: self.env.clone(),
+                meta: MalValue::new_map(meta),
+            })),
+        );
+    }
+
+    /// Defines `name` as a builtin that calls an ordinary Rust function, such
+    /// as `fn(String, i64) -> Result<Vec<String>, E>`. Arguments are converted
+    /// with `FromMal` and the result with `IntoMal`, and arity and conversion
+    /// errors are reported with the builtin's name.
+    pub fn register_fn<Args, F: NativeFn<Args>>(&mut self, name: &str, func: F) {
+        let func_name = name.to_string();
+
+        self.register(name, Arity::Exactly(F::ARITY), move |args, _env| {
+            func.call_native(&func_name, args)
+        });
+    }
+
+    /// Calls the function bound to `name` in the root environment.
+    pub fn call(&mut self, name: &str, args: &[MalValue]) -> MalResult {
+        core::core_apply(&self.env.get(name)?, args)
+    }
+}
+
+/// The number of arguments accepted by a builtin defined with
+/// `Interpreter::register`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+    Between(usize, usize),
+}
+
+impl Arity {
+    fn bounds(self) -> (usize, Option<usize>) {
+        match self {
+            Arity::Exactly(n) => (n, Some(n)),
+            Arity::AtLeast(min) => (min, None),
+            Arity::Between(min, max) => (min, Some(max)),
+        }
+    }
+
+    pub(crate) fn check(self, name: &str, args: &[MalValue]) -> Result<(), MalError> {
+        let (min_args, max_args) = self.bounds();
+
+        let within_max = match max_args {
+            Some(max) => args.len() <= max,
+            None => true,
+        };
+
+        if args.len() >= min_args && within_max {
+            return Ok(());
+        }
+
+        let expected = match self {
+            Arity::Exactly(n) => format!("{} argument{}", n, if n == 1 { "" } else { "s" }),
+            Arity::AtLeast(min) => format!("at least {} arguments", min),
+            Arity::Between(min, max) => format!("{} to {} arguments", min, max),
+        };
+
+        Err(MalError::RustFunction(format!(
+            "{}: Expected {}, got {}",
+            name,
+            expected,
+            args.len()
+        )))
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::fs;
+
+    fn int(n: i64) -> MalValue {
+        MalValue::new(Int(n))
+    }
+
+    #[test]
+    fn test_eval_str() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.eval_str("(def! a 1) (+ a 2)"), Ok(int(3)));
+        assert_eq!(interpreter.eval_str(""), Ok(MalValue::nil()));
+        assert_eq!(interpreter.eval_str("(or nil 4)"), Ok(int(4)));
+        assert!(interpreter.eval_str("(undefined)").is_err());
+    }
+
+    #[test]
+    fn test_eval_value() {
+        let mut interpreter = Interpreter::new();
+        let ast = MalValue::new_list(vec![interpreter.env().get("+").unwrap(), int(1), int(2)]);
+
+        assert_eq!(interpreter.eval_value(&ast), Ok(int(3)));
+    }
+
+    #[test]
+    fn test_define_and_call() {
+        let mut interpreter = Interpreter::new();
+        interpreter.define("base", int(10));
+        interpreter
+            .eval_str("(def! add-base (fn* [x] (+ base x)))")
+            .unwrap();
+
+        assert_eq!(interpreter.call("add-base", &[int(5)]), Ok(int(15)));
+        assert_eq!(
+            interpreter.call("keyword", &[MalValue::new(Str("k".to_string()))]),
+            Ok(MalValue::new(Keyword("k".to_string())))
+        );
+        assert!(interpreter.call("base", &[]).is_err());
+    }
+
+    #[test]
+    fn test_args() {
+        let mut interpreter = Interpreter::with_args(&["a", "b"]);
+
+        assert_eq!(
+            interpreter.eval_str("*ARGV*"),
+            Ok(MalValue::new_list(vec![
+                MalValue::new(Str("a".to_string())),
+                MalValue::new(Str("b".to_string()))
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_load_file() {
+        let path = std::env::temp_dir().join("malrs_interpreter_test_load_file.mal");
+        fs::write(&path, "(def! loaded 1)\n(+ loaded 1)\n").unwrap();
+
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.load_file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result, Ok(int(2)));
+        assert_eq!(interpreter.eval_str("loaded"), Ok(int(1)));
+    }
+
+    #[test]
+    fn test_interpreters_are_independent() {
+        let mut first = Interpreter::new();
+        let mut second = Interpreter::new();
+
+        first.eval_str("(def! x 1)").unwrap();
+
+        assert!(second.eval_str("x").is_err());
+    }
+
+    #[test]
+    fn test_register_closure() {
+        let mut interpreter = Interpreter::new();
+        let counter = Rc::new(Cell::new(0));
+
+        let captured = counter.clone();
+        interpreter.register("bump!", Arity::Between(0, 1), move |args, _env| {
+            let step = match args.first().map(|arg| &*arg.mal_type) {
+                Some(Int(step)) => *step,
+                _ => 1,
+            };
+            captured.set(captured.get() + step);
+            Ok(MalValue::new(Int(captured.get())))
+        });
+
+        assert_eq!(interpreter.eval_str("(bump!) (bump! 10)"), Ok(int(11)));
+        assert_eq!(counter.get(), 11);
+        assert_eq!(
+            interpreter.eval_str("(map (fn* [_] (bump!)) [1 2])"),
+            Ok(MalValue::new_list(vec![int(12), int(13)]))
+        );
+        assert!(interpreter.eval_str("(bump! 1 2)").is_err());
+        assert_eq!(counter.get(), 13);
+        assert_eq!(
+            interpreter.eval_str("(meta bump!)"),
+            interpreter.eval_str(r#"{:name "bump!" :min-args 0 :max-args 1}"#)
+        );
+        assert_eq!(
+            interpreter.eval_str("(= bump! bump!)"),
+            Ok(MalValue::new_boolean(true))
+        );
+    }
+
+    #[test]
+    fn test_arity_errors() {
+        let args = [int(1), int(2)];
+
+        assert_eq!(Arity::AtLeast(2).check("f", &args), Ok(()));
+        assert_eq!(
+            Arity::Exactly(1).check("f", &args),
+            Err(MalError::RustFunction(
+                "f: Expected 1 argument, got 2".to_string()
+            ))
+        );
+        assert_eq!(
+            Arity::AtLeast(3).check("f", &args),
+            Err(MalError::RustFunction(
+                "f: Expected at least 3 arguments, got 2".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_register_fn() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_fn("label", |name: String, count: i64| {
+            if count < 0 {
+                Err(format!("negative count {}", count))
+            } else {
+                Ok(format!("{} ({})", name, count))
+            }
+        });
+
+        assert_eq!(
+            interpreter.eval_str(r#"(label "apples" 3)"#),
+            Ok(MalValue::new(Str("apples (3)".to_string())))
+        );
+        assert_eq!(
+            interpreter.eval_str(r#"(try* (label "apples" -1) (catch* e e))"#),
+            Ok(MalValue::new(Str(
+                "Error when calling rust function: label: negative count -1".to_string()
+            )))
+        );
+        assert_eq!(
+            interpreter.eval_str(r#"(try* (label "apples") (catch* e e))"#),
+            Ok(MalValue::new(Str(
+                "Error when calling rust function: label: Expected 2 arguments, got 1".to_string()
+            )))
+        );
+        assert_eq!(
+            interpreter.eval_str(r#"(try* (label :apples 3) (catch* e e))"#),
+            Ok(MalValue::new(Str(
+                "Error when calling rust function: label: argument 1: Expected a string"
+                    .to_string()
+            )))
+        );
+    }
+}
@@ -1,7 +1,27 @@
+pub mod convert;
 pub mod core;
+#[cfg(feature = "serde")]
+pub mod de;
 pub mod env;
+pub mod eval;
+pub mod interpreter;
+pub mod number;
 pub mod printer;
 pub mod reader;
 pub mod readline;
+pub mod seq;
+#[cfg(feature = "serde")]
+pub mod ser;
+#[cfg(feature = "serde")]
+mod serde_error;
 pub mod tokenizer;
 pub mod types;
+
+pub use crate::convert::{FromMal, IntoMal};
+#[cfg(feature = "serde")]
+pub use crate::de::{from_str, from_value};
+pub use crate::interpreter::Interpreter;
+#[cfg(feature = "serde")]
+pub use crate::ser::to_value;
+#[cfg(feature = "serde")]
+pub use crate::serde_error::SerdeError;
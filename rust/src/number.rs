@@ -0,0 +1,528 @@
+use crate::types::MalValueType::{BigInt as MalBigInt, Float, Int, Ratio};
+use crate::types::{MalError, MalValue};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{ToPrimitive, Zero};
+use std::cmp::Ordering;
+
+/// The largest exact power `pow` computes, in bits.
+const MAX_POW_BITS: u64 = 1 << 22;
+
+/// A numeric value detached from `MalValue`, used to implement arithmetic
+/// across the numeric tower: `Int` < `BigInt` < `Ratio` < `Float`.
+///
+/// Operations on two numbers first promote both to the higher of their two
+/// types. `Int` operations that overflow are promoted to `BigInt`, and ratios
+/// whose denominator is 1 are converted back to integers.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Number {
+    Int(i64),
+    BigInt(BigInt),
+    Ratio(BigRational),
+    Float(f64),
+}
+
+impl Number {
+    pub fn from_value(mal_value: &MalValue) -> Option<Number> {
+        match *mal_value.mal_type {
+            Int(n) => Some(Number::Int(n)),
+            MalBigInt(ref n) => Some(Number::BigInt(n.clone())),
+            Ratio(ref n) => Some(Number::Ratio(n.clone())),
+            Float(n) => Some(Number::Float(n)),
+            _ => None,
+        }
+    }
+
+    /// Like `from_value`, but fails with an error naming the builtin and the
+    /// position of the argument, `index` being 0-based.
+    pub fn from_arg(name: &str, index: usize, mal_value: &MalValue) -> Result<Number, MalError> {
+        Number::from_value(mal_value).ok_or_else(|| {
+            MalError::RustFunction(format!("{}: argument {} must be a number", name, index + 1))
+        })
+    }
+
+    pub fn into_value(self) -> MalValue {
+        match self {
+            Number::Int(n) => MalValue::new(Int(n)),
+            Number::BigInt(n) => MalValue::new(MalBigInt(n)),
+            Number::Ratio(n) => MalValue::new(Ratio(n)),
+            Number::Float(n) => MalValue::new(Float(n)),
+        }
+    }
+
+    /// Converts an integer-valued ratio to `Int` (or `BigInt` if it does not fit).
+    pub fn from_ratio(ratio: BigRational) -> Number {
+        if ratio.is_integer() {
+            Number::from_bigint(ratio.to_integer())
+        } else {
+            Number::Ratio(ratio)
+        }
+    }
+
+    /// Converts a `BigInt` to `Int` if it fits.
+    pub fn from_bigint(n: BigInt) -> Number {
+        match n.to_i64() {
+            Some(n) => Number::Int(n),
+            None => Number::BigInt(n),
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Int(n) => *n as f64,
+            Number::BigInt(n) => n.to_f64().unwrap_or(f64::NAN),
+            Number::Ratio(n) => ratio_to_f64(n),
+            Number::Float(n) => *n,
+        }
+    }
+
+    fn to_bigint(&self) -> BigInt {
+        match self {
+            Number::Int(n) => BigInt::from(*n),
+            Number::BigInt(n) => n.clone(),
+            _ => unreachable!("to_bigint called on a non-integer"),
+        }
+    }
+
+    fn to_ratio(&self) -> BigRational {
+        match self {
+            Number::Int(_) | Number::BigInt(_) => BigRational::from_integer(self.to_bigint()),
+            Number::Ratio(n) => n.clone(),
+            Number::Float(_) => unreachable!("to_ratio called on a float"),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Number::Int(_) => 0,
+            Number::BigInt(_) => 1,
+            Number::Ratio(_) => 2,
+            Number::Float(_) => 3,
+        }
+    }
+
+    pub fn add(&self, other: &Number) -> Result<Number, MalError> {
+        Ok(self.binary_op(
+            other,
+            i64::checked_add,
+            |a, b| a + b,
+            |a, b| a + b,
+            |a, b| a + b,
+        ))
+    }
+
+    pub fn sub(&self, other: &Number) -> Result<Number, MalError> {
+        Ok(self.binary_op(
+            other,
+            i64::checked_sub,
+            |a, b| a - b,
+            |a, b| a - b,
+            |a, b| a - b,
+        ))
+    }
+
+    pub fn mul(&self, other: &Number) -> Result<Number, MalError> {
+        Ok(self.binary_op(
+            other,
+            i64::checked_mul,
+            |a, b| a * b,
+            |a, b| a * b,
+            |a, b| a * b,
+        ))
+    }
+
+    /// Exact division: integers that do not divide evenly produce a ratio.
+    pub fn div(&self, other: &Number) -> Result<Number, MalError> {
+        if self.rank().max(other.rank()) < 3 && other.is_zero() {
+            return Err(MalError::RustFunction("Division by zero".to_string()));
+        }
+
+        Ok(match (self, other) {
+            (Number::Int(a), Number::Int(b)) if a.checked_rem(*b) == Some(0) => Number::Int(a / b),
+            _ if self.rank().max(other.rank()) == 3 => {
+                Number::Float(self.to_f64() / other.to_f64())
+            }
+            _ => Number::from_ratio(self.to_ratio() / other.to_ratio()),
+        })
+    }
+
+    /// Division truncated towards zero.
+    pub fn quot(&self, other: &Number) -> Result<Number, MalError> {
+        check_divisor(other)?;
+
+        Ok(self.binary_op(
+            other,
+            i64::checked_div,
+            |a, b| a / b,
+            |a, b| (a / b).trunc(),
+            |a, b| (a / b).trunc(),
+        ))
+    }
+
+    /// Remainder of `quot`, which has the sign of the dividend.
+    pub fn rem(&self, other: &Number) -> Result<Number, MalError> {
+        check_divisor(other)?;
+
+        Ok(self.binary_op(
+            other,
+            |a, b| Some(a.wrapping_rem(b)),
+            |a, b| a % b,
+            |a, b| a % b,
+            |a, b| a % b,
+        ))
+    }
+
+    /// Remainder of floored division, which has the sign of the divisor.
+    pub fn modulo(&self, other: &Number) -> Result<Number, MalError> {
+        let rem = self.rem(other)?;
+
+        if !rem.is_zero() && rem.is_negative() != other.is_negative() {
+            rem.add(other)
+        } else {
+            Ok(rem)
+        }
+    }
+
+    /// Exact when the base is rational and the exponent an integer, otherwise
+    /// computed with floats.
+    pub fn pow(&self, exponent: &Number) -> Result<Number, MalError> {
+        let exponent = match (self, exponent) {
+            (Number::Float(_), _) | (_, Number::Float(_)) | (_, Number::Ratio(_)) => {
+                return Ok(Number::Float(self.to_f64().powf(exponent.to_f64())))
+            }
+            (_, Number::BigInt(_)) => {
+                return Err(MalError::RustFunction("Exponent is too large".to_string()))
+            }
+            (_, Number::Int(exponent)) => *exponent,
+        };
+
+        // The result has about `bits * exponent` bits. Only 0, 1 and -1 have
+        // at most one bit, and their powers never grow.
+        let base = self.to_ratio();
+        let bits = base.numer().bits().max(base.denom().bits());
+        if bits > 1 && bits.saturating_mul(exponent.unsigned_abs()) > MAX_POW_BITS {
+            return Err(MalError::RustFunction("Exponent is too large".to_string()));
+        }
+
+        let power = num_traits::pow(base, exponent.unsigned_abs() as usize);
+
+        if exponent >= 0 {
+            Ok(Number::from_ratio(power))
+        } else {
+            Number::Int(1).div(&Number::from_ratio(power))
+        }
+    }
+
+    pub fn floor(&self) -> Number {
+        self.round_with(BigRational::floor, f64::floor)
+    }
+
+    pub fn ceil(&self) -> Number {
+        self.round_with(BigRational::ceil, f64::ceil)
+    }
+
+    /// Rounds half away from zero.
+    pub fn round(&self) -> Number {
+        self.round_with(BigRational::round, f64::round)
+    }
+
+    /// Integers are returned unchanged and floats stay floats.
+    fn round_with(
+        &self,
+        ratio_op: fn(&BigRational) -> BigRational,
+        float_op: fn(f64) -> f64,
+    ) -> Number {
+        match self {
+            Number::Int(_) | Number::BigInt(_) => self.clone(),
+            Number::Ratio(n) => Number::from_ratio(ratio_op(n)),
+            Number::Float(n) => Number::Float(float_op(*n)),
+        }
+    }
+
+    pub fn abs(&self) -> Result<Number, MalError> {
+        match self {
+            Number::Float(n) => Ok(Number::Float(n.abs())),
+            _ if self.is_negative() => Number::Int(0).sub(self),
+            _ => Ok(self.clone()),
+        }
+    }
+
+    /// -1, 0 or 1, as a float for floats. Zero and NaN floats are returned
+    /// unchanged.
+    pub fn signum(&self) -> Number {
+        match self {
+            Number::Float(n) if *n == 0. || n.is_nan() => Number::Float(*n),
+            Number::Float(n) => Number::Float(n.signum()),
+            _ => match self.partial_cmp(&Number::Int(0)) {
+                Some(Ordering::Less) => Number::Int(-1),
+                Some(Ordering::Greater) => Number::Int(1),
+                _ => Number::Int(0),
+            },
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.partial_cmp(&Number::Int(0)) == Some(Ordering::Less)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Int(n) => *n == 0,
+            Number::BigInt(n) => n.is_zero(),
+            Number::Ratio(n) => n.is_zero(),
+            Number::Float(n) => *n == 0.,
+        }
+    }
+
+    pub fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => Some(a.cmp(b)),
+            _ if self.rank().max(other.rank()) == 3 => self.to_f64().partial_cmp(&other.to_f64()),
+            _ => Some(self.to_ratio().cmp(&other.to_ratio())),
+        }
+    }
+
+    fn binary_op(
+        &self,
+        other: &Number,
+        int_op: fn(i64, i64) -> Option<i64>,
+        bigint_op: fn(BigInt, BigInt) -> BigInt,
+        ratio_op: fn(BigRational, BigRational) -> BigRational,
+        float_op: fn(f64, f64) -> f64,
+    ) -> Number {
+        match self.rank().max(other.rank()) {
+            0 => match (self, other) {
+                (Number::Int(a), Number::Int(b)) => match int_op(*a, *b) {
+                    Some(n) => Number::Int(n),
+                    None => Number::BigInt(bigint_op(BigInt::from(*a), BigInt::from(*b))),
+                },
+                _ => unreachable!(),
+            },
+            1 => Number::BigInt(bigint_op(self.to_bigint(), other.to_bigint())),
+            2 => Number::from_ratio(ratio_op(self.to_ratio(), other.to_ratio())),
+            _ => Number::Float(float_op(self.to_f64(), other.to_f64())),
+        }
+    }
+}
+
+fn check_divisor(divisor: &Number) -> Result<(), MalError> {
+    if divisor.is_zero() {
+        Err(MalError::RustFunction("Division by zero".to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+fn ratio_to_f64(ratio: &BigRational) -> f64 {
+    match (ratio.numer().to_f64(), ratio.denom().to_f64()) {
+        (Some(numer), Some(denom)) if numer.is_finite() && denom.is_finite() => numer / denom,
+        _ => {
+            // Scale both parts down so that they fit in an f64.
+            let shift = ratio.numer().bits().max(ratio.denom().bits()) as usize - 1000;
+            let numer = (ratio.numer() >> shift).to_f64().unwrap_or(0.);
+            let denom = (ratio.denom() >> shift).to_f64().unwrap_or(0.);
+            numer / denom
+        }
+    }
+}
+
+/// Converts a float to the ratio of its shortest decimal representation, so
+/// that `0.1` becomes `1/10` rather than its exact binary expansion.
+pub fn rationalize(val: f64) -> Result<Number, MalError> {
+    if !val.is_finite() {
+        return Err(MalError::RustFunction(
+            "Cannot rationalize a non-finite number".to_string(),
+        ));
+    }
+
+    let text = format!("{:e}", val);
+    let (mantissa, exponent) = text.split_at(text.find('e').unwrap());
+    let exponent: i32 = exponent[1..].parse().unwrap();
+
+    let (int_part, frac_part) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+
+    let digits: BigInt = format!("{}{}", int_part, frac_part).parse().unwrap();
+    let exponent = exponent - frac_part.len() as i32;
+    let scale = num_traits::pow(BigInt::from(10), exponent.unsigned_abs() as usize);
+
+    Ok(Number::from_ratio(if exponent >= 0 {
+        BigRational::from_integer(digits * scale)
+    } else {
+        BigRational::new(digits, scale)
+    }))
+}
+
+pub fn numerator(number: &Number) -> Result<Number, MalError> {
+    match number {
+        Number::Ratio(n) => Ok(Number::from_bigint(n.numer().clone())),
+        Number::Int(_) | Number::BigInt(_) => Ok(number.clone()),
+        Number::Float(_) => Err(MalError::RustFunction(
+            "numerator expects a rational number".to_string(),
+        )),
+    }
+}
+
+pub fn denominator(number: &Number) -> Result<Number, MalError> {
+    match number {
+        Number::Ratio(n) => Ok(Number::from_bigint(n.denom().clone())),
+        Number::Int(_) | Number::BigInt(_) => Ok(Number::Int(1)),
+        Number::Float(_) => Err(MalError::RustFunction(
+            "denominator expects a rational number".to_string(),
+        )),
+    }
+}
+
+/// Builds a normalized ratio from a literal such as `22/7`.
+pub fn parse_ratio(numer: &str, denom: &str) -> Option<Number> {
+    let numer: BigInt = numer.parse().ok()?;
+    let denom: BigInt = denom.parse().ok()?;
+
+    if denom.is_zero() {
+        return None;
+    }
+
+    Some(Number::from_ratio(BigRational::new(numer, denom)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(numer: i64, denom: i64) -> Number {
+        Number::Ratio(BigRational::new(numer.into(), denom.into()))
+    }
+
+    #[test]
+    fn test_int_overflow_promotes_to_bigint() {
+        assert_eq!(
+            Number::Int(i64::MAX).add(&Number::Int(1)),
+            Ok(Number::BigInt(BigInt::from(i64::MAX) + 1))
+        );
+        assert_eq!(
+            Number::Int(i64::MIN).div(&Number::Int(-1)),
+            Ok(Number::BigInt(-BigInt::from(i64::MIN)))
+        );
+    }
+
+    #[test]
+    fn test_results_are_normalized() {
+        assert_eq!(Number::Int(1).div(&Number::Int(3)), Ok(ratio(1, 3)));
+        assert_eq!(ratio(1, 3).mul(&Number::Int(3)), Ok(Number::Int(1)));
+        assert_eq!(
+            Number::BigInt(BigInt::from(i64::MAX) + 1).sub(&Number::Int(1)),
+            Ok(Number::BigInt(BigInt::from(i64::MAX)))
+        );
+    }
+
+    #[test]
+    fn test_float_contagion() {
+        assert_eq!(
+            ratio(1, 2).add(&Number::Float(0.25)),
+            Ok(Number::Float(0.75))
+        );
+        assert_eq!(
+            Number::Float(1.).div(&Number::Int(0)),
+            Ok(Number::Float(f64::INFINITY))
+        );
+        assert!(ratio(1, 2).div(&Number::Int(0)).is_err());
+    }
+
+    #[test]
+    fn test_quot_rem_and_modulo() {
+        assert_eq!(Number::Int(-7).quot(&Number::Int(2)), Ok(Number::Int(-3)));
+        assert_eq!(Number::Int(-7).rem(&Number::Int(2)), Ok(Number::Int(-1)));
+        assert_eq!(Number::Int(-7).modulo(&Number::Int(2)), Ok(Number::Int(1)));
+        assert_eq!(Number::Int(7).modulo(&Number::Int(-2)), Ok(Number::Int(-1)));
+        assert_eq!(
+            Number::Int(i64::MIN).quot(&Number::Int(-1)),
+            Ok(Number::BigInt(-BigInt::from(i64::MIN)))
+        );
+        assert_eq!(
+            Number::Int(i64::MIN).rem(&Number::Int(-1)),
+            Ok(Number::Int(0))
+        );
+        assert_eq!(ratio(7, 2).quot(&Number::Int(1)), Ok(Number::Int(3)));
+        assert_eq!(ratio(7, 2).modulo(&Number::Int(-1)), Ok(ratio(-1, 2)));
+        assert_eq!(
+            Number::Float(-7.5).modulo(&Number::Int(2)),
+            Ok(Number::Float(0.5))
+        );
+        assert!(Number::Float(1.).rem(&Number::Int(0)).is_err());
+    }
+
+    #[test]
+    fn test_pow() {
+        assert_eq!(Number::Int(2).pow(&Number::Int(10)), Ok(Number::Int(1024)));
+        assert_eq!(
+            Number::Int(2).pow(&Number::Int(64)),
+            Ok(Number::BigInt(BigInt::from(2).pow(64u32)))
+        );
+        assert_eq!(Number::Int(2).pow(&Number::Int(-2)), Ok(ratio(1, 4)));
+        assert_eq!(ratio(2, 3).pow(&Number::Int(2)), Ok(ratio(4, 9)));
+        assert_eq!(
+            Number::Int(4).pow(&Number::Float(0.5)),
+            Ok(Number::Float(2.))
+        );
+        assert!(Number::Int(0).pow(&Number::Int(-1)).is_err());
+
+        let huge = Number::Int(1_000_000_000_000);
+        assert_eq!(Number::Int(1).pow(&huge), Ok(Number::Int(1)));
+        assert_eq!(Number::Int(-1).pow(&huge), Ok(Number::Int(1)));
+        assert_eq!(Number::Int(0).pow(&huge), Ok(Number::Int(0)));
+        assert_eq!(
+            Number::Int(2).pow(&huge),
+            Err(MalError::RustFunction("Exponent is too large".to_string()))
+        );
+        assert!(ratio(1, 2).pow(&huge).is_err());
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(ratio(7, 2).floor(), Number::Int(3));
+        assert_eq!(ratio(-7, 2).floor(), Number::Int(-4));
+        assert_eq!(ratio(7, 2).ceil(), Number::Int(4));
+        assert_eq!(ratio(-5, 2).round(), Number::Int(-3));
+        assert_eq!(Number::Float(2.5).round(), Number::Float(3.));
+        assert_eq!(Number::Int(5).floor(), Number::Int(5));
+    }
+
+    #[test]
+    fn test_abs_and_signum() {
+        assert_eq!(
+            Number::Int(i64::MIN).abs(),
+            Ok(Number::BigInt(-BigInt::from(i64::MIN)))
+        );
+        assert_eq!(ratio(-1, 2).abs(), Ok(ratio(1, 2)));
+        assert_eq!(Number::Float(-0.).abs(), Ok(Number::Float(0.)));
+        assert_eq!(ratio(-1, 2).signum(), Number::Int(-1));
+        assert_eq!(Number::Float(-3.).signum(), Number::Float(-1.));
+        assert_eq!(Number::Float(0.).signum(), Number::Float(0.));
+    }
+
+    #[test]
+    fn test_partial_cmp() {
+        assert_eq!(
+            ratio(1, 3).partial_cmp(&ratio(1, 4)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            Number::BigInt(BigInt::from(5)).partial_cmp(&Number::Int(5)),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(Number::Float(f64::NAN).partial_cmp(&Number::Int(1)), None);
+    }
+
+    #[test]
+    fn test_rationalize() {
+        assert_eq!(rationalize(0.1), Ok(ratio(1, 10)));
+        assert_eq!(rationalize(-2.5), Ok(ratio(-5, 2)));
+        assert_eq!(
+            rationalize(1e20),
+            Ok(Number::BigInt(BigInt::from(10).pow(20)))
+        );
+        assert!(rationalize(f64::NAN).is_err());
+    }
+}
@@ -1,14 +1,35 @@
-use crate::types::MalMap;
+use crate::types::MalError;
 use crate::types::MalValue;
 use crate::types::MalValueType::*;
 use std::iter::once;
 
 pub fn pr_str(mal_value: &MalValue, print_readably: bool) -> String {
-    match *mal_value.mal_type {
+    try_pr_str(mal_value, print_readably).unwrap_or_else(|err| format!("#<error: {}>", err))
+}
+
+/// Like `pr_str`, but fails if realizing a lazy sequence fails.
+pub fn try_pr_str(mal_value: &MalValue, print_readably: bool) -> Result<String, MalError> {
+    Ok(match *mal_value.mal_type {
         Nil => "nil".to_string(),
         True => "true".to_string(),
         False => "false".to_string(),
-        Number(val) => val.to_string(),
+        Int(val) => val.to_string(),
+        BigInt(ref val) => {
+            if print_readably {
+                format!("{}N", val)
+            } else {
+                val.to_string()
+            }
+        }
+        Ratio(ref val) => val.to_string(),
+        Float(val) => pr_float(val),
+        Char(val) => {
+            if print_readably {
+                pr_char(val)
+            } else {
+                val.to_string()
+            }
+        }
         Symbol(ref val) => val.clone(),
         Str(ref val) => {
             if print_readably {
@@ -18,13 +39,80 @@ pub fn pr_str(mal_value: &MalValue, print_readably: bool) -> String {
             }
         }
         Keyword(ref val) => format!(":{}", val),
-        List(ref mal_list) => pr_seq(&mal_list.vec, "(", ")", print_readably),
-        Vector(ref mal_vec) => pr_seq(&mal_vec.vec, "[", "]", print_readably),
-        Map(ref mal_map) => pr_map(mal_map, print_readably),
+        List(ref mal_list) => pr_seq(&mal_list.vec, "(", ")", print_readably)?,
+        Vector(ref mal_vec) => pr_seq(&mal_vec.vec, "[", "]", print_readably)?,
+        LazySeq(ref lazy_seq) => pr_seq(&lazy_seq.to_vec()?, "(", ")", print_readably)?,
+        Map(ref mal_map) => pr_map(mal_map.iter(), print_readably)?,
+        Set(ref mal_set) => pr_seq(mal_set.iter(), "#{", "}", print_readably)?,
+        SortedMap(ref sorted_map) => pr_map(sorted_map.iter(), print_readably)?,
+        SortedSet(ref sorted_set) => pr_seq(sorted_set.iter(), "#{", "}", print_readably)?,
         RustFunc(_) => "#<rust_function>".to_string(),
         MalFunc(_) => "#<function>".to_string(),
-        Atom(ref val) => format!("(atom {})", pr_str(&(*val.borrow()), print_readably)),
+        Atom(ref val) => format!("(atom {})", try_pr_str(&(*val.borrow()), print_readably)?),
+        Regex(ref val) => {
+            if print_readably {
+                pr_regex(val.as_str())
+            } else {
+                val.as_str().to_string()
+            }
+        }
+        Reduced(ref val) => format!("(reduced {})", try_pr_str(val, print_readably)?),
+    })
+}
+
+fn pr_float(val: f64) -> String {
+    if val.is_nan() {
+        return "##NaN".to_string();
+    }
+
+    if val.is_infinite() {
+        return if val > 0. { "##Inf" } else { "##-Inf" }.to_string();
+    }
+
+    // Very large and very small floats are printed in exponent form, rather
+    // than with hundreds of digits.
+    if val != 0. && (val.abs() >= 1e16 || val.abs() < 1e-4) {
+        return format!("{:e}", val);
+    }
+
+    let text = val.to_string();
+
+    if text.contains('.') {
+        text
+    } else {
+        format!("{}.0", text)
+    }
+}
+
+fn pr_char(val: char) -> String {
+    match val {
+        '\n' => "\\newline".to_string(),
+        ' ' => "\\space".to_string(),
+        '\t' => "\\tab".to_string(),
+        '\r' => "\\return".to_string(),
+        '\u{8}' => "\\backspace".to_string(),
+        '\u{c}' => "\\formfeed".to_string(),
+        c if c.is_control() => format!("\\u{:04x}", c as u32),
+        c => format!("\\{}", c),
+    }
+}
+
+/// Escapes the quotes in the regex source that are not escaped already, so
+/// that the reader gets back an equivalent regex.
+fn pr_regex(source: &str) -> String {
+    let mut printed = String::from("#\"");
+    let mut escaped = false;
+
+    for c in source.chars() {
+        if c == '"' && !escaped {
+            printed.push('\\');
+        }
+        escaped = c == '\\' && !escaped;
+        printed.push(c);
     }
+
+    printed.push('"');
+    printed
 }
 
 fn escape_string(text: &str) -> String {
@@ -36,7 +124,11 @@ fn escape_string(text: &str) -> String {
             None => break,
             Some('\\') => escaped_str.push_str("\\\\"),
             Some('\n') => escaped_str.push_str("\\n"),
+            Some('\t') => escaped_str.push_str("\\t"),
+            Some('\r') => escaped_str.push_str("\\r"),
+            Some('\0') => escaped_str.push_str("\\0"),
             Some('"') => escaped_str.push_str("\\\""),
+            Some(c) if c.is_control() => escaped_str.push_str(&format!("\\u{{{:x}}}", c as u32)),
             Some(c) => escaped_str.push(c),
         }
     }
@@ -44,19 +136,27 @@ fn escape_string(text: &str) -> String {
     format!("\"{}\"", escaped_str)
 }
 
-fn pr_seq(list: &[MalValue], start: &str, end: &str, print_readably: bool) -> String {
-    let elements: Vec<String> = list.iter().map(|val| pr_str(val, print_readably)).collect();
+fn pr_seq<'a>(
+    list: impl IntoIterator<Item = &'a MalValue>,
+    start: &str,
+    end: &str,
+    print_readably: bool,
+) -> Result<String, MalError> {
+    let elements = list
+        .into_iter()
+        .map(|val| try_pr_str(val, print_readably))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    format!("{}{}{}", start, elements.join(" "), end)
+    Ok(format!("{}{}{}", start, elements.join(" "), end))
 }
 
-fn pr_map(mal_map: &MalMap, print_readably: bool) -> String {
-    let map_args: Vec<_> = mal_map
-        .iter()
-        .flat_map(|(key, val)| once(key.clone()).chain(once(val.clone())))
-        .collect();
+fn pr_map<'a>(
+    entries: impl Iterator<Item = (&'a MalValue, &'a MalValue)>,
+    print_readably: bool,
+) -> Result<String, MalError> {
+    let map_args = entries.flat_map(|(key, val)| once(key).chain(once(val)));
 
-    pr_seq(map_args.as_slice(), "{", "}", print_readably)
+    pr_seq(map_args, "{", "}", print_readably)
 }
 
 #[cfg(test)]
@@ -64,6 +164,7 @@ mod tests {
     use super::*;
     use crate::env::Env;
     use crate::types::MalMap;
+    use crate::types::MalSet;
 
     #[test]
     fn test_pr_str_nil() {
@@ -82,11 +183,38 @@ mod tests {
 
     #[test]
     fn test_pr_str_number() {
-        assert_eq!(pr_str(&MalValue::new(Number(123.)), true), "123");
-        assert_eq!(pr_str(&MalValue::new(Number(-12.)), true), "-12");
-        assert_eq!(pr_str(&MalValue::new(Number(7.5)), true), "7.5");
-        assert_eq!(pr_str(&MalValue::new(Number(0.)), true), "0");
-        assert_eq!(pr_str(&MalValue::new(Number(-12.3)), true), "-12.3");
+        assert_eq!(pr_str(&MalValue::new(Int(123)), true), "123");
+        assert_eq!(pr_str(&MalValue::new(Int(-12)), true), "-12");
+        assert_eq!(pr_str(&MalValue::new(Int(0)), true), "0");
+        assert_eq!(pr_str(&MalValue::new(BigInt(5.into())), true), "5N");
+        assert_eq!(pr_str(&MalValue::new(BigInt(5.into())), false), "5");
+        assert_eq!(
+            pr_str(
+                &MalValue::new(Ratio(num_rational::BigRational::new((-2).into(), 6.into()))),
+                true
+            ),
+            "-1/3"
+        );
+        assert_eq!(pr_str(&MalValue::new(Float(7.5)), true), "7.5");
+        assert_eq!(pr_str(&MalValue::new(Float(-12.3)), true), "-12.3");
+        assert_eq!(pr_str(&MalValue::new(Float(123.)), true), "123.0");
+        assert_eq!(pr_str(&MalValue::new(Float(0.)), true), "0.0");
+        assert_eq!(pr_str(&MalValue::new(Float(-12.)), true), "-12.0");
+        assert_eq!(pr_str(&MalValue::new(Float(f64::INFINITY)), true), "##Inf");
+        assert_eq!(
+            pr_str(&MalValue::new(Float(f64::NEG_INFINITY)), true),
+            "##-Inf"
+        );
+        assert_eq!(pr_str(&MalValue::new(Float(f64::NAN)), true), "##NaN");
+        assert_eq!(pr_str(&MalValue::new(Float(1e300)), true), "1e300");
+        assert_eq!(pr_str(&MalValue::new(Float(-2.5e-300)), true), "-2.5e-300");
+        assert_eq!(pr_str(&MalValue::new(Float(1e16)), true), "1e16");
+        assert_eq!(
+            pr_str(&MalValue::new(Float(1e15)), true),
+            "1000000000000000.0"
+        );
+        assert_eq!(pr_str(&MalValue::new(Float(0.0001)), true), "0.0001");
+        assert_eq!(pr_str(&MalValue::new(Float(0.00001)), true), "1e-5");
     }
 
     #[test]
@@ -133,6 +261,29 @@ mod tests {
             pr_str(&MalValue::new(Str("123\\abc".to_string())), true),
             r#""123\\abc""#
         );
+        assert_eq!(
+            pr_str(&MalValue::new(Str("a\tb\rc\0d".to_string())), true),
+            r#""a\tb\rc\0d""#
+        );
+        assert_eq!(
+            pr_str(&MalValue::new(Str("bell\u{7}".to_string())), true),
+            r#""bell\u{7}""#
+        );
+        assert_eq!(
+            pr_str(&MalValue::new(Str("smile \u{1F600}".to_string())), true),
+            "\"smile \u{1F600}\""
+        );
+    }
+
+    #[test]
+    fn test_pr_str_char() {
+        assert_eq!(pr_str(&MalValue::new(Char('a')), true), r"\a");
+        assert_eq!(pr_str(&MalValue::new(Char('λ')), true), r"\λ");
+        assert_eq!(pr_str(&MalValue::new(Char('\n')), true), r"\newline");
+        assert_eq!(pr_str(&MalValue::new(Char(' ')), true), r"\space");
+        assert_eq!(pr_str(&MalValue::new(Char('\u{1b}')), true), r"\u001b");
+        assert_eq!(pr_str(&MalValue::new(Char('a')), false), "a");
+        assert_eq!(pr_str(&MalValue::new(Char('\n')), false), "\n");
     }
 
     #[test]
@@ -177,7 +328,7 @@ mod tests {
             pr_str(
                 &MalValue::new_list(vec![
                     MalValue::new(Symbol("+".to_string())),
-                    MalValue::new(Number(456.)),
+                    MalValue::new(Int(456)),
                     MalValue::new(Symbol("y".to_string())),
                 ]),
                 true,
@@ -193,7 +344,7 @@ mod tests {
             pr_str(
                 &MalValue::new_vector(vec![
                     MalValue::new(Symbol("x".to_string())),
-                    MalValue::new(Number(456.)),
+                    MalValue::new(Int(456)),
                     MalValue::new(Symbol("y".to_string())),
                 ]),
                 true,
@@ -212,7 +363,7 @@ mod tests {
                     MalValue::new(Keyword("a".to_string())),
                     MalValue::new(Map(MalMap::from_arguments(&[
                         MalValue::new(Str("b".to_string())),
-                        MalValue::new(Number(12.)),
+                        MalValue::new(Int(12)),
                     ])
                     .unwrap())),
                 ])
@@ -223,11 +374,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_pr_str_set() {
+        assert_eq!(pr_str(&MalValue::new_set(MalSet::new()), true), "#{}");
+        assert_eq!(
+            pr_str(
+                &MalValue::new_set(MalSet::from_values(&[MalValue::new(Str("a".to_string()))])),
+                true
+            ),
+            "#{\"a\"}"
+        );
+    }
+
     #[test]
     fn test_pr_str_rustfunc() {
         assert_eq!(
             pr_str(
-                &MalValue::new_rust_func(|_, _| Ok(MalValue::new(Number(0.))), &Env::new()),
+                &MalValue::new_rust_func(|_, _| Ok(MalValue::new(Int(0))), &Env::new()),
                 true,
             ),
             "#<rust_function>"
@@ -248,7 +411,7 @@ mod tests {
     #[test]
     fn test_pr_str_atom() {
         assert_eq!(
-            pr_str(&MalValue::new_atom(MalValue::new(Number(123.))), true),
+            pr_str(&MalValue::new_atom(MalValue::new(Int(123))), true),
             "(atom 123)"
         )
     }
@@ -1,58 +1,104 @@
-use crate::tokenizer::tokenize;
+use crate::tokenizer::Tokenizer;
 use crate::types::MalError::*;
 use crate::types::MalTokenType;
 use crate::types::MalValueType::*;
-use crate::types::{MalError, MalMap, MalResult, MalToken, MalValue};
-
-#[derive(Debug)]
-struct Reader {
-    tokens: Vec<MalToken>,
-    cur_pos: usize,
+use crate::types::{MalError, MalMap, MalResult, MalSet, MalToken, MalValue, SourcePos};
+use std::rc::Rc;
+
+/// Reads the top-level forms of a program one at a time.
+///
+/// Each call to `next` only consumes the tokens of a single form, so earlier
+/// forms can be evaluated before a syntax error further down is reached.
+pub struct Reader<'a> {
+    tokens: Tokenizer<'a>,
+    peeked: Option<MalToken>,
 }
 
-impl Reader {
-    fn new(tokens: Vec<MalToken>) -> Reader {
-        Reader { tokens, cur_pos: 0 }
+impl<'a> Reader<'a> {
+    pub fn new(program: &'a str, file: Option<&str>) -> Reader<'a> {
+        Reader {
+            tokens: Tokenizer::new(program, file),
+            peeked: None,
+        }
     }
 
-    fn next(&mut self) -> Option<&MalToken> {
-        let token = self.tokens.get(self.cur_pos)?;
-        self.cur_pos += 1;
-        Some(token)
+    fn next_token(&mut self) -> Result<Option<MalToken>, MalError> {
+        match self.peeked.take() {
+            Some(token) => Ok(Some(token)),
+            None => self.tokens.next().transpose(),
+        }
     }
 
-    fn peek(&self) -> Option<&MalToken> {
-        self.tokens.get(self.cur_pos)
+    fn peek_token(&mut self) -> Result<Option<&MalToken>, MalError> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next().transpose()?;
+        }
+
+        Ok(self.peeked.as_ref())
+    }
+
+    fn peek_pos(&mut self) -> Result<Option<Rc<SourcePos>>, MalError> {
+        Ok(self.peek_token()?.and_then(|token| token.pos.clone()))
     }
 }
 
-pub fn read_str(program: &str) -> MalResult {
-    let tokens = tokenize(program)?;
+impl<'a> Iterator for Reader<'a> {
+    type Item = MalResult;
 
-    if tokens.is_empty() {
-        return Err(EmptyProgram);
+    fn next(&mut self) -> Option<MalResult> {
+        match self.peek_token() {
+            Ok(None) => None,
+            Ok(Some(_)) => Some(read_form(self)),
+            Err(err) => {
+                self.peeked = None;
+                Some(Err(err))
+            }
+        }
     }
+}
 
-    let mut reader = Reader::new(tokens);
+pub fn read_all<'a>(program: &'a str, file: Option<&str>) -> Reader<'a> {
+    Reader::new(program, file)
+}
+
+pub fn read_str(program: &str) -> MalResult {
+    read_str_with_file(program, None)
+}
+
+pub fn read_str_with_file(program: &str, file: Option<&str>) -> MalResult {
+    let mut reader = Reader::new(program, file);
 
-    let mal_value = read_form(&mut reader)?;
+    let mal_value = reader.next().unwrap_or(Err(EmptyProgram))?;
 
-    if reader.peek().is_some() {
-        return Err(Parser("Expected EOF, found token".to_string()));
+    if let Some(token) = reader.peek_token()? {
+        return Err(Parser(format!(
+            "Expected EOF, found token{}",
+            describe_pos(&token.pos)
+        )));
     }
 
     Ok(mal_value)
 }
 
+fn describe_pos(pos: &Option<Rc<SourcePos>>) -> String {
+    match pos {
+        Some(pos) => format!(" at {}", pos),
+        None => String::new(),
+    }
+}
+
 fn read_form(reader: &mut Reader) -> MalResult {
-    match reader
-        .peek()
+    let pos = reader.peek_pos()?;
+
+    let mal_value = match reader
+        .peek_token()?
         .ok_or_else(|| Parser("Unexpected EOF".to_string()))?
         .token_type
     {
         MalTokenType::LParen => read_list(reader),
         MalTokenType::LBracket => read_vector(reader),
         MalTokenType::LCurly => read_map(reader),
+        MalTokenType::HashLCurly => read_set(reader),
         MalTokenType::AtSign => read_short_form(reader, "deref"),
         MalTokenType::SingleQuote => read_short_form(reader, "quote"),
         MalTokenType::BackTick => read_short_form(reader, "quasiquote"),
@@ -60,7 +106,9 @@ fn read_form(reader: &mut Reader) -> MalResult {
         MalTokenType::TildeAtSign => read_short_form(reader, "splice-unquote"),
         MalTokenType::Caret => read_with_meta(reader),
         _ => read_atom(reader),
-    }
+    }?;
+
+    Ok(mal_value.with_pos(pos))
 }
 
 fn read_list(reader: &mut Reader) -> MalResult {
@@ -75,24 +123,41 @@ fn read_vector(reader: &mut Reader) -> MalResult {
 }
 
 fn read_map(reader: &mut Reader) -> MalResult {
-    Ok(MalValue::new(Map(MalMap::from_arguments(
-        read_seq(reader, &MalTokenType::RCurly)?.as_slice(),
-    )?)))
+    let pos = reader.peek_pos()?;
+    let elems = read_seq(reader, &MalTokenType::RCurly)?;
+
+    Ok(MalValue::new(Map(MalMap::from_arguments(elems.as_slice())
+        .map_err(|err| match err {
+            Parser(message) => Parser(format!("{}{}", message, describe_pos(&pos))),
+            other => other,
+        })?)))
+}
+
+fn read_set(reader: &mut Reader) -> MalResult {
+    let elems = read_seq(reader, &MalTokenType::RCurly)?;
+
+    Ok(MalValue::new_set(MalSet::from_values(elems.as_slice())))
 }
 
 fn read_seq(reader: &mut Reader, end_token: &MalTokenType) -> Result<Vec<MalValue>, MalError> {
-    reader.next().unwrap();
+    let start_pos = reader.next_token()?.unwrap().pos;
 
     let mut elems = Vec::new();
 
     loop {
         match reader
-            .peek()
-            .ok_or_else(|| Parser(format!("Expected '{:?}', got EOF", end_token).to_string()))?
+            .peek_token()?
+            .ok_or_else(|| {
+                Parser(format!(
+                    "Expected '{:?}', got EOF (opened{})",
+                    end_token,
+                    describe_pos(&start_pos)
+                ))
+            })?
             .token_type
         {
             ref t if t == end_token => {
-                reader.next().unwrap();
+                reader.next_token()?;
                 break;
             }
             _ => elems.push(read_form(reader)?),
@@ -103,44 +168,68 @@ fn read_seq(reader: &mut Reader, end_token: &MalTokenType) -> Result<Vec<MalValu
 }
 
 fn read_atom(reader: &mut Reader) -> MalResult {
-    match reader
-        .next()
-        .ok_or_else(|| Parser("Unexpected EOF".to_string()))?
-        .token_type
-    {
+    let token = reader
+        .next_token()?
+        .ok_or_else(|| Parser("Unexpected EOF".to_string()))?;
+
+    match token.token_type {
         MalTokenType::Nil => Ok(MalValue::nil()),
         MalTokenType::True => Ok(MalValue::new(True)),
         MalTokenType::False => Ok(MalValue::new(False)),
-        MalTokenType::Number(val) => Ok(MalValue::new(Number(val))),
+        MalTokenType::Int(val) => Ok(MalValue::new(Int(val))),
+        MalTokenType::BigInt(ref val) => Ok(MalValue::new(BigInt(val.clone()))),
+        MalTokenType::Ratio(ref val) => Ok(MalValue::new(Ratio(val.clone()))),
+        MalTokenType::Float(val) => Ok(MalValue::new(Float(val))),
+        MalTokenType::Char(val) => Ok(MalValue::new(Char(val))),
         MalTokenType::Symbol(ref val) => Ok(MalValue::new(Symbol(val.clone()))),
         MalTokenType::Str(ref val) => Ok(MalValue::new(Str(val.clone()))),
         MalTokenType::Keyword(ref val) => Ok(MalValue::new(Keyword(val.clone()))),
-        _ => Err(Parser("Unexpected token".to_string())),
+        MalTokenType::Regex(ref val) => regex::Regex::new(val)
+            .map(|re| MalValue::new(Regex(re.into())))
+            .map_err(|err| {
+                Parser(format!(
+                    "Invalid regex{}: {}",
+                    describe_pos(&token.pos),
+                    err
+                ))
+            }),
+        _ => Err(Parser(format!(
+            "Unexpected token{}",
+            describe_pos(&token.pos)
+        ))),
     }
 }
 
 fn read_short_form(reader: &mut Reader, name: &str) -> MalResult {
-    reader.next().unwrap();
+    let pos = reader.next_token()?.unwrap().pos;
 
     Ok(MalValue::new_list(vec![
-        MalValue::new(Symbol(name.to_string())),
-        read_form(reader)?,
+        MalValue::new(Symbol(name.to_string())).with_pos(pos.clone()),
+        read_operand(reader, &pos)?,
     ]))
 }
 
 fn read_with_meta(reader: &mut Reader) -> MalResult {
-    reader.next().unwrap();
+    let pos = reader.next_token()?.unwrap().pos;
 
-    let meta = read_form(reader)?;
-    let arg = read_form(reader)?;
+    let meta = read_operand(reader, &pos)?;
+    let arg = read_operand(reader, &pos)?;
 
     Ok(MalValue::new_list(vec![
-        MalValue::new(Symbol("with-meta".to_string())),
+        MalValue::new(Symbol("with-meta".to_string())).with_pos(pos),
         arg,
         meta,
     ]))
 }
 
+fn read_operand(reader: &mut Reader, pos: &Option<Rc<SourcePos>>) -> MalResult {
+    if reader.peek_token()?.is_none() {
+        return Err(Parser(format!("Unexpected EOF{}", describe_pos(pos))));
+    }
+
+    read_form(reader)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -151,52 +240,46 @@ mod tests {
 
     #[test]
     fn test_reader() {
-        let mut reader = Reader::new(vec![
-            MalToken::new(LParen),
-            MalToken::new(MalTokenType::Symbol("+".to_string())),
-            MalToken::new(MalTokenType::Number(2.)),
-            MalToken::new(MalTokenType::Symbol("x".to_string())),
-            MalToken::new(RParen),
-        ]);
+        let mut reader = Reader::new("(+ 2 x)", None);
 
-        assert_eq!(reader.peek(), Some(&MalToken::new(LParen)));
-        assert_eq!(reader.next(), Some(&MalToken::new(LParen)));
+        assert_eq!(reader.peek_token(), Ok(Some(&MalToken::new(LParen))));
+        assert_eq!(reader.next_token(), Ok(Some(MalToken::new(LParen))));
 
         assert_eq!(
-            reader.peek(),
-            Some(&MalToken::new(MalTokenType::Symbol("+".to_string())))
+            reader.peek_token(),
+            Ok(Some(&MalToken::new(MalTokenType::Symbol("+".to_string()))))
         );
         assert_eq!(
-            reader.next(),
-            Some(&MalToken::new(MalTokenType::Symbol("+".to_string())))
+            reader.next_token(),
+            Ok(Some(MalToken::new(MalTokenType::Symbol("+".to_string()))))
         );
 
         assert_eq!(
-            reader.peek(),
-            Some(&MalToken::new(MalTokenType::Number(2.)))
+            reader.peek_token(),
+            Ok(Some(&MalToken::new(MalTokenType::Int(2))))
         );
         assert_eq!(
-            reader.next(),
-            Some(&MalToken::new(MalTokenType::Number(2.)))
+            reader.next_token(),
+            Ok(Some(MalToken::new(MalTokenType::Int(2))))
         );
 
         assert_eq!(
-            reader.peek(),
-            Some(&MalToken::new(MalTokenType::Symbol("x".to_string())))
+            reader.peek_token(),
+            Ok(Some(&MalToken::new(MalTokenType::Symbol("x".to_string()))))
         );
         assert_eq!(
-            reader.next(),
-            Some(&MalToken::new(MalTokenType::Symbol("x".to_string())))
+            reader.next_token(),
+            Ok(Some(MalToken::new(MalTokenType::Symbol("x".to_string()))))
         );
 
-        assert_eq!(reader.peek(), Some(&MalToken::new(RParen)));
-        assert_eq!(reader.next(), Some(&MalToken::new(RParen)));
+        assert_eq!(reader.peek_token(), Ok(Some(&MalToken::new(RParen))));
+        assert_eq!(reader.next_token(), Ok(Some(MalToken::new(RParen))));
 
-        assert_eq!(reader.peek(), None);
-        assert_eq!(reader.next(), None);
+        assert_eq!(reader.peek_token(), Ok(None));
+        assert_eq!(reader.next_token(), Ok(None));
 
-        assert_eq!(reader.peek(), None);
-        assert_eq!(reader.next(), None);
+        assert_eq!(reader.peek_token(), Ok(None));
+        assert_eq!(reader.next_token(), Ok(None));
     }
 
     #[test]
@@ -223,10 +306,10 @@ mod tests {
 
     #[test]
     fn test_read_str_number() {
-        assert_eq!(read_str("123"), Ok(MalValue::new(Number(123.))));
-        assert_eq!(read_str("-12"), Ok(MalValue::new(Number(-12.))));
-        assert_eq!(read_str("-5.5"), Ok(MalValue::new(Number(-5.5))));
-        assert_eq!(read_str("10."), Ok(MalValue::new(Number(10.))));
+        assert_eq!(read_str("123"), Ok(MalValue::new(Int(123))));
+        assert_eq!(read_str("-12"), Ok(MalValue::new(Int(-12))));
+        assert_eq!(read_str("-5.5"), Ok(MalValue::new(Float(-5.5))));
+        assert_eq!(read_str("10."), Ok(MalValue::new(Float(10.))));
     }
 
     #[test]
@@ -257,6 +340,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_str_regex() {
+        assert_eq!(
+            read_str(r#"#"a\d""#),
+            Ok(MalValue::new(Regex(
+                regex::Regex::new(r"a\d").unwrap().into()
+            )))
+        );
+
+        match read_str(r#"#"a(""#) {
+            Err(MalError::Parser(_)) => {}
+            _ => unreachable!("Expected Parser error."),
+        }
+    }
+
     #[test]
     fn test_read_str_keyword() {
         assert_eq!(
@@ -286,7 +384,7 @@ mod tests {
             Ok(MalValue::new_list(vec![
                 MalValue::new(Symbol("-".to_string())),
                 MalValue::new(Symbol("xy".to_string())),
-                MalValue::new(Number(123.1)),
+                MalValue::new(Float(123.1)),
             ]))
         );
 
@@ -297,9 +395,9 @@ mod tests {
                 MalValue::new_list(vec![
                     MalValue::new(Symbol("f".to_string())),
                     MalValue::new_list(vec![MalValue::new(Symbol("g".to_string())),]),
-                    MalValue::new(Number(1.)),
+                    MalValue::new(Int(1)),
                 ]),
-                MalValue::new(Number(123.)),
+                MalValue::new(Int(123)),
             ]))
         );
 
@@ -325,7 +423,7 @@ mod tests {
             Ok(MalValue::new_vector(vec![
                 MalValue::new(Symbol("x".to_string())),
                 MalValue::new(Symbol("y".to_string())),
-                MalValue::new(Number(123.1)),
+                MalValue::new(Float(123.1)),
             ]))
         );
 
@@ -336,9 +434,9 @@ mod tests {
                 MalValue::new_vector(vec![
                     MalValue::new(Symbol("i".to_string())),
                     MalValue::new_vector(vec![MalValue::new(Symbol("j".to_string())),]),
-                    MalValue::new(Number(5.)),
+                    MalValue::new(Int(5)),
                 ]),
-                MalValue::new(Number(123.)),
+                MalValue::new(Int(123)),
             ]))
         );
 
@@ -372,7 +470,7 @@ mod tests {
                     MalValue::new(Map(MalMap::from_arguments(
                         vec![
                             MalValue::new(Keyword("s2".to_string())),
-                            MalValue::new(Number(123.)),
+                            MalValue::new(Int(123)),
                         ]
                         .as_slice()
                     )
@@ -394,6 +492,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_read_str_hash_set() {
+        assert_eq!(read_str("#{}"), Ok(MalValue::new_set(MalSet::new())));
+
+        assert_eq!(
+            read_str("#{\"a\" :b \"a\"}"),
+            Ok(MalValue::new_set(MalSet::from_values(&[
+                MalValue::new(Keyword("b".to_string())),
+                MalValue::new(Str("a".to_string())),
+            ])))
+        );
+
+        match read_str("#{:a") {
+            Err(MalError::Parser(_)) => {}
+            _ => unreachable!("Expected Parser error."),
+        }
+    }
+
     #[test]
     fn test_read_str_extra_tokens() {
         match read_str("aa 123") {
@@ -508,4 +624,84 @@ mod tests {
             _ => unreachable!("Expected Parser error."),
         }
     }
+
+    #[test]
+    fn test_read_str_positions() {
+        let form = read_str_with_file("(def! x\n  [1 {:a \"b\"}])", Some("f.mal")).unwrap();
+        let pos_of = |val: &MalValue| val.pos.as_ref().unwrap().to_string();
+
+        assert_eq!(pos_of(&form), "f.mal:1:1");
+
+        if let List(ref mal_list) = *form.mal_type {
+            assert_eq!(pos_of(&mal_list.vec[1]), "f.mal:1:7");
+            assert_eq!(pos_of(&mal_list.vec[2]), "f.mal:2:3");
+        } else {
+            unreachable!("Expected list.");
+        }
+
+        assert_eq!(pos_of(&read_str("  'a").unwrap()), "1:3");
+    }
+
+    #[test]
+    fn test_read_str_error_positions() {
+        assert_eq!(
+            read_str_with_file("\n (+ 1", Some("f.mal")),
+            Err(MalError::Parser(
+                "Expected 'RParen', got EOF (opened at f.mal:2:2)".to_string()
+            ))
+        );
+
+        assert_eq!(
+            read_str("(a))"),
+            Err(MalError::Parser(
+                "Expected EOF, found token at 1:4".to_string()
+            ))
+        );
+
+        assert_eq!(
+            read_str("[ ]\n)"),
+            Err(MalError::Parser(
+                "Expected EOF, found token at 2:1".to_string()
+            ))
+        );
+
+        assert_eq!(
+            read_str("(1 '"),
+            Err(MalError::Parser("Unexpected EOF at 1:4".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_read_all() {
+        let forms: Vec<MalResult> =
+            read_all("(+ 1 x) :a\n\"s\" ; trailing comment", None).collect();
+
+        assert_eq!(
+            forms,
+            vec![
+                Ok(MalValue::new_list(vec![
+                    MalValue::new(Symbol("+".to_string())),
+                    MalValue::new(Int(1)),
+                    MalValue::new(Symbol("x".to_string())),
+                ])),
+                Ok(MalValue::new(Keyword("a".to_string()))),
+                Ok(MalValue::new(Str("s".to_string()))),
+            ]
+        );
+
+        assert_eq!(read_all("", None).count(), 0);
+        assert_eq!(read_all("; only a comment", None).count(), 0);
+    }
+
+    #[test]
+    fn test_read_all_is_lazy() {
+        let mut reader = read_all("(def! a 1) (b \"unterminated", None);
+
+        assert!(reader.next().unwrap().is_ok());
+
+        match reader.next() {
+            Some(Err(MalError::Tokenizer(_))) => {}
+            _ => unreachable!("Expected Tokenizer error."),
+        }
+    }
 }
@@ -0,0 +1,247 @@
+use crate::env::Env;
+use crate::types::MalValueType::{
+    Char, LazySeq, List, Map, Nil, Set, SortedMap, SortedSet, Str, Vector,
+};
+use crate::types::{MalError, MalLazySeq, MalList, MalResult, MalValue, MalVector, RustFn};
+use std::cell::RefCell;
+use std::iter;
+use std::rc::Rc;
+
+/// A value whose elements can be walked in order: lists, vectors, lazy
+/// sequences, maps and sorted maps (as `[key value]` entries), sets, strings
+/// (as chars) and nil (as the empty sequence).
+///
+/// Sequence builtins should be written against this trait rather than match
+/// on collection types, so that every collection is accepted uniformly.
+pub trait Seqable {
+    /// Splits the sequence into its first element and the rest, realizing lazy
+    /// sequences as needed. Returns `None` when the sequence is empty.
+    fn first_rest(&self) -> Result<Option<(MalValue, MalValue)>, MalError>;
+
+    /// Returns all the elements, realizing lazy sequences entirely. This is
+    /// cheap for lists and vectors, which share their structure.
+    fn seq_elements(&self) -> Result<im_rc::Vector<MalValue>, MalError>;
+
+    /// Returns nil for an empty sequence. Otherwise returns lists and lazy
+    /// sequences unchanged and any other collection as a list.
+    fn to_seq(&self) -> MalResult;
+
+    fn seq_iter(&self) -> SeqIter;
+}
+
+impl Seqable for MalValue {
+    fn first_rest(&self) -> Result<Option<(MalValue, MalValue)>, MalError> {
+        let vec = match *self.mal_type {
+            Nil => return Ok(None),
+            LazySeq(ref lazy_seq) => return lazy_seq.realize(),
+            List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) => vec.clone(),
+            _ => return cursor_first_rest(&Rc::new(RefCell::new(collection_iter(self)?))),
+        };
+
+        Ok(vec
+            .front()
+            .map(|first| (first.clone(), MalValue::new_list(vec.skip(1)))))
+    }
+
+    fn seq_elements(&self) -> Result<im_rc::Vector<MalValue>, MalError> {
+        match *self.mal_type {
+            List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) => Ok(vec.clone()),
+            LazySeq(ref lazy_seq) => Ok(lazy_seq.to_vec()?.into()),
+            _ => Ok(collection_iter(self)?.collect()),
+        }
+    }
+
+    fn to_seq(&self) -> MalResult {
+        match *self.mal_type {
+            List(MalList { ref vec, .. }) if !vec.is_empty() => Ok(self.clone()),
+            LazySeq(ref lazy_seq) if lazy_seq.realize()?.is_some() => Ok(self.clone()),
+            _ => {
+                let elements = self.seq_elements()?;
+
+                Ok(if elements.is_empty() {
+                    MalValue::nil()
+                } else {
+                    MalValue::new_list(elements)
+                })
+            }
+        }
+    }
+
+    fn seq_iter(&self) -> SeqIter {
+        SeqIter {
+            rest: Some(self.clone()),
+        }
+    }
+}
+
+/// Iterates over a sequence one element at a time, so that lazy sequences are
+/// only realized as far as they are consumed.
+pub struct SeqIter {
+    rest: Option<MalValue>,
+}
+
+impl Iterator for SeqIter {
+    type Item = Result<MalValue, MalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.rest.take()?.first_rest() {
+            Ok(Some((first, rest))) => {
+                self.rest = Some(rest);
+                Some(Ok(first))
+            }
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+pub fn map_entry((key, val): (&MalValue, &MalValue)) -> MalValue {
+    MalValue::new_vector(vec![key.clone(), val.clone()])
+}
+
+type Cursor = Rc<RefCell<Box<dyn Iterator<Item = MalValue>>>>;
+
+/// Takes the next element from `cursor`, with the rest of the sequence as a
+/// lazy sequence that resumes from the same cursor when realized. Every cell
+/// is realized once, so each element is taken exactly once and walking a map,
+/// set or string costs O(1) per step instead of copying what is left of it.
+fn cursor_first_rest(cursor: &Cursor) -> Result<Option<(MalValue, MalValue)>, MalError> {
+    let first = cursor.borrow_mut().next();
+
+    Ok(first.map(|first| (first, cursor_seq(cursor.clone()))))
+}
+
+fn cursor_seq(cursor: Cursor) -> MalValue {
+    let step: RustFn = Rc::new(move |_, _| {
+        Ok(match cursor_first_rest(&cursor)? {
+            Some((first, rest)) => MalValue::new_lazy_seq(MalLazySeq::cons(first, rest)),
+            None => MalValue::nil(),
+        })
+    });
+
+    MalValue::new_lazy_seq(MalLazySeq::new(
+        MalValue::new_rust_closure(step, &Env::new()),
+        vec![],
+    ))
+}
+
+/// Iterates over the elements of a collection that is not itself a list,
+/// vector or lazy sequence, without copying it.
+fn collection_iter(value: &MalValue) -> Result<Box<dyn Iterator<Item = MalValue>>, MalError> {
+    let owned_entry = |(key, val)| MalValue::new_vector(vec![key, val]);
+
+    match *value.mal_type {
+        Nil => Ok(Box::new(iter::empty())),
+        Map(ref mal_map) => Ok(Box::new(mal_map.iter_owned().map(owned_entry))),
+        SortedMap(ref sorted_map) => Ok(Box::new(sorted_map.iter_owned().map(owned_entry))),
+        Set(ref mal_set) => Ok(Box::new(mal_set.iter_owned())),
+        SortedSet(ref sorted_set) => Ok(Box::new(sorted_set.iter_owned())),
+        Str(_) => {
+            let string = value.clone();
+            let mut offset = 0;
+
+            Ok(Box::new(iter::from_fn(move || match *string.mal_type {
+                Str(ref s) => {
+                    let c = s[offset..].chars().next()?;
+                    offset += c.len_utf8();
+                    Some(MalValue::new(Char(c)))
+                }
+                _ => None,
+            })))
+        }
+        _ => Err(MalError::RustFunction(
+            "Argument must be a sequence".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MalMap;
+    use crate::types::MalValueType::Int;
+
+    fn int(n: i64) -> MalValue {
+        MalValue::new(Int(n))
+    }
+
+    #[test]
+    fn test_first_rest_of_any_collection() {
+        let list = MalValue::new_list(vec![int(1), int(2)]);
+        assert_eq!(
+            list.first_rest(),
+            Ok(Some((int(1), MalValue::new_list(vec![int(2)]))))
+        );
+
+        let string = MalValue::new(Str("ab".to_string()));
+        assert_eq!(
+            string.first_rest(),
+            Ok(Some((
+                MalValue::new(Char('a')),
+                MalValue::new_list(vec![MalValue::new(Char('b'))])
+            )))
+        );
+
+        assert_eq!(MalValue::nil().first_rest(), Ok(None));
+        assert!(int(1).first_rest().is_err());
+    }
+
+    #[test]
+    fn test_maps_are_sequences_of_entries() {
+        let map =
+            MalValue::new_map(MalMap::from_arguments(&[int(1), int(2), int(3), int(4)]).unwrap());
+
+        let entries: Result<Vec<_>, _> = map.seq_iter().collect();
+        assert_eq!(
+            entries,
+            Ok(vec![
+                MalValue::new_vector(vec![int(1), int(2)]),
+                MalValue::new_vector(vec![int(3), int(4)])
+            ])
+        );
+    }
+
+    #[test]
+    fn test_rest_of_a_collection_is_walked_lazily() {
+        let map = MalValue::new_map(
+            MalMap::from_arguments(&[int(1), int(2), int(3), int(4), int(5), int(6)]).unwrap(),
+        );
+
+        let (first, rest) = map.first_rest().unwrap().unwrap();
+        assert_eq!(first, MalValue::new_vector(vec![int(1), int(2)]));
+        match *rest.mal_type {
+            LazySeq(ref lazy_seq) => assert!(!lazy_seq.is_realized()),
+            _ => panic!("Expected a lazy sequence, got {:?}", rest),
+        }
+        assert_eq!(
+            rest.seq_iter().collect::<Result<Vec<_>, _>>(),
+            Ok(vec![
+                MalValue::new_vector(vec![int(3), int(4)]),
+                MalValue::new_vector(vec![int(5), int(6)])
+            ])
+        );
+
+        let string = MalValue::new(Str("añb".to_string()));
+        assert_eq!(
+            string.seq_iter().collect::<Result<Vec<_>, _>>(),
+            Ok(vec![
+                MalValue::new(Char('a')),
+                MalValue::new(Char('ñ')),
+                MalValue::new(Char('b'))
+            ])
+        );
+    }
+
+    #[test]
+    fn test_to_seq() {
+        assert_eq!(MalValue::new_vector(vec![]).to_seq(), Ok(MalValue::nil()));
+        assert_eq!(
+            MalValue::new_vector(vec![int(1)]).to_seq(),
+            Ok(MalValue::new_list(vec![int(1)]))
+        );
+        assert_eq!(
+            MalValue::new(Str(String::new())).to_seq(),
+            Ok(MalValue::nil())
+        );
+    }
+}
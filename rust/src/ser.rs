@@ -0,0 +1,414 @@
+use crate::serde_error::SerdeError;
+use crate::types::MalValueType::{BigInt, Char, Float, Int, Keyword, Str};
+use crate::types::{MalMap, MalValue};
+use num_traits::ToPrimitive;
+use serde::ser::{self, Serialize};
+
+/// Converts a Rust value into a mal value. Structs become maps with keyword
+/// keys, sequences and tuples become vectors and unit variants become
+/// keywords. Other enum variants become a map with a single entry from the
+/// variant name to its content.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<MalValue, SerdeError> {
+    value.serialize(Serializer)
+}
+
+pub struct Serializer;
+
+fn new_integer(val: num_bigint::BigInt) -> MalValue {
+    match val.to_i64() {
+        Some(val) => MalValue::new(Int(val)),
+        None => MalValue::new(BigInt(val)),
+    }
+}
+
+fn new_keyword(name: &str) -> MalValue {
+    MalValue::new(Keyword(name.to_string()))
+}
+
+fn new_variant(variant: &str, content: MalValue) -> Result<MalValue, SerdeError> {
+    Ok(MalValue::new_map(MalMap::from_arguments(&[
+        new_keyword(variant),
+        content,
+    ])?))
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = MalValue;
+    type Error = SerdeError;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeVec;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    fn serialize_bool(self, v: bool) -> Result<MalValue, SerdeError> {
+        Ok(MalValue::new_boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<MalValue, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<MalValue, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<MalValue, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<MalValue, SerdeError> {
+        Ok(MalValue::new(Int(v)))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<MalValue, SerdeError> {
+        Ok(new_integer(num_bigint::BigInt::from(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<MalValue, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<MalValue, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<MalValue, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<MalValue, SerdeError> {
+        Ok(new_integer(num_bigint::BigInt::from(v)))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<MalValue, SerdeError> {
+        Ok(new_integer(num_bigint::BigInt::from(v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<MalValue, SerdeError> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<MalValue, SerdeError> {
+        Ok(MalValue::new(Float(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<MalValue, SerdeError> {
+        Ok(MalValue::new(Char(v)))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<MalValue, SerdeError> {
+        Ok(MalValue::new(Str(v.to_string())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<MalValue, SerdeError> {
+        Ok(MalValue::new_vector(
+            v.iter()
+                .map(|&byte| MalValue::new(Int(i64::from(byte))))
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<MalValue, SerdeError> {
+        Ok(MalValue::nil())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<MalValue, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<MalValue, SerdeError> {
+        Ok(MalValue::nil())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<MalValue, SerdeError> {
+        Ok(MalValue::nil())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<MalValue, SerdeError> {
+        Ok(new_keyword(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<MalValue, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<MalValue, SerdeError> {
+        new_variant(variant, to_value(value)?)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec, SerdeError> {
+        Ok(SerializeVec {
+            elements: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec, SerdeError> {
+        Ok(SerializeVec {
+            elements: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap, SerdeError> {
+        Ok(SerializeMap {
+            arguments: Vec::with_capacity(2 * len.unwrap_or(0)),
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, SerdeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeMap, SerdeError> {
+        Ok(SerializeMap {
+            arguments: Vec::with_capacity(2 * len),
+            variant: Some(variant),
+        })
+    }
+}
+
+/// Collects sequences and tuples into a vector. Tuple variants are wrapped in
+/// a map from the variant name to the vector.
+pub struct SerializeVec {
+    elements: Vec<MalValue>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeVec {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.elements.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<MalValue, SerdeError> {
+        let vector = MalValue::new_vector(self.elements);
+
+        match self.variant {
+            Some(variant) => new_variant(variant, vector),
+            None => Ok(vector),
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = MalValue;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<MalValue, SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = MalValue;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<MalValue, SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = MalValue;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<MalValue, SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeVec {
+    type Ok = MalValue;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<MalValue, SerdeError> {
+        self.finish()
+    }
+}
+
+/// Collects maps and structs into a map. Struct fields become keyword keys.
+/// Struct variants are wrapped in a map from the variant name to the map.
+pub struct SerializeMap {
+    arguments: Vec<MalValue>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeMap {
+    fn finish(self) -> Result<MalValue, SerdeError> {
+        let map = MalValue::new_map(MalMap::from_arguments(&self.arguments)?);
+
+        match self.variant {
+            Some(variant) => new_variant(variant, map),
+            None => Ok(map),
+        }
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = MalValue;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        self.arguments.push(to_value(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.arguments.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<MalValue, SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = MalValue;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.arguments.push(new_keyword(key));
+        self.arguments.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<MalValue, SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap {
+    type Ok = MalValue;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<MalValue, SerdeError> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de::from_value;
+    use crate::printer::pr_str;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Line(i64, i64),
+        Rect { width: u64, height: u64 },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Drawing {
+        title: String,
+        shapes: Vec<Shape>,
+        origin: (i64, i64),
+        scale: Option<f64>,
+        big: u64,
+    }
+
+    #[test]
+    fn test_to_value() {
+        let drawing = Drawing {
+            title: "d".to_string(),
+            shapes: vec![
+                Shape::Point,
+                Shape::Circle(1.5),
+                Shape::Line(1, 2),
+                Shape::Rect {
+                    width: 3,
+                    height: 4,
+                },
+            ],
+            origin: (0, -1),
+            scale: None,
+            big: u64::MAX,
+        };
+
+        let value = to_value(&drawing).unwrap();
+
+        assert_eq!(
+            pr_str(&value, true),
+            "{:title \"d\" :shapes [:Point {:Circle 1.5} {:Line [1 2]} {:Rect {:width 3 :height 4}}] \
+             :origin [0 -1] :scale nil :big 18446744073709551615N}"
+        );
+        assert_eq!(from_value::<Drawing>(&value), Ok(drawing));
+    }
+}
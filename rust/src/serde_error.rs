@@ -0,0 +1,86 @@
+use crate::types::MalError;
+use std::fmt;
+
+/// An error converting between a `MalValue` and a Rust type with serde.
+///
+/// Deserialization errors record the path to the offending value, such as
+/// `servers[1].port`, so that mistakes in configuration files are easy to find.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SerdeError {
+    message: String,
+    path: Vec<PathSegment>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PathSegment {
+    Field(String),
+    Index(usize),
+}
+
+impl SerdeError {
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The path from the root value to the offending value, or an empty string
+    /// if the error is about the root value itself.
+    pub fn path(&self) -> String {
+        let mut path = String::new();
+
+        for segment in &self.path {
+            match segment {
+                PathSegment::Field(name) if path.is_empty() => path.push_str(name),
+                PathSegment::Field(name) => {
+                    path.push('.');
+                    path.push_str(name);
+                }
+                PathSegment::Index(index) => path.push_str(&format!("[{}]", index)),
+            }
+        }
+
+        path
+    }
+
+    pub(crate) fn in_field(mut self, name: String) -> SerdeError {
+        self.path.insert(0, PathSegment::Field(name));
+        self
+    }
+
+    pub(crate) fn at_index(mut self, index: usize) -> SerdeError {
+        self.path.insert(0, PathSegment::Index(index));
+        self
+    }
+}
+
+impl fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{} at {}", self.message, self.path())
+        }
+    }
+}
+
+impl std::error::Error for SerdeError {}
+
+impl serde::de::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> SerdeError {
+        SerdeError {
+            message: msg.to_string(),
+            path: Vec::new(),
+        }
+    }
+}
+
+impl serde::ser::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> SerdeError {
+        <SerdeError as serde::de::Error>::custom(msg)
+    }
+}
+
+impl From<MalError> for SerdeError {
+    fn from(err: MalError) -> SerdeError {
+        <SerdeError as serde::de::Error>::custom(err)
+    }
+}
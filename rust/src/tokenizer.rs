@@ -1,26 +1,105 @@
+use crate::number::{parse_ratio, Number};
 use crate::types::MalError;
 use crate::types::MalToken;
 use crate::types::MalTokenType;
 use crate::types::MalTokenType::*;
+use crate::types::SourcePos;
 use lazy_static::lazy_static;
-use regex::Regex;
+use regex::{CaptureMatches, Regex};
+use std::rc::Rc;
 
 pub fn tokenize(program: &str) -> Result<Vec<MalToken>, MalError> {
-    const TOKEN_RE_STR: &str =
-        r##"[\s,]*(~@|[\[\]{}()'`~^@]|"(?:\\.|[^\\"])*"?|;.*|[^\s\[\]{}('"`,;)]+)"##;
-    lazy_static! {
-        static ref TOKEN_RE: Regex = Regex::new(TOKEN_RE_STR).unwrap();
+    tokenize_with_file(program, None)
+}
+
+pub fn tokenize_with_file(program: &str, file: Option<&str>) -> Result<Vec<MalToken>, MalError> {
+    Tokenizer::new(program, file).collect()
+}
+
+/// Lazily scans the tokens of `program`, so that a reader can start building
+/// forms before the whole program has been tokenized.
+pub struct Tokenizer<'a> {
+    program: &'a str,
+    captures: CaptureMatches<'static, 'a>,
+    file: Option<Rc<str>>,
+    cursor: Cursor,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(program: &'a str, file: Option<&str>) -> Tokenizer<'a> {
+        const TOKEN_RE_STR: &str = r##"[\s,]*(~@|#\{|[\[\]{}()'`~^@]|#?"(?:\\(?s:.)|[^\\"])*"?|r#"(?s:.)*?(?:"#|\z)|r"[^"]*"?|;.*|\\(?s:.)[^\s\[\]{}('"`,;)]*|[^\s\[\]{}('"`,;)]+)"##;
+        lazy_static! {
+            static ref TOKEN_RE: Regex = Regex::new(TOKEN_RE_STR).unwrap();
+        }
+
+        Tokenizer {
+            program,
+            captures: TOKEN_RE.captures_iter(program),
+            file: file.map(Rc::from),
+            cursor: Cursor::new(),
+        }
     }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<MalToken, MalError>;
 
-    let mut tokens: Vec<MalToken> = vec![];
+    fn next(&mut self) -> Option<Result<MalToken, MalError>> {
+        for capture in &mut self.captures {
+            let token_match = capture.get(1).unwrap();
+            self.cursor.advance_to(self.program, token_match.start());
 
-    for capture in TOKEN_RE.captures_iter(program) {
-        if let Some(token_type) = scan_token(&capture[1])? {
-            tokens.push(MalToken::new(token_type))
+            let pos = SourcePos {
+                file: self.file.clone(),
+                line: self.cursor.line,
+                column: self.cursor.column,
+            };
+
+            match scan_token(token_match.as_str()) {
+                Ok(Some(token_type)) => return Some(Ok(MalToken::with_pos(token_type, pos))),
+                Ok(None) => {}
+                Err(err) => return Some(Err(locate_tokenizer_error(err, &pos))),
+            }
         }
+
+        None
     }
+}
+
+struct Cursor {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Cursor {
+    fn new() -> Cursor {
+        Cursor {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn advance_to(&mut self, program: &str, offset: usize) {
+        for c in program[self.offset..offset].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
 
-    Ok(tokens)
+        self.offset = offset;
+    }
+}
+
+fn locate_tokenizer_error(err: MalError, pos: &SourcePos) -> MalError {
+    match err {
+        MalError::Tokenizer(message) => MalError::Tokenizer(format!("{} at {}", message, pos)),
+        other => other,
+    }
 }
 
 fn scan_token(text: &str) -> Result<Option<MalTokenType>, MalError> {
@@ -40,9 +119,15 @@ fn scan_token(text: &str) -> Result<Option<MalTokenType>, MalError> {
         '`' => Ok(Some(BackTick)),
         '~' => Ok(Some(if text == "~@" { TildeAtSign } else { Tilde })),
         '^' => Ok(Some(Caret)),
+        '#' if text == "#{" => Ok(Some(HashLCurly)),
+        '#' if text.starts_with("#\"") => Ok(Some(MalTokenType::Regex(scan_regex(text)?))),
         ';' => Ok(None),
         '"' => Ok(Some(Str(scan_string(text)?))),
+        'r' if text.starts_with("r\"") || text.starts_with("r#\"") => {
+            Ok(Some(Str(scan_raw_string(text)?)))
+        }
         ':' => Ok(Some(scan_keyword(text))),
+        '\\' => Ok(Some(Char(scan_char(text)?))),
         _ => Ok(Some(scan_nonspecial_token(text)?)),
     }
 }
@@ -50,32 +135,139 @@ fn scan_token(text: &str) -> Result<Option<MalTokenType>, MalError> {
 fn scan_string(text: &str) -> Result<String, MalError> {
     let mut unescaped_str = String::new();
 
-    let mut chars = text.chars();
+    let mut chars = text.chars().peekable();
     chars.next().unwrap();
 
     loop {
         match chars.next() {
             Some('\"') => break,
-            Some('\\') => {
-                unescaped_str.push(unescape_char(chars.next().ok_or_else(|| {
-                    MalError::Tokenizer("Expected '\"', got EOF".to_string())
-                })?))
-            }
+            Some('\\') => match chars.next() {
+                // A backslash at the end of a line joins it with the next
+                // one, skipping the next line's leading whitespace.
+                Some('\n') => while chars.next_if(|c| c.is_whitespace()).is_some() {},
+                Some(c) => unescaped_str.push(unescape_char(c, &mut chars)?),
+                None => return Err(MalError::Tokenizer("Expected '\"', got EOF".to_string())),
+            },
             Some(c) => unescaped_str.push(c),
             None => return Err(MalError::Tokenizer("Expected '\"', got EOF".to_string())),
         }
     }
 
-    Ok(unescaped_str.to_string())
+    Ok(unescaped_str)
 }
 
-fn unescape_char(c: char) -> char {
+fn unescape_char(c: char, chars: &mut impl Iterator<Item = char>) -> Result<char, MalError> {
     match c {
-        'n' => '\n',
-        other => other,
+        'n' => Ok('\n'),
+        't' => Ok('\t'),
+        'r' => Ok('\r'),
+        '0' => Ok('\0'),
+        '\\' | '"' => Ok(c),
+        'x' => {
+            let digits: String = chars.take(2).collect();
+            scan_code_point(&digits, 2).ok_or_else(|| invalid_escape("\\x", &digits))
+        }
+        'u' => {
+            let mut chars = chars.peekable();
+
+            if chars.peek() == Some(&'{') {
+                chars.next();
+                let digits: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                scan_code_point(&digits, 6)
+                    .ok_or_else(|| invalid_escape("\\u{", &format!("{}}}", digits)))
+            } else {
+                let digits: String = chars.take(4).collect();
+                scan_code_point(&digits, 4).ok_or_else(|| invalid_escape("\\u", &digits))
+            }
+        }
+        other => Err(invalid_escape("\\", &other.to_string())),
     }
 }
 
+/// Parses the hex digits of a `\x`, `\u` or `\u{...}` escape. `\x` and `\u`
+/// must have exactly `max_len` digits; `\u{...}` takes between 1 and 6.
+fn scan_code_point(digits: &str, max_len: usize) -> Option<char> {
+    let valid_len = if max_len == 6 {
+        (1..=6).contains(&digits.len())
+    } else {
+        digits.len() == max_len
+    };
+
+    if !valid_len || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    std::char::from_u32(u32::from_str_radix(digits, 16).ok()?)
+}
+
+fn invalid_escape(prefix: &str, rest: &str) -> MalError {
+    MalError::Tokenizer(format!("Invalid escape sequence: {}{}", prefix, rest))
+}
+
+/// Raw strings (`r"..."` or `r#"..."#`) are taken verbatim, without escapes.
+/// The `#` form may contain `"` characters.
+fn scan_raw_string(text: &str) -> Result<String, MalError> {
+    let (start, end) = if text.starts_with("r#") {
+        ("r#\"", "\"#")
+    } else {
+        ("r\"", "\"")
+    };
+
+    if text.len() < start.len() + end.len() || !text.ends_with(end) {
+        return Err(MalError::Tokenizer(format!("Expected '{}', got EOF", end)));
+    }
+
+    Ok(text[start.len()..text.len() - end.len()].to_string())
+}
+
+/// Regex literals (`#"..."`) are taken verbatim, so that backslashes reach the
+/// regex engine untouched. Only `\"` is unescaped, to allow `"` in a pattern.
+fn scan_regex(text: &str) -> Result<String, MalError> {
+    let mut pattern = String::new();
+    let mut chars = text[2..].chars();
+
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(pattern),
+            Some('\\') => match chars.next() {
+                Some('"') => pattern.push('"'),
+                Some(c) => {
+                    pattern.push('\\');
+                    pattern.push(c);
+                }
+                None => break,
+            },
+            Some(c) => pattern.push(c),
+            None => break,
+        }
+    }
+
+    Err(MalError::Tokenizer("Expected '\"', got EOF".to_string()))
+}
+
+fn scan_char(text: &str) -> Result<char, MalError> {
+    let name = &text[1..];
+
+    match name {
+        "newline" => return Ok('\n'),
+        "space" => return Ok(' '),
+        "tab" => return Ok('\t'),
+        "return" => return Ok('\r'),
+        "backspace" => return Ok('\u{8}'),
+        "formfeed" => return Ok('\u{c}'),
+        _ => {}
+    }
+
+    let mut chars = name.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        (Some('u'), Some(_)) => scan_code_point(&name[1..], 4),
+        _ => None,
+    }
+    .ok_or_else(|| MalError::Tokenizer(format!("Invalid character literal: {}", text)))
+}
+
 fn scan_keyword(text: &str) -> MalTokenType {
     Keyword(text[1..].to_string())
 }
@@ -92,21 +284,108 @@ fn scan_nonspecial_token(text: &str) -> Result<MalTokenType, MalError> {
         return Ok(reserved_name.unwrap());
     }
 
-    const NUMBER_RE_STR: &str = r#"^-?\d+\.?\d*$"#;
+    match text {
+        "##Inf" => return Ok(Float(f64::INFINITY)),
+        "##-Inf" => return Ok(Float(f64::NEG_INFINITY)),
+        "##NaN" => return Ok(Float(f64::NAN)),
+        _ => {}
+    }
+
+    const INT_RE_STR: &str = r#"^[+-]?\d+(?:_\d+)*(N?)$"#;
+    const RATIO_RE_STR: &str = r#"^([+-]?\d+)/(\d+)$"#;
+    const RADIX_INT_RE_STR: &str = r#"^([+-]?)0([xXoObB])([[:xdigit:]]+(?:_[[:xdigit:]]+)*)$"#;
+    const FLOAT_RE_STR: &str =
+        r#"^[+-]?(?:\d+(?:_\d+)*(?:\.(?:\d+(?:_\d+)*)?)?|\.\d+(?:_\d+)*)(?:[eE][+-]?\d+)?$"#;
     lazy_static! {
-        static ref NUMBER_RE: Regex = Regex::new(NUMBER_RE_STR).unwrap();
+        static ref INT_RE: Regex = Regex::new(INT_RE_STR).unwrap();
+        static ref RATIO_RE: Regex = Regex::new(RATIO_RE_STR).unwrap();
+        static ref RADIX_INT_RE: Regex = Regex::new(RADIX_INT_RE_STR).unwrap();
+        static ref FLOAT_RE: Regex = Regex::new(FLOAT_RE_STR).unwrap();
     }
 
-    if NUMBER_RE.is_match(&text) {
-        return Ok(Number(
-            text.parse()
-                .unwrap_or_else(|_| panic!("Error parsing number: {}", text)),
-        ));
+    if let Some(captures) = INT_RE.captures(text) {
+        let digits = text.trim_end_matches('N').replace('_', "");
+
+        if captures[1].is_empty() {
+            return scan_int(text, &digits, 10);
+        }
+
+        return Ok(BigInt(digits.parse().unwrap()));
+    }
+
+    if let Some(captures) = RATIO_RE.captures(text) {
+        return match parse_ratio(&captures[1], &captures[2]) {
+            Some(Number::Int(val)) => Ok(Int(val)),
+            Some(Number::BigInt(val)) => Ok(BigInt(val)),
+            Some(Number::Ratio(val)) => Ok(Ratio(val)),
+            _ => Err(MalError::Tokenizer(format!(
+                "Invalid ratio literal: {}",
+                text
+            ))),
+        };
+    }
+
+    if let Some(captures) = RADIX_INT_RE.captures(text) {
+        let radix = match &captures[2] {
+            "x" | "X" => 16,
+            "o" | "O" => 8,
+            _ => 2,
+        };
+        let digits = format!("{}{}", &captures[1], captures[3].replace('_', ""));
+
+        return scan_int(text, &digits, radix);
+    }
+
+    if FLOAT_RE.is_match(text) {
+        return text
+            .replace('_', "")
+            .parse()
+            .map(Float)
+            .map_err(|_| MalError::Tokenizer(format!("Invalid float literal: {}", text)));
+    }
+
+    if looks_like_number(text) {
+        return Err(MalError::Tokenizer(format!(
+            "Invalid number literal: {}",
+            text
+        )));
     }
 
     Ok(Symbol(text.to_string()))
 }
 
+/// Whether a token is meant as a number: it starts with a digit, or with a
+/// sign or a dot followed by a digit, or is made of digits and separators
+/// only (like `_1`). Such a token can't be a symbol.
+fn looks_like_number(text: &str) -> bool {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_digit() => true,
+        Some('+') | Some('-') | Some('.') => matches!(chars.next(), Some(c) if c.is_ascii_digit()),
+        _ => {
+            text.chars().any(|c| c.is_ascii_digit())
+                && text.chars().all(|c| c.is_ascii_digit() || c == '_')
+        }
+    }
+}
+
+fn scan_int(text: &str, digits: &str, radix: u32) -> Result<MalTokenType, MalError> {
+    use std::num::IntErrorKind::{NegOverflow, PosOverflow};
+
+    i64::from_str_radix(digits, radix)
+        .map(Int)
+        .or_else(|err| match err.kind() {
+            PosOverflow | NegOverflow => num_bigint::BigInt::parse_bytes(digits.as_bytes(), radix)
+                .map(BigInt)
+                .ok_or_else(|| MalError::Tokenizer(format!("Invalid integer literal: {}", text))),
+            _ => Err(MalError::Tokenizer(format!(
+                "Invalid integer literal: {}",
+                text
+            ))),
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -180,6 +459,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tokenize_hash_curly_bracket() {
+        assert_eq!(
+            tokenize("#{:a}"),
+            Ok(vec![
+                MalToken::new(HashLCurly),
+                MalToken::new(Keyword("a".to_string())),
+                MalToken::new(RCurly),
+            ])
+        );
+        assert_eq!(
+            tokenize("a#{"),
+            Ok(vec![
+                MalToken::new(Symbol("a#".to_string())),
+                MalToken::new(LCurly),
+            ])
+        );
+    }
+
     #[test]
     fn test_tokenize_square_brackets() {
         assert_eq!(tokenize("["), Ok(vec![MalToken::new(LBracket)]));
@@ -296,28 +594,150 @@ mod tests {
 
     #[test]
     fn test_tokenize_numbers() {
-        assert_eq!(tokenize("1"), Ok(vec![MalToken::new(Number(1.))]));
-        assert_eq!(tokenize("-1"), Ok(vec![MalToken::new(Number(-1.))]));
+        assert_eq!(tokenize("1"), Ok(vec![MalToken::new(Int(1))]));
+        assert_eq!(tokenize("-1"), Ok(vec![MalToken::new(Int(-1))]));
+        assert_eq!(tokenize("123456"), Ok(vec![MalToken::new(Int(123_456))]));
+        assert_eq!(tokenize("12.2"), Ok(vec![MalToken::new(Float(12.2))]));
+        assert_eq!(tokenize("-123.99"), Ok(vec![MalToken::new(Float(-123.99))]));
+        assert_eq!(tokenize("80."), Ok(vec![MalToken::new(Float(80.))]));
+        assert_eq!(tokenize("-2."), Ok(vec![MalToken::new(Float(-2.))]));
+        assert_eq!(tokenize("1.0"), Ok(vec![MalToken::new(Float(1.))]));
+        assert_eq!(
+            tokenize("-12 0 53.2 -5."),
+            Ok(vec![
+                MalToken::new(Int(-12)),
+                MalToken::new(Int(0)),
+                MalToken::new(Float(53.2)),
+                MalToken::new(Float(-5.)),
+            ])
+        );
+        assert_eq!(
+            tokenize("9223372036854775807 -9223372036854775808"),
+            Ok(vec![
+                MalToken::new(Int(i64::MAX)),
+                MalToken::new(Int(i64::MIN)),
+            ])
+        );
+
         assert_eq!(
-            tokenize("123456"),
-            Ok(vec![MalToken::new(Number(123_456.))])
+            tokenize("9223372036854775808"),
+            Ok(vec![MalToken::new(BigInt(
+                "9223372036854775808".parse().unwrap()
+            ))])
         );
-        assert_eq!(tokenize("12.2"), Ok(vec![MalToken::new(Number(12.2))]));
+    }
+
+    #[test]
+    fn test_tokenize_extended_numbers() {
+        assert_eq!(tokenize("+3"), Ok(vec![MalToken::new(Int(3))]));
         assert_eq!(
-            tokenize("-123.99"),
-            Ok(vec![MalToken::new(Number(-123.99))])
+            tokenize("1_000_000"),
+            Ok(vec![MalToken::new(Int(1_000_000))])
         );
-        assert_eq!(tokenize("80."), Ok(vec![MalToken::new(Number(80.))]));
-        assert_eq!(tokenize("-2."), Ok(vec![MalToken::new(Number(-2.))]));
+        assert_eq!(tokenize("0xFF"), Ok(vec![MalToken::new(Int(255))]));
+        assert_eq!(tokenize("-0x1_f"), Ok(vec![MalToken::new(Int(-31))]));
+        assert_eq!(tokenize("0b1010"), Ok(vec![MalToken::new(Int(10))]));
+        assert_eq!(tokenize("0o17"), Ok(vec![MalToken::new(Int(15))]));
         assert_eq!(
-            tokenize("-12 0 53.2 -5."),
+            tokenize("-0x8000000000000000"),
+            Ok(vec![MalToken::new(Int(i64::MIN))])
+        );
+        assert_eq!(
+            tokenize("-0x8000000000000001"),
+            Ok(vec![MalToken::new(BigInt(
+                "-9223372036854775809".parse().unwrap()
+            ))])
+        );
+        assert_eq!(tokenize("1e10"), Ok(vec![MalToken::new(Float(1e10))]));
+        assert_eq!(tokenize("2.5E-3"), Ok(vec![MalToken::new(Float(2.5e-3))]));
+        assert_eq!(tokenize(".5"), Ok(vec![MalToken::new(Float(0.5))]));
+        assert_eq!(tokenize("-.5"), Ok(vec![MalToken::new(Float(-0.5))]));
+        assert_eq!(
+            tokenize("1_000.000_1"),
+            Ok(vec![MalToken::new(Float(1000.0001))])
+        );
+        assert_eq!(
+            tokenize("##Inf ##-Inf"),
             Ok(vec![
-                MalToken::new(Number(-12.)),
-                MalToken::new(Number(0.)),
-                MalToken::new(Number(53.2)),
-                MalToken::new(Number(-5.)),
+                MalToken::new(Float(f64::INFINITY)),
+                MalToken::new(Float(f64::NEG_INFINITY)),
             ])
         );
+
+        match tokenize("##NaN").unwrap()[0].token_type {
+            Float(val) if val.is_nan() => {}
+            _ => unreachable!("Expected NaN."),
+        }
+
+        match tokenize("0b102") {
+            Err(MalError::Tokenizer(_)) => {}
+            _ => unreachable!("Expected Tokenizer error."),
+        }
+
+        assert_eq!(
+            tokenize("0x10000000000000000"),
+            Ok(vec![MalToken::new(BigInt(
+                "18446744073709551616".parse().unwrap()
+            ))])
+        );
+
+        assert_eq!(
+            tokenize("_ _a e10 . .e1 -> +a"),
+            Ok(vec![
+                MalToken::new(Symbol("_".to_string())),
+                MalToken::new(Symbol("_a".to_string())),
+                MalToken::new(Symbol("e10".to_string())),
+                MalToken::new(Symbol(".".to_string())),
+                MalToken::new(Symbol(".e1".to_string())),
+                MalToken::new(Symbol("->".to_string())),
+                MalToken::new(Symbol("+a".to_string())),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_invalid_numbers() {
+        for invalid in &[
+            "1_", "1__0", "_1", "1e", "0x", "0x-1", "1.5N", "0xFFN", "2/-4", "-1a", ".5.",
+        ] {
+            assert_eq!(
+                tokenize(invalid),
+                Err(MalError::Tokenizer(format!(
+                    "Invalid number literal: {} at 1:1",
+                    invalid
+                )))
+            );
+        }
+    }
+
+    #[test]
+    fn test_tokenize_bigints_and_ratios() {
+        assert_eq!(
+            tokenize("123N -7N"),
+            Ok(vec![
+                MalToken::new(BigInt(123.into())),
+                MalToken::new(BigInt((-7).into())),
+            ])
+        );
+        assert_eq!(
+            tokenize("22/7 -3/4"),
+            Ok(vec![
+                MalToken::new(Ratio(num_rational::BigRational::new(22.into(), 7.into()))),
+                MalToken::new(Ratio(num_rational::BigRational::new((-3).into(), 4.into()))),
+            ])
+        );
+        assert_eq!(
+            tokenize("6/3 2/4"),
+            Ok(vec![
+                MalToken::new(Int(2)),
+                MalToken::new(Ratio(num_rational::BigRational::new(1.into(), 2.into()))),
+            ])
+        );
+
+        match tokenize("1/0") {
+            Err(MalError::Tokenizer(_)) => {}
+            _ => unreachable!("Expected Tokenizer error."),
+        }
     }
 
     #[test]
@@ -387,6 +807,131 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tokenize_string_escapes() {
+        assert_eq!(
+            tokenize(r#""a\tb\rc\0d""#),
+            Ok(vec![MalToken::new(Str("a\tb\rc\0d".to_string()))])
+        );
+        assert_eq!(
+            tokenize(r#""\x41\u00e9\u{1F600}""#),
+            Ok(vec![MalToken::new(Str("A\u{e9}\u{1F600}".to_string()))])
+        );
+        assert_eq!(
+            tokenize("\"line one\nline two\""),
+            Ok(vec![MalToken::new(Str("line one\nline two".to_string()))])
+        );
+        assert_eq!(
+            tokenize("\"joined \\\n    line\""),
+            Ok(vec![MalToken::new(Str("joined line".to_string()))])
+        );
+
+        for invalid in &[
+            r#""\q""#,
+            r#""\x4""#,
+            r#""\xZZ""#,
+            r#""\u12""#,
+            r#""\u{}""#,
+            r#""\u{1234567}""#,
+            r#""\u{D800}""#,
+            r#""\u{41""#,
+        ] {
+            match tokenize(invalid) {
+                Err(MalError::Tokenizer(_)) => {}
+                _ => unreachable!("Expected Tokenizer error for {}.", invalid),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokenize_raw_strings() {
+        assert_eq!(
+            tokenize(r#"r"C:\path\n" r"""#),
+            Ok(vec![
+                MalToken::new(Str(r"C:\path\n".to_string())),
+                MalToken::new(Str("".to_string())),
+            ])
+        );
+        assert_eq!(
+            tokenize(r##"r#"say "hi"\d+"# r"##),
+            Ok(vec![
+                MalToken::new(Str(r#"say "hi"\d+"#.to_string())),
+                MalToken::new(Symbol("r".to_string())),
+            ])
+        );
+        assert_eq!(
+            tokenize("r\"multi\nline\""),
+            Ok(vec![MalToken::new(Str("multi\nline".to_string()))])
+        );
+
+        for unterminated in &[r#"r"abc"#, r##"r#"abc""##, r#"r""#] {
+            match tokenize(unterminated) {
+                Err(MalError::Tokenizer(_)) => {}
+                _ => unreachable!("Expected Tokenizer error for {}.", unterminated),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokenize_regexes() {
+        assert_eq!(
+            tokenize(r#"#"\d+\.\"x\"" #"""#),
+            Ok(vec![
+                MalToken::new(MalTokenType::Regex(r#"\d+\."x""#.to_string())),
+                MalToken::new(MalTokenType::Regex("".to_string())),
+            ])
+        );
+
+        for unterminated in &[r#"#"abc"#, r#"#"abc\""#] {
+            match tokenize(unterminated) {
+                Err(MalError::Tokenizer(_)) => {}
+                _ => unreachable!("Expected Tokenizer error for {}.", unterminated),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokenize_chars() {
+        assert_eq!(
+            tokenize(r"\a \λ \\ \( \) \;"),
+            Ok(vec![
+                MalToken::new(Char('a')),
+                MalToken::new(Char('λ')),
+                MalToken::new(Char('\\')),
+                MalToken::new(Char('(')),
+                MalToken::new(Char(')')),
+                MalToken::new(Char(';')),
+            ])
+        );
+        assert_eq!(
+            tokenize(r"\newline \space \tab \return \backspace \formfeed"),
+            Ok(vec![
+                MalToken::new(Char('\n')),
+                MalToken::new(Char(' ')),
+                MalToken::new(Char('\t')),
+                MalToken::new(Char('\r')),
+                MalToken::new(Char('\u{8}')),
+                MalToken::new(Char('\u{c}')),
+            ])
+        );
+        assert_eq!(
+            tokenize(r"(\u03bb \u)"),
+            Ok(vec![
+                MalToken::new(LParen),
+                MalToken::new(Char('λ')),
+                MalToken::new(Char('u')),
+                MalToken::new(RParen),
+            ])
+        );
+
+        for invalid in &[r"\ab", r"\u12", r"\ud800", r"\"] {
+            match tokenize(invalid) {
+                Err(MalError::Tokenizer(_)) => {}
+                _ => unreachable!("Expected Tokenizer error for {}.", invalid),
+            }
+        }
+    }
+
     #[test]
     fn test_tokenize_keywords() {
         assert_eq!(
@@ -399,4 +944,37 @@ mod tests {
             Ok(vec![MalToken::new(Keyword("ab12".to_string()))])
         );
     }
+
+    #[test]
+    fn test_tokenize_positions() {
+        let tokens = tokenize_with_file("(a\n  \"b\nc\" ; d\n  λ :e)", Some("f.mal")).unwrap();
+        let positions: Vec<String> = tokens
+            .iter()
+            .map(|token| token.pos.as_ref().unwrap().to_string())
+            .collect();
+
+        assert_eq!(
+            positions,
+            vec![
+                "f.mal:1:1",
+                "f.mal:1:2",
+                "f.mal:2:3",
+                "f.mal:4:3",
+                "f.mal:4:5",
+                "f.mal:4:7"
+            ]
+        );
+
+        assert_eq!(
+            tokenize("x").unwrap()[0].pos.as_ref().unwrap().to_string(),
+            "1:1"
+        );
+
+        assert_eq!(
+            tokenize_with_file("\n  \"abc", Some("f.mal")),
+            Err(MalError::Tokenizer(
+                "Expected '\"', got EOF at f.mal:2:3".to_string()
+            ))
+        );
+    }
 }
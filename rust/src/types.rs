@@ -1,25 +1,44 @@
+use crate::core::core_apply;
 use crate::env::Env;
 use crate::printer::pr_str;
+use crate::seq::Seqable;
 use crate::types::MalError::*;
-use std::cell::RefCell;
-use std::collections::hash_map;
-use std::collections::HashMap;
+use im_rc::{hashset, ordmap, HashMap, HashSet, OrdMap, Vector};
+use num_traits::ToPrimitive;
+use std::cell::{OnceCell, RefCell};
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
 use std::fmt;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::iter::FusedIterator;
+use std::ops::Deref;
 use std::rc::Rc;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct MalValue {
     pub mal_type: Rc<MalValueType>,
-    // Possible extra fields: line, column
+    pub pos: Option<Rc<SourcePos>>,
+}
+
+impl PartialEq for MalValue {
+    fn eq(&self, other: &MalValue) -> bool {
+        self.mal_type == other.mal_type
+    }
 }
 
 impl MalValue {
     pub fn new(mal_type: MalValueType) -> MalValue {
         MalValue {
             mal_type: Rc::new(mal_type),
+            pos: None,
+        }
+    }
+
+    pub fn with_pos(self, pos: Option<Rc<SourcePos>>) -> MalValue {
+        MalValue {
+            mal_type: self.mal_type,
+            pos,
         }
     }
 
@@ -32,6 +51,11 @@ impl MalValue {
     }
 
     pub fn new_rust_func(func: fn(&[MalValue], &mut Env) -> MalResult, env: &Env) -> MalValue {
+        MalValue::new_rust_closure(Rc::new(func), env)
+    }
+
+    /// Like `new_rust_func`, but `func` may capture state from the host.
+    pub fn new_rust_closure(func: RustFn, env: &Env) -> MalValue {
         MalValue::new(MalValueType::RustFunc(RustFunction {
             func,
             env: env.clone(),
@@ -63,16 +87,20 @@ impl MalValue {
         MalValue::new(MalValueType::Atom(RefCell::new(value)))
     }
 
-    pub fn new_list(vec: Vec<MalValue>) -> MalValue {
+    pub fn new_reduced(value: MalValue) -> MalValue {
+        MalValue::new(MalValueType::Reduced(value))
+    }
+
+    pub fn new_list(vec: impl Into<Vector<MalValue>>) -> MalValue {
         MalValue::new(MalValueType::List(MalList {
-            vec,
+            vec: vec.into(),
             meta: MalValue::nil(),
         }))
     }
 
-    pub fn new_vector(vec: Vec<MalValue>) -> MalValue {
+    pub fn new_vector(vec: impl Into<Vector<MalValue>>) -> MalValue {
         MalValue::new(MalValueType::Vector(MalVector {
-            vec,
+            vec: vec.into(),
             meta: MalValue::nil(),
         }))
     }
@@ -81,6 +109,22 @@ impl MalValue {
         MalValue::new(MalValueType::Map(mal_map))
     }
 
+    pub fn new_set(mal_set: MalSet) -> MalValue {
+        MalValue::new(MalValueType::Set(mal_set))
+    }
+
+    pub fn new_lazy_seq(lazy_seq: MalLazySeq) -> MalValue {
+        MalValue::new(MalValueType::LazySeq(lazy_seq))
+    }
+
+    pub fn new_sorted_map(sorted_map: MalSortedMap) -> MalValue {
+        MalValue::new(MalValueType::SortedMap(sorted_map))
+    }
+
+    pub fn new_sorted_set(sorted_set: MalSortedSet) -> MalValue {
+        MalValue::new(MalValueType::SortedSet(sorted_set))
+    }
+
     pub fn nil() -> MalValue {
         MalValue::new(MalValueType::Nil)
     }
@@ -98,7 +142,7 @@ impl MalValue {
             }
             MalValueType::RustFunc(ref rust_func) => {
                 Ok(MalValue::new(MalValueType::RustFunc(RustFunction {
-                    func: rust_func.func,
+                    func: rust_func.func.clone(),
                     env: rust_func.env.clone(),
                     meta,
                 })))
@@ -113,7 +157,17 @@ impl MalValue {
                     meta,
                 })))
             }
+            MalValueType::LazySeq(ref lazy_seq) => {
+                Ok(MalValue::new_lazy_seq(lazy_seq.clone_with_meta(meta)?))
+            }
             MalValueType::Map(ref mal_map) => Ok(MalValue::new_map(mal_map.clone_with_meta(meta))),
+            MalValueType::Set(ref mal_set) => Ok(MalValue::new_set(mal_set.clone_with_meta(meta))),
+            MalValueType::SortedMap(ref sorted_map) => {
+                Ok(MalValue::new_sorted_map(sorted_map.clone_with_meta(meta)))
+            }
+            MalValueType::SortedSet(ref sorted_set) => {
+                Ok(MalValue::new_sorted_set(sorted_set.clone_with_meta(meta)))
+            }
             _ => Err(MalError::Evaluation(
                 "The given type does not support meta attributes.".to_string(),
             )),
@@ -126,7 +180,11 @@ impl MalValue {
             MalValueType::RustFunc(ref rust_func) => Ok(rust_func.meta.clone()),
             MalValueType::List(ref mal_list) => Ok(mal_list.meta.clone()),
             MalValueType::Vector(ref mal_vec) => Ok(mal_vec.meta.clone()),
+            MalValueType::LazySeq(ref lazy_seq) => Ok(lazy_seq.meta.clone()),
             MalValueType::Map(ref mal_map) => Ok(mal_map.meta.clone()),
+            MalValueType::Set(ref mal_set) => Ok(mal_set.meta.clone()),
+            MalValueType::SortedMap(ref sorted_map) => Ok(sorted_map.meta.clone()),
+            MalValueType::SortedSet(ref sorted_set) => Ok(sorted_set.meta.clone()),
             _ => Err(MalError::RustFunction(
                 "The given type does not support meta attributes.".to_string(),
             )),
@@ -141,6 +199,14 @@ impl MalValue {
         }
     }
 
+    pub fn is_nil(&self) -> bool {
+        matches!(*self.mal_type, MalValueType::Nil)
+    }
+
+    pub fn is_lazy_seq(&self) -> bool {
+        matches!(*self.mal_type, MalValueType::LazySeq(_))
+    }
+
     pub fn is_function(&self) -> bool {
         match *self.mal_type {
             MalValueType::RustFunc(_) => true,
@@ -180,12 +246,18 @@ impl MalValue {
         }
     }
 
+    pub fn is_char(&self) -> bool {
+        matches!(*self.mal_type, MalValueType::Char(_))
+    }
+
     pub fn is_number(&self) -> bool {
-        if let MalValueType::Number(_) = *self.mal_type {
-            true
-        } else {
-            false
-        }
+        matches!(
+            *self.mal_type,
+            MalValueType::Int(_)
+                | MalValueType::BigInt(_)
+                | MalValueType::Ratio(_)
+                | MalValueType::Float(_)
+        )
     }
 }
 
@@ -194,16 +266,27 @@ pub enum MalValueType {
     Nil,
     True,
     False,
-    Number(f64),
+    Int(i64),
+    BigInt(num_bigint::BigInt),
+    Ratio(num_rational::BigRational),
+    Float(f64),
+    Char(char),
     Symbol(String),
     Str(String),
     Keyword(String),
     List(MalList),
     Vector(MalVector),
+    LazySeq(MalLazySeq),
     Map(MalMap),
+    Set(MalSet),
+    SortedMap(MalSortedMap),
+    SortedSet(MalSortedSet),
     RustFunc(RustFunction),
     MalFunc(MalFunction),
     Atom(RefCell<MalValue>),
+    Regex(MalRegex),
+    /// Wraps the result of a `reduce` step to stop the reduction early.
+    Reduced(MalValue),
 }
 
 impl PartialEq for MalValueType {
@@ -214,7 +297,12 @@ impl PartialEq for MalValueType {
             (Nil, Nil) => true,
             (True, True) => true,
             (False, False) => true,
-            (Number(l), Number(r)) => l == r,
+            (Int(l), Int(r)) => l == r,
+            (BigInt(l), BigInt(r)) => l == r,
+            (Int(l), BigInt(r)) | (BigInt(r), Int(l)) => num_bigint::BigInt::from(*l) == *r,
+            (Ratio(l), Ratio(r)) => l == r,
+            (Float(l), Float(r)) => l == r,
+            (Char(l), Char(r)) => l == r,
             (Symbol(l), Symbol(r)) => l == r,
             (Str(l), Str(r)) => l == r,
             (Keyword(l), Keyword(r)) => l == r,
@@ -222,41 +310,294 @@ impl PartialEq for MalValueType {
             | (Vector(MalVector { vec: l, .. }), Vector(MalVector { vec: r, .. }))
             | (List(MalList { vec: l, .. }), Vector(MalVector { vec: r, .. }))
             | (Vector(MalVector { vec: l, .. }), List(MalList { vec: r, .. })) => l == r,
+            (LazySeq(l), LazySeq(r)) => match (l.to_vec(), r.to_vec()) {
+                (Ok(l), Ok(r)) => l == r,
+                _ => false,
+            },
+            (LazySeq(l), List(MalList { vec: r, .. }))
+            | (LazySeq(l), Vector(MalVector { vec: r, .. }))
+            | (List(MalList { vec: r, .. }), LazySeq(l))
+            | (Vector(MalVector { vec: r, .. }), LazySeq(l)) => match l.to_vec() {
+                Ok(l) => l.len() == r.len() && l.iter().eq(r.iter()),
+                Err(_) => false,
+            },
             (Map(l), Map(r)) => l == r,
+            (Set(l), Set(r)) => l == r,
+            (SortedMap(l), SortedMap(r)) => l.to_hash_map() == r.to_hash_map(),
+            (Map(l), SortedMap(r)) | (SortedMap(r), Map(l)) => l.has_entries(r.iter()),
+            (SortedSet(l), SortedSet(r)) => l.to_hash_set() == r.to_hash_set(),
+            (Set(l), SortedSet(r)) | (SortedSet(r), Set(l)) => l.set == r.to_hash_set(),
             (RustFunc(l), RustFunc(r)) => l == r,
             (MalFunc(l), MalFunc(r)) => l == r,
+            (Regex(l), Regex(r)) => l.as_str() == r.as_str(),
             _ => false,
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// Hashes values consistently with `PartialEq`: lists and vectors with the
+/// same elements hash alike, as do an `Int` and a `BigInt` of the same value.
+/// Maps and sets combine the hashes of their entries so that iteration order
+/// does not matter. Metadata takes no part in either.
+impl Hash for MalValueType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        use crate::types::MalValueType::*;
+
+        match self {
+            Nil | True | False | MalFunc(_) | Atom(_) => {}
+            Reduced(val) => val.mal_type.hash(state),
+            Int(val) => val.hash(state),
+            BigInt(val) => match val.to_i64() {
+                Some(val) => val.hash(state),
+                None => val.hash(state),
+            },
+            Ratio(val) => val.hash(state),
+            // 0.0 and -0.0 are equal, so they must hash alike.
+            Float(val) => (if *val == 0. { 0. } else { *val }).to_bits().hash(state),
+            Char(val) => val.hash(state),
+            Symbol(val) | Str(val) | Keyword(val) => val.hash(state),
+            Regex(val) => val.as_str().hash(state),
+            List(MalList { vec, .. }) | Vector(MalVector { vec, .. }) => {
+                vec.len().hash(state);
+                for elem in vec {
+                    elem.mal_type.hash(state);
+                }
+            }
+            // Lazy sequences hash like the list of their elements.
+            LazySeq(lazy_seq) => {
+                let elements = lazy_seq.to_vec().unwrap_or_default();
+
+                elements.len().hash(state);
+                for elem in elements {
+                    elem.mal_type.hash(state);
+                }
+            }
+            Map(mal_map) => {
+                hash_unordered(mal_map.iter(), state, |(key, val), hasher| {
+                    key.mal_type.hash(hasher);
+                    val.mal_type.hash(hasher);
+                });
+            }
+            SortedMap(sorted_map) => {
+                hash_unordered(sorted_map.iter(), state, |(key, val), hasher| {
+                    key.mal_type.hash(hasher);
+                    val.mal_type.hash(hasher);
+                });
+            }
+            Set(mal_set) => {
+                hash_unordered(mal_set.iter(), state, |elem, hasher| {
+                    elem.mal_type.hash(hasher)
+                });
+            }
+            SortedSet(sorted_set) => {
+                hash_unordered(sorted_set.iter(), state, |elem, hasher| {
+                    elem.mal_type.hash(hasher)
+                });
+            }
+            RustFunc(rust_func) => rust_func.func_address().hash(state),
+        }
+
+        hash_kind(self).hash(state);
+    }
+}
+
+/// Distinguishes values of different types whose payloads hash alike.
+fn hash_kind(mal_type: &MalValueType) -> u8 {
+    use crate::types::MalValueType::*;
+
+    match mal_type {
+        Nil => 0,
+        True => 1,
+        False => 2,
+        Int(_) | BigInt(_) => 3,
+        Ratio(_) => 4,
+        Float(_) => 5,
+        Char(_) => 6,
+        Symbol(_) => 7,
+        Str(_) => 8,
+        Keyword(_) => 9,
+        List(_) | Vector(_) | LazySeq(_) => 10,
+        Map(_) | SortedMap(_) => 11,
+        Set(_) | SortedSet(_) => 12,
+        RustFunc(_) => 13,
+        MalFunc(_) => 14,
+        Atom(_) => 15,
+        Reduced(_) => 16,
+        Regex(_) => 17,
+    }
+}
+
+fn hash_unordered<T, I, F, H>(items: I, state: &mut H, hash_item: F)
+where
+    I: ExactSizeIterator<Item = T>,
+    F: Fn(T, &mut DefaultHasher),
+    H: Hasher,
+{
+    let len = items.len();
+    let combined = items.fold(0u64, |acc, item| {
+        let mut hasher = DefaultHasher::new();
+        hash_item(item, &mut hasher);
+        acc.wrapping_add(hasher.finish())
+    });
+
+    len.hash(state);
+    combined.hash(state);
+}
+
+/// Lists and vectors are both backed by a persistent RRB vector, so that
+/// `cons`, `conj` and `rest` share structure with the original collection.
+#[derive(Debug)]
 pub struct MalList {
-    pub vec: Vec<MalValue>,
+    pub vec: Vector<MalValue>,
     pub meta: MalValue,
 }
 
-#[derive(Debug, PartialEq)]
+impl PartialEq for MalList {
+    fn eq(&self, other: &MalList) -> bool {
+        self.vec == other.vec
+    }
+}
+
+#[derive(Debug)]
 pub struct MalVector {
-    pub vec: Vec<MalValue>,
+    pub vec: Vector<MalValue>,
     pub meta: MalValue,
 }
 
-#[derive(Debug, PartialEq)]
+impl PartialEq for MalVector {
+    fn eq(&self, other: &MalVector) -> bool {
+        self.vec == other.vec
+    }
+}
+
+/// A sequence whose elements are computed on demand. Until it is realized it
+/// holds a function and the arguments to call it with. The function returns
+/// nil or a sequence, which is memoized as either the empty sequence or a
+/// first element and the rest of the sequence (which may itself be lazy).
+#[derive(Debug)]
+pub struct MalLazySeq {
+    cell: RefCell<LazyCell>,
+    pub meta: MalValue,
+}
+
+#[derive(Clone, Debug)]
+enum LazyCell {
+    Thunk(MalValue, Vec<MalValue>),
+    Empty,
+    Cons(MalValue, MalValue),
+}
+
+impl MalLazySeq {
+    pub fn new(func: MalValue, args: Vec<MalValue>) -> MalLazySeq {
+        MalLazySeq {
+            cell: RefCell::new(LazyCell::Thunk(func, args)),
+            meta: MalValue::nil(),
+        }
+    }
+
+    /// An already realized sequence, used to prepend to a lazy sequence
+    /// without realizing it.
+    pub fn cons(first: MalValue, rest: MalValue) -> MalLazySeq {
+        MalLazySeq {
+            cell: RefCell::new(LazyCell::Cons(first, rest)),
+            meta: MalValue::nil(),
+        }
+    }
+
+    pub fn is_realized(&self) -> bool {
+        !matches!(*self.cell.borrow(), LazyCell::Thunk(..))
+    }
+
+    pub fn realize(&self) -> Result<Option<(MalValue, MalValue)>, MalError> {
+        let (func, args) = match *self.cell.borrow() {
+            LazyCell::Empty => return Ok(None),
+            LazyCell::Cons(ref first, ref rest) => return Ok(Some((first.clone(), rest.clone()))),
+            LazyCell::Thunk(ref func, ref args) => (func.clone(), args.clone()),
+        };
+
+        let first_rest = core_apply(&func, &args)?.first_rest()?;
+
+        *self.cell.borrow_mut() = match first_rest {
+            Some((ref first, ref rest)) => LazyCell::Cons(first.clone(), rest.clone()),
+            None => LazyCell::Empty,
+        };
+
+        Ok(first_rest)
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<MalValue>, MalError> {
+        let mut elements = Vec::new();
+        let mut next = self.realize()?;
+
+        while let Some((first, rest)) = next {
+            elements.push(first);
+            next = rest.first_rest()?;
+        }
+
+        Ok(elements)
+    }
+
+    /// Realizes the sequence first, so that the copy does not evaluate it again.
+    pub fn clone_with_meta(&self, meta: MalValue) -> Result<MalLazySeq, MalError> {
+        self.realize()?;
+
+        Ok(MalLazySeq {
+            cell: RefCell::new(self.cell.borrow().clone()),
+            meta,
+        })
+    }
+}
+
+/// Unlinks a realized chain of lazy sequences iteratively, since dropping a
+/// long chain recursively would overflow the stack.
+impl Drop for MalLazySeq {
+    fn drop(&mut self) {
+        let mut next = take_lazy_rest(self.cell.get_mut());
+
+        while let Some(mal_type) = next {
+            next = match Rc::try_unwrap(mal_type) {
+                Ok(MalValueType::LazySeq(mut lazy_seq)) => take_lazy_rest(lazy_seq.cell.get_mut()),
+                _ => None,
+            };
+        }
+    }
+}
+
+fn take_lazy_rest(cell: &mut LazyCell) -> Option<Rc<MalValueType>> {
+    match std::mem::replace(cell, LazyCell::Empty) {
+        LazyCell::Cons(_, rest) => Some(rest.mal_type),
+        _ => None,
+    }
+}
+
+/// A hash map that iterates in insertion order. `map` holds each value along
+/// with its insertion index, for lookups, and `order` holds the entries by
+/// insertion index, for iteration.
+#[derive(Debug)]
 pub struct MalMap {
-    map: HashMap<MalMapKey, MalValue>,
+    map: HashMap<MalMapKey, (usize, MalValue)>,
+    order: OrdMap<usize, (MalValue, MalValue)>,
+    next_index: usize,
     pub meta: MalValue,
 }
 
+/// Wraps a map key or set element so that it is hashed structurally.
 #[derive(Clone, Debug)]
 struct MalMapKey {
-    key: String,
     mal_value: MalValue,
 }
 
+impl MalMapKey {
+    fn new(mal_value: &MalValue) -> MalMapKey {
+        MalMapKey {
+            mal_value: mal_value.clone(),
+        }
+    }
+}
+
 impl PartialEq for MalMapKey {
     fn eq(&self, other: &MalMapKey) -> bool {
-        self.key == other.key
+        self.mal_value == other.mal_value
     }
 }
 
@@ -264,7 +605,7 @@ impl Eq for MalMapKey {}
 
 impl Hash for MalMapKey {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.key.hash(state);
+        self.mal_value.mal_type.hash(state);
     }
 }
 
@@ -272,6 +613,8 @@ impl MalMap {
     pub fn new() -> MalMap {
         MalMap {
             map: HashMap::new(),
+            order: OrdMap::new(),
+            next_index: 0,
             meta: MalValue::nil(),
         }
     }
@@ -283,18 +626,17 @@ impl MalMap {
             ));
         }
 
-        let mut map = HashMap::with_capacity(arguments.len() % 2);
+        let mut mal_map = MalMap::new();
+        mal_map.extend_from_arguments(arguments);
 
-        MalMap::extend_map_from_arguments(&mut map, arguments)?;
-        Ok(MalMap {
-            map,
-            meta: MalValue::nil(),
-        })
+        Ok(mal_map)
     }
 
     pub fn clone_with_meta(&self, meta: MalValue) -> MalMap {
         MalMap {
             map: self.map.clone(),
+            order: self.order.clone(),
+            next_index: self.next_index,
             meta,
         }
     }
@@ -306,99 +648,93 @@ impl MalMap {
             ));
         }
 
-        let mut map = self.map.clone();
-        MalMap::extend_map_from_arguments(&mut map, arguments)?;
-        Ok(MalMap {
-            map,
-            meta: MalValue::nil(),
-        })
+        let mut mal_map = self.clone_with_meta(MalValue::nil());
+        mal_map.extend_from_arguments(arguments);
+
+        Ok(mal_map)
     }
 
     pub fn dissoc(&self, arguments: &[MalValue]) -> Result<MalMap, MalError> {
-        let mut map = self.map.clone();
+        let mut mal_map = self.clone_with_meta(MalValue::nil());
 
         for arg in arguments {
-            let key = match *arg.mal_type {
-                MalValueType::Str(ref val) => Ok(format!("s{}", val)),
-                MalValueType::Keyword(ref val) => Ok(format!("k{}", val)),
-                _ => Err(MalError::RustFunction(
-                    "hash map keys must be strings or keywords".to_string(),
-                )),
-            }?;
-
-            map.remove(&MalMapKey {
-                key,
-                mal_value: arg.clone(),
-            });
-        }
-
-        Ok(MalMap {
-            map,
-            meta: MalValue::nil(),
-        })
+            if let Some((index, _)) = mal_map.map.remove(&MalMapKey::new(arg)) {
+                mal_map.order.remove(&index);
+            }
+        }
+
+        Ok(mal_map)
     }
 
-    fn extend_map_from_arguments(
-        map: &mut HashMap<MalMapKey, MalValue>,
-        arguments: &[MalValue],
-    ) -> Result<(), MalError> {
+    /// Inserts the entries in `arguments`. A key that is already present keeps
+    /// its original position.
+    fn extend_from_arguments(&mut self, arguments: &[MalValue]) {
         assert_eq!(0, arguments.len() % 2);
 
         for i in (0..arguments.len()).step_by(2) {
-            let key = match *arguments[i].mal_type {
-                MalValueType::Str(ref val) => Ok(format!("s{}", val)),
-                MalValueType::Keyword(ref val) => Ok(format!("k{}", val)),
-                _ => Err(MalError::Parser(
-                    "hash map keys must be strings or keywords".to_string(),
-                )),
-            }?;
-
-            map.insert(
-                MalMapKey {
-                    key,
-                    mal_value: arguments[i].clone(),
-                },
-                arguments[i + 1].clone(),
-            );
+            let (key, val) = (&arguments[i], &arguments[i + 1]);
+            let map_key = MalMapKey::new(key);
+
+            let index = match self.map.get(&map_key) {
+                Some((index, _)) => *index,
+                None => {
+                    self.next_index += 1;
+                    self.next_index - 1
+                }
+            };
+
+            self.order.insert(index, (key.clone(), val.clone()));
+            self.map.insert(map_key, (index, val.clone()));
         }
-
-        Ok(())
     }
 
     pub fn get(&self, key: &MalValue) -> MalValue {
-        let str_key = match *key.mal_type {
-            MalValueType::Str(ref val) => format!("s{}", val),
-            MalValueType::Keyword(ref val) => format!("k{}", val),
-            _ => return MalValue::nil(),
-        };
-
         self.map
-            .get(&MalMapKey {
-                key: str_key,
-                mal_value: key.clone(),
-            })
-            .cloned()
+            .get(&MalMapKey::new(key))
+            .map(|(_, val)| val.clone())
             .unwrap_or_else(MalValue::nil)
     }
 
     pub fn contains(&self, key: &MalValue) -> bool {
-        let str_key = match *key.mal_type {
-            MalValueType::Str(ref val) => format!("s{}", val),
-            MalValueType::Keyword(ref val) => format!("k{}", val),
-            _ => return false,
-        };
+        self.map.contains_key(&MalMapKey::new(key))
+    }
 
-        self.map.contains_key(&MalMapKey {
-            key: str_key,
-            mal_value: key.clone(),
-        })
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
     }
 
-    pub fn iter(&self) -> MalMapIter {
+    pub fn iter(&self) -> MalMapIter<'_> {
         MalMapIter {
-            inner: self.map.iter(),
+            inner: self.order.values(),
         }
     }
+
+    /// Iterates over clones of the entries without borrowing the map. The
+    /// entries are shared with the map, so this does not copy it up front.
+    pub fn iter_owned(&self) -> impl Iterator<Item = (MalValue, MalValue)> {
+        self.order.clone().into_iter().map(|(_, entry)| entry)
+    }
+
+    /// Whether the map holds exactly the given entries, in any order.
+    fn has_entries<'a>(
+        &self,
+        mut entries: impl ExactSizeIterator<Item = (&'a MalValue, &'a MalValue)>,
+    ) -> bool {
+        entries.len() == self.len()
+            && entries.all(
+                |(key, val)| matches!(self.map.get(&MalMapKey::new(key)), Some((_, v)) if v == val),
+            )
+    }
+}
+
+impl PartialEq for MalMap {
+    fn eq(&self, other: &MalMap) -> bool {
+        self.has_entries(other.iter())
+    }
 }
 
 impl Default for MalMap {
@@ -407,9 +743,8 @@ impl Default for MalMap {
     }
 }
 
-#[derive(Clone, Debug)]
 pub struct MalMapIter<'a> {
-    inner: hash_map::Iter<'a, MalMapKey, MalValue>,
+    inner: ordmap::Values<'a, usize, (MalValue, MalValue)>,
 }
 
 impl<'a> Iterator for MalMapIter<'a> {
@@ -417,9 +752,137 @@ impl<'a> Iterator for MalMapIter<'a> {
 
     #[inline]
     fn next(&mut self) -> Option<(&'a MalValue, &'a MalValue)> {
-        let inner_next = self.inner.next();
+        self.inner.next().map(|(key, val)| (key, val))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for MalMapIter<'a> {}
+
+impl<'a> FusedIterator for MalMapIter<'a> {}
+
+#[derive(Debug)]
+pub struct MalSet {
+    set: HashSet<MalMapKey>,
+    pub meta: MalValue,
+}
+
+impl MalSet {
+    pub fn new() -> MalSet {
+        MalSet {
+            set: HashSet::new(),
+            meta: MalValue::nil(),
+        }
+    }
+
+    pub fn from_values<'a>(values: impl IntoIterator<Item = &'a MalValue>) -> MalSet {
+        MalSet::new().conj(values)
+    }
+
+    pub fn clone_with_meta(&self, meta: MalValue) -> MalSet {
+        MalSet {
+            set: self.set.clone(),
+            meta,
+        }
+    }
+
+    pub fn conj<'a>(&self, values: impl IntoIterator<Item = &'a MalValue>) -> MalSet {
+        let mut set = self.set.clone();
+
+        for value in values {
+            set.insert(MalMapKey::new(value));
+        }
+
+        MalSet {
+            set,
+            meta: MalValue::nil(),
+        }
+    }
+
+    pub fn disj(&self, values: &[MalValue]) -> MalSet {
+        let mut set = self.set.clone();
+
+        for value in values {
+            set.remove(&MalMapKey::new(value));
+        }
+
+        MalSet {
+            set,
+            meta: MalValue::nil(),
+        }
+    }
+
+    pub fn contains(&self, value: &MalValue) -> bool {
+        self.set.contains(&MalMapKey::new(value))
+    }
+
+    pub fn union(&self, other: &MalSet) -> MalSet {
+        MalSet {
+            set: self.set.clone().union(other.set.clone()),
+            meta: MalValue::nil(),
+        }
+    }
+
+    pub fn intersection(&self, other: &MalSet) -> MalSet {
+        MalSet {
+            set: self.set.clone().intersection(other.set.clone()),
+            meta: MalValue::nil(),
+        }
+    }
+
+    pub fn difference(&self, other: &MalSet) -> MalSet {
+        MalSet {
+            set: self.set.clone().relative_complement(other.set.clone()),
+            meta: MalValue::nil(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    pub fn iter(&self) -> MalSetIter<'_> {
+        MalSetIter {
+            inner: self.set.iter(),
+        }
+    }
+
+    /// The set counterpart of `MalMap::iter_owned`.
+    pub fn iter_owned(&self) -> impl Iterator<Item = MalValue> {
+        self.set.clone().into_iter().map(|key| key.mal_value)
+    }
+}
 
-        inner_next.map(|(key, val)| (&key.mal_value, val))
+impl PartialEq for MalSet {
+    fn eq(&self, other: &MalSet) -> bool {
+        self.set == other.set
+    }
+}
+
+impl Default for MalSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct MalSetIter<'a> {
+    inner: hashset::Iter<'a, MalMapKey>,
+}
+
+impl<'a> Iterator for MalSetIter<'a> {
+    type Item = &'a MalValue;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a MalValue> {
+        self.inner.next().map(|key| &key.mal_value)
     }
 
     #[inline]
@@ -428,25 +891,344 @@ impl<'a> Iterator for MalMapIter<'a> {
     }
 }
 
-impl<'a> ExactSizeIterator for MalMapIter<'a> {
+impl<'a> ExactSizeIterator for MalSetIter<'a> {
     #[inline]
     fn len(&self) -> usize {
         self.inner.len()
     }
 }
 
-impl<'a> FusedIterator for MalMapIter<'a> {}
+impl<'a> FusedIterator for MalSetIter<'a> {}
+
+/// Orders the keys of a sorted collection. The ordering may be a mal
+/// function, so it is supplied by the caller on each operation.
+pub type MalComparator<'a> = dyn FnMut(&MalValue, &MalValue) -> Result<Ordering, MalError> + 'a;
+
+/// A map whose entries are kept in key order in a persistent vector and
+/// located by binary search. `comparator` is the function given to
+/// `sorted-map-by`, or nil for the default `compare` ordering.
+#[derive(Debug)]
+pub struct MalSortedMap {
+    entries: Vector<(MalValue, MalValue)>,
+    pub comparator: MalValue,
+    pub meta: MalValue,
+}
+
+impl MalSortedMap {
+    pub fn new(comparator: MalValue) -> MalSortedMap {
+        MalSortedMap {
+            entries: Vector::new(),
+            comparator,
+            meta: MalValue::nil(),
+        }
+    }
+
+    pub fn clone_with_meta(&self, meta: MalValue) -> MalSortedMap {
+        MalSortedMap {
+            entries: self.entries.clone(),
+            comparator: self.comparator.clone(),
+            meta,
+        }
+    }
+
+    pub fn assoc(
+        &self,
+        arguments: &[MalValue],
+        cmp: &mut MalComparator,
+    ) -> Result<MalSortedMap, MalError> {
+        if arguments.len() % 2 == 1 {
+            return Err(MalError::RustFunction(
+                "sorted map must have an even number of arguments".to_string(),
+            ));
+        }
+
+        let mut entries = self.entries.clone();
+
+        for i in (0..arguments.len()).step_by(2) {
+            let entry = (arguments[i].clone(), arguments[i + 1].clone());
+
+            match bisect(&entries, &arguments[i], |(key, _)| key, cmp)? {
+                Ok(index) => entries[index].1 = entry.1,
+                Err(index) => entries.insert(index, entry),
+            }
+        }
+
+        Ok(MalSortedMap {
+            entries,
+            comparator: self.comparator.clone(),
+            meta: MalValue::nil(),
+        })
+    }
+
+    pub fn dissoc(
+        &self,
+        arguments: &[MalValue],
+        cmp: &mut MalComparator,
+    ) -> Result<MalSortedMap, MalError> {
+        let mut entries = self.entries.clone();
+
+        for arg in arguments {
+            if let Ok(index) = bisect(&entries, arg, |(key, _)| key, cmp)? {
+                entries.remove(index);
+            }
+        }
+
+        Ok(MalSortedMap {
+            entries,
+            comparator: self.comparator.clone(),
+            meta: MalValue::nil(),
+        })
+    }
+
+    pub fn get(&self, key: &MalValue, cmp: &mut MalComparator) -> MalResult {
+        Ok(match self.search(key, cmp)? {
+            Ok(index) => self.entries[index].1.clone(),
+            Err(_) => MalValue::nil(),
+        })
+    }
+
+    pub fn contains(&self, key: &MalValue, cmp: &mut MalComparator) -> Result<bool, MalError> {
+        Ok(self.search(key, cmp)?.is_ok())
+    }
+
+    /// Returns the index of `key`, or the index where it would be inserted.
+    pub fn search(
+        &self,
+        key: &MalValue,
+        cmp: &mut MalComparator,
+    ) -> Result<Result<usize, usize>, MalError> {
+        bisect(&self.entries, key, |(key, _)| key, cmp)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (&MalValue, &MalValue)> + ExactSizeIterator {
+        self.range(0, self.len())
+    }
+
+    /// The sorted counterpart of `MalMap::iter_owned`.
+    pub fn iter_owned(&self) -> impl Iterator<Item = (MalValue, MalValue)> {
+        self.entries.clone().into_iter()
+    }
+
+    /// Iterates over the entries with indices in `from..to`.
+    pub fn range(
+        &self,
+        from: usize,
+        to: usize,
+    ) -> impl DoubleEndedIterator<Item = (&MalValue, &MalValue)> + ExactSizeIterator {
+        self.entries
+            .iter()
+            .skip(from)
+            .take(to.saturating_sub(from))
+            .map(|(key, val)| (key, val))
+    }
+
+    fn to_hash_map(&self) -> HashMap<MalMapKey, MalValue> {
+        self.iter()
+            .map(|(key, val)| (MalMapKey::new(key), val.clone()))
+            .collect()
+    }
+}
+
+/// The set counterpart of `MalSortedMap`.
+#[derive(Debug)]
+pub struct MalSortedSet {
+    elements: Vector<MalValue>,
+    pub comparator: MalValue,
+    pub meta: MalValue,
+}
+
+impl MalSortedSet {
+    pub fn new(comparator: MalValue) -> MalSortedSet {
+        MalSortedSet {
+            elements: Vector::new(),
+            comparator,
+            meta: MalValue::nil(),
+        }
+    }
+
+    pub fn clone_with_meta(&self, meta: MalValue) -> MalSortedSet {
+        MalSortedSet {
+            elements: self.elements.clone(),
+            comparator: self.comparator.clone(),
+            meta,
+        }
+    }
+
+    pub fn conj<'a>(
+        &self,
+        values: impl IntoIterator<Item = &'a MalValue>,
+        cmp: &mut MalComparator,
+    ) -> Result<MalSortedSet, MalError> {
+        let mut elements = self.elements.clone();
+
+        for value in values {
+            if let Err(index) = bisect(&elements, value, |elem| elem, cmp)? {
+                elements.insert(index, value.clone());
+            }
+        }
+
+        Ok(MalSortedSet {
+            elements,
+            comparator: self.comparator.clone(),
+            meta: MalValue::nil(),
+        })
+    }
+
+    pub fn disj(
+        &self,
+        values: &[MalValue],
+        cmp: &mut MalComparator,
+    ) -> Result<MalSortedSet, MalError> {
+        let mut elements = self.elements.clone();
+
+        for value in values {
+            if let Ok(index) = bisect(&elements, value, |elem| elem, cmp)? {
+                elements.remove(index);
+            }
+        }
+
+        Ok(MalSortedSet {
+            elements,
+            comparator: self.comparator.clone(),
+            meta: MalValue::nil(),
+        })
+    }
+
+    pub fn contains(&self, value: &MalValue, cmp: &mut MalComparator) -> Result<bool, MalError> {
+        Ok(self.search(value, cmp)?.is_ok())
+    }
+
+    /// Returns the index of `value`, or the index where it would be inserted.
+    pub fn search(
+        &self,
+        value: &MalValue,
+        cmp: &mut MalComparator,
+    ) -> Result<Result<usize, usize>, MalError> {
+        bisect(&self.elements, value, |elem| elem, cmp)
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &MalValue> + ExactSizeIterator {
+        self.range(0, self.len())
+    }
+
+    /// The sorted counterpart of `MalSet::iter_owned`.
+    pub fn iter_owned(&self) -> impl Iterator<Item = MalValue> {
+        self.elements.clone().into_iter()
+    }
+
+    /// Iterates over the elements with indices in `from..to`.
+    pub fn range(
+        &self,
+        from: usize,
+        to: usize,
+    ) -> impl DoubleEndedIterator<Item = &MalValue> + ExactSizeIterator {
+        self.elements
+            .iter()
+            .skip(from)
+            .take(to.saturating_sub(from))
+    }
+
+    fn to_hash_set(&self) -> HashSet<MalMapKey> {
+        self.iter().map(MalMapKey::new).collect()
+    }
+}
+
+fn bisect<T: Clone>(
+    items: &Vector<T>,
+    key: &MalValue,
+    key_of: fn(&T) -> &MalValue,
+    cmp: &mut MalComparator,
+) -> Result<Result<usize, usize>, MalError> {
+    let (mut low, mut high) = (0, items.len());
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+
+        match cmp(key_of(&items[mid]), key)? {
+            Ordering::Less => low = mid + 1,
+            Ordering::Greater => high = mid,
+            Ordering::Equal => return Ok(Ok(mid)),
+        }
+    }
+
+    Ok(Err(low))
+}
+
+/// A compiled regex. `re-matches` needs the regex anchored at both ends,
+/// which is compiled the first time it is used and kept with the regex.
+#[derive(Debug)]
+pub struct MalRegex {
+    regex: regex::Regex,
+    anchored: OnceCell<regex::Regex>,
+}
+
+impl MalRegex {
+    pub fn anchored(&self) -> Result<&regex::Regex, regex::Error> {
+        if let Some(anchored) = self.anchored.get() {
+            return Ok(anchored);
+        }
+
+        let anchored = regex::Regex::new(&format!(r"\A(?:{})\z", self.regex.as_str()))?;
+
+        Ok(self.anchored.get_or_init(|| anchored))
+    }
+}
 
+impl From<regex::Regex> for MalRegex {
+    fn from(regex: regex::Regex) -> MalRegex {
+        MalRegex {
+            regex,
+            anchored: OnceCell::new(),
+        }
+    }
+}
+
+impl Deref for MalRegex {
+    type Target = regex::Regex;
+
+    fn deref(&self) -> &regex::Regex {
+        &self.regex
+    }
+}
+
+pub type RustFn = Rc<dyn Fn(&[MalValue], &mut Env) -> MalResult>;
+
+/// A builtin implemented in Rust. Two builtins are equal only if they share the
+/// same `func`, as copies made by `with-meta` do.
 pub struct RustFunction {
-    pub func: fn(&[MalValue], &mut Env) -> MalResult,
+    pub func: RustFn,
     pub env: Env,
     pub meta: MalValue,
 }
 
+impl RustFunction {
+    fn func_address(&self) -> usize {
+        Rc::as_ptr(&self.func) as *const () as usize
+    }
+}
+
 impl fmt::Debug for RustFunction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("RustFunction")
-            .field("func", &(self.func as usize))
+            .field("func", &self.func_address())
             .field("env", &self.env)
             .finish()
     }
@@ -454,7 +1236,7 @@ impl fmt::Debug for RustFunction {
 
 impl PartialEq for RustFunction {
     fn eq(&self, other: &RustFunction) -> bool {
-        (self.func as usize == other.func as usize) && (self.env == other.env)
+        self.func_address() == other.func_address()
     }
 }
 
@@ -477,6 +1259,34 @@ pub enum MalError {
     RustFunction(String),
     SpecialForm(String),
     Exception(MalValue),
+    Located(Rc<SourcePos>, Box<MalError>),
+    Context(String, Box<MalError>),
+}
+
+impl MalError {
+    /// Attaches the position of `mal_value` to the error, unless the error
+    /// already carries a (more specific) position. Tokenizer and parser errors
+    /// report the position of the offending text in their message.
+    pub fn at(self, mal_value: &MalValue) -> MalError {
+        match (self, &mal_value.pos) {
+            (err @ EmptyProgram, _) | (err @ Tokenizer(_), _) | (err @ Parser(_), _) => err,
+            (err @ Located(..), _) | (err @ Context(..), _) => err,
+            (err, Some(pos)) => Located(pos.clone(), Box::new(err)),
+            (err, None) => err,
+        }
+    }
+
+    pub fn with_context(self, context: String) -> MalError {
+        Context(context, Box::new(self))
+    }
+
+    /// Strips positions and context, returning the error that was originally raised.
+    pub fn root_cause(self) -> MalError {
+        match self {
+            Located(_, err) | Context(_, err) => err.root_cause(),
+            err => err,
+        }
+    }
 }
 
 impl fmt::Display for MalError {
@@ -494,21 +1304,55 @@ impl fmt::Display for MalError {
                 write!(f, "Error when evaluating special form: {}", message)
             }
             MalError::Exception(ref val) => write!(f, "Exception: {}", pr_str(val, true)),
+            Located(pos, err) => write!(f, "{}: {}", pos, err),
+            Context(context, err) => write!(f, "{} ({})", err, context),
         }
     }
 }
 
 pub type MalResult = Result<MalValue, MalError>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourcePos {
+    pub file: Option<Rc<str>>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for SourcePos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.file {
+            Some(ref file) => write!(f, "{}:{}:{}", file, self.line, self.column),
+            None => write!(f, "{}:{}", self.line, self.column),
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct MalToken {
     pub token_type: MalTokenType,
-    // Possible extra fields: line, column
+    pub pos: Option<Rc<SourcePos>>,
 }
 
 impl MalToken {
     pub fn new(token_type: MalTokenType) -> MalToken {
-        MalToken { token_type }
+        MalToken {
+            token_type,
+            pos: None,
+        }
+    }
+
+    pub fn with_pos(token_type: MalTokenType, pos: SourcePos) -> MalToken {
+        MalToken {
+            token_type,
+            pos: Some(Rc::new(pos)),
+        }
+    }
+}
+
+impl PartialEq for MalToken {
+    fn eq(&self, other: &MalToken) -> bool {
+        self.token_type == other.token_type
     }
 }
 
@@ -517,6 +1361,7 @@ pub enum MalTokenType {
     LParen,
     RParen,
     LCurly,
+    HashLCurly,
     RCurly,
     LBracket,
     RBracket,
@@ -529,8 +1374,254 @@ pub enum MalTokenType {
     Nil,
     True,
     False,
-    Number(f64),
+    Int(i64),
+    BigInt(num_bigint::BigInt),
+    Ratio(num_rational::BigRational),
+    Float(f64),
+    Char(char),
     Symbol(String),
     Str(String),
     Keyword(String),
+    Regex(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash_of(mal_value: &MalValue) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        mal_value.mal_type.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_rust_closures_are_equal_by_identity() {
+        let env = Env::new();
+        let func: RustFn = Rc::new(|_, _| Ok(MalValue::nil()));
+
+        let closure = MalValue::new_rust_closure(func.clone(), &env);
+        let same_func = MalValue::new_rust_closure(func, &env);
+        let other_func = MalValue::new_rust_closure(Rc::new(|_, _| Ok(MalValue::nil())), &env);
+
+        assert_eq!(closure, same_func);
+        assert_eq!(hash_of(&closure), hash_of(&same_func));
+        assert_ne!(closure, other_func);
+        assert_eq!(
+            closure.clone_with_meta(MalValue::new_boolean(true)),
+            Ok(closure)
+        );
+    }
+
+    fn map_of(arguments: &[MalValue]) -> MalValue {
+        MalValue::new_map(MalMap::from_arguments(arguments).unwrap())
+    }
+
+    #[test]
+    fn test_equal_values_hash_alike() {
+        let int = MalValue::new(MalValueType::Int(5));
+        let big_int = MalValue::new(MalValueType::BigInt(5.into()));
+        assert_eq!(int, big_int);
+        assert_eq!(hash_of(&int), hash_of(&big_int));
+
+        let list = MalValue::new_list(vec![int.clone(), MalValue::nil()]);
+        let vector = MalValue::new_vector(vec![big_int.clone(), MalValue::nil()]);
+        assert_eq!(list, vector);
+        assert_eq!(hash_of(&list), hash_of(&vector));
+
+        let zero = MalValue::new(MalValueType::Float(0.));
+        let neg_zero = MalValue::new(MalValueType::Float(-0.));
+        assert_eq!(zero, neg_zero);
+        assert_eq!(hash_of(&zero), hash_of(&neg_zero));
+
+        let key_a = MalValue::new(MalValueType::Keyword("a".to_string()));
+        let key_b = MalValue::new(MalValueType::Keyword("b".to_string()));
+        let map_1 = map_of(&[key_a.clone(), int.clone(), key_b.clone(), list.clone()]);
+        let map_2 = map_of(&[key_b, vector, key_a, big_int]);
+        assert_eq!(map_1, map_2);
+        assert_eq!(hash_of(&map_1), hash_of(&map_2));
+    }
+
+    #[test]
+    fn test_map_with_arbitrary_keys() {
+        let coords = MalValue::new_vector(vec![
+            MalValue::new(MalValueType::Int(1)),
+            MalValue::new(MalValueType::Int(2)),
+        ]);
+        let mal_map = MalMap::from_arguments(&[
+            coords,
+            MalValue::new(MalValueType::Str("found".to_string())),
+            MalValue::new(MalValueType::Int(3)),
+            MalValue::new(MalValueType::Str("int".to_string())),
+            MalValue::new(MalValueType::Float(3.)),
+            MalValue::new(MalValueType::Str("float".to_string())),
+        ])
+        .unwrap();
+
+        let coords_list = MalValue::new_list(vec![
+            MalValue::new(MalValueType::Int(1)),
+            MalValue::new(MalValueType::Int(2)),
+        ]);
+        assert_eq!(
+            mal_map.get(&coords_list),
+            MalValue::new(MalValueType::Str("found".to_string()))
+        );
+        assert_eq!(
+            mal_map.get(&MalValue::new(MalValueType::BigInt(3.into()))),
+            MalValue::new(MalValueType::Str("int".to_string()))
+        );
+        assert_eq!(
+            mal_map.get(&MalValue::new(MalValueType::Float(3.))),
+            MalValue::new(MalValueType::Str("float".to_string()))
+        );
+        assert!(!mal_map.contains(&MalValue::nil()));
+
+        let dissociated = mal_map.dissoc(std::slice::from_ref(&coords_list)).unwrap();
+        assert!(!dissociated.contains(&coords_list));
+        assert!(dissociated.contains(&MalValue::new(MalValueType::Int(3))));
+    }
+
+    #[test]
+    fn test_updates_do_not_modify_original() {
+        let key = MalValue::new(MalValueType::Keyword("a".to_string()));
+        let mal_map = MalMap::new();
+        let assoced = mal_map
+            .assoc(&[key.clone(), MalValue::new(MalValueType::Int(1))])
+            .unwrap();
+        assert!(!mal_map.contains(&key));
+        assert!(assoced.contains(&key));
+
+        let mal_set = MalSet::from_values(std::slice::from_ref(&key));
+        let disjed = mal_set.disj(std::slice::from_ref(&key));
+        assert!(mal_set.contains(&key));
+        assert!(disjed.is_empty());
+    }
+
+    #[test]
+    fn test_sorted_map_keeps_keys_in_order() {
+        let int = |n| MalValue::new(MalValueType::Int(n));
+        let mut cmp = |a: &MalValue, b: &MalValue| match (&*a.mal_type, &*b.mal_type) {
+            (MalValueType::Int(a), MalValueType::Int(b)) => Ok(a.cmp(b)),
+            _ => Err(MalError::RustFunction("not an int".to_string())),
+        };
+
+        let sorted_map = MalSortedMap::new(MalValue::nil())
+            .assoc(
+                &[int(3), int(30), int(1), int(10), int(2), int(20)],
+                &mut cmp,
+            )
+            .unwrap();
+        let keys: Vec<_> = sorted_map.iter().map(|(key, _)| key.clone()).collect();
+        assert_eq!(keys, vec![int(1), int(2), int(3)]);
+        assert_eq!(sorted_map.get(&int(2), &mut cmp), Ok(int(20)));
+        assert_eq!(sorted_map.search(&int(4), &mut cmp), Ok(Err(3)));
+
+        let updated = sorted_map.assoc(&[int(2), int(0)], &mut cmp).unwrap();
+        assert_eq!(updated.len(), 3);
+        assert_eq!(updated.get(&int(2), &mut cmp), Ok(int(0)));
+        assert_eq!(sorted_map.get(&int(2), &mut cmp), Ok(int(20)));
+
+        let hash_map = MalValue::new_map(
+            MalMap::from_arguments(&[int(1), int(10), int(2), int(20), int(3), int(30)]).unwrap(),
+        );
+        assert_eq!(MalValue::new_sorted_map(sorted_map), hash_map);
+
+        let keyword = MalValue::new(MalValueType::Keyword("a".to_string()));
+        assert!(MalSortedMap::new(MalValue::nil())
+            .assoc(&[int(1), int(10), keyword, int(20)], &mut cmp)
+            .is_err());
+    }
+
+    #[test]
+    fn test_collection_equality_ignores_meta() {
+        let int = |n| MalValue::new(MalValueType::Int(n));
+        let meta = map_of(&[int(0), int(0)]);
+        let mut cmp = |a: &MalValue, b: &MalValue| match (&*a.mal_type, &*b.mal_type) {
+            (MalValueType::Int(a), MalValueType::Int(b)) => Ok(a.cmp(b)),
+            _ => Err(MalError::RustFunction("not an int".to_string())),
+        };
+
+        let hash_map = map_of(&[int(1), int(10)]);
+        let sorted_map = MalValue::new_sorted_map(
+            MalSortedMap::new(MalValue::nil())
+                .assoc(&[int(1), int(10)], &mut cmp)
+                .unwrap(),
+        );
+        let hash_map_with_meta = hash_map.clone_with_meta(meta.clone()).unwrap();
+        let sorted_map_with_meta = sorted_map.clone_with_meta(meta.clone()).unwrap();
+        assert_eq!(hash_map_with_meta, hash_map);
+        assert_eq!(hash_map_with_meta, sorted_map);
+        assert_eq!(sorted_map, hash_map_with_meta);
+        assert_eq!(sorted_map_with_meta, hash_map);
+        assert_eq!(hash_of(&hash_map_with_meta), hash_of(&hash_map));
+
+        let hash_set = MalValue::new_set(MalSet::from_values(&[int(1)]));
+        let sorted_set = MalValue::new_sorted_set(
+            MalSortedSet::new(MalValue::nil())
+                .conj(&[int(1)], &mut cmp)
+                .unwrap(),
+        );
+        let hash_set_with_meta = hash_set.clone_with_meta(meta.clone()).unwrap();
+        assert_eq!(hash_set_with_meta, hash_set);
+        assert_eq!(hash_set_with_meta, sorted_set);
+        assert_eq!(sorted_set, hash_set_with_meta);
+        assert_eq!(hash_of(&hash_set_with_meta), hash_of(&hash_set));
+
+        let vector = MalValue::new_vector(vec![int(1)]);
+        assert_eq!(vector.clone_with_meta(meta).unwrap(), vector);
+    }
+
+    #[test]
+    fn test_anchored_regex_is_compiled_once() {
+        let re = MalRegex::from(regex::Regex::new("a|ab").unwrap());
+
+        let anchored = re.anchored().unwrap();
+        assert!(anchored.is_match("ab"));
+        assert!(!anchored.is_match("abc"));
+        assert!(std::ptr::eq(anchored, re.anchored().unwrap()));
+        assert_eq!(re.as_str(), "a|ab");
+    }
+
+    #[test]
+    fn test_map_iterates_in_insertion_order() {
+        let keyword = |name: &str| MalValue::new(MalValueType::Keyword(name.to_string()));
+        let int = |n| MalValue::new(MalValueType::Int(n));
+        let keys = |mal_map: &MalMap| -> Vec<MalValue> {
+            mal_map.iter().map(|(key, _)| key.clone()).collect()
+        };
+
+        let mal_map = MalMap::from_arguments(&[
+            keyword("c"),
+            int(1),
+            keyword("a"),
+            int(2),
+            keyword("b"),
+            int(3),
+        ])
+        .unwrap();
+        assert_eq!(
+            keys(&mal_map),
+            vec![keyword("c"), keyword("a"), keyword("b")]
+        );
+
+        let updated = mal_map
+            .assoc(&[keyword("a"), int(0), keyword("d"), int(4)])
+            .unwrap();
+        assert_eq!(
+            keys(&updated),
+            vec![keyword("c"), keyword("a"), keyword("b"), keyword("d")]
+        );
+        assert_eq!(updated.get(&keyword("a")), int(0));
+
+        let readded = updated
+            .dissoc(&[keyword("c")])
+            .unwrap()
+            .assoc(&[keyword("c"), int(1)])
+            .unwrap();
+        assert_eq!(
+            keys(&readded),
+            vec![keyword("a"), keyword("b"), keyword("d"), keyword("c")]
+        );
+        assert_eq!(readded, updated);
+    }
 }